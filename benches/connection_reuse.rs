@@ -0,0 +1,54 @@
+//! compares a pooled client that is reused for all requests with building a
+//! new client, and with it a new connection, for every request. Run with
+//! `cargo bench --bench connection_reuse`.
+
+#[path = "../tests/mock_server/mod.rs"]
+mod mock_server;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use rust_llm_utils::{OpenAiClient, PromptType};
+use tokio::runtime::Runtime;
+
+use mock_server::{MockResponse, MockServer};
+
+fn client_for(base_url: &str) -> OpenAiClient {
+    OpenAiClient::builder()
+        .base_url(base_url)
+        .allow_http(true)
+        .token("bench-token")
+        .build()
+        .unwrap()
+}
+
+fn connection_reuse(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let mock_server = runtime.block_on(MockServer::start(vec![MockResponse::chat_completion(
+        "pong",
+    )]));
+    let base_url = mock_server.base_url();
+    let prompt = PromptType::new_zero_shot_prompt("ping".to_string());
+
+    let mut group = c.benchmark_group("connection_reuse");
+
+    let pooled_client = client_for(&base_url);
+    group.bench_function("pooled_client", |b| {
+        b.iter(|| {
+            runtime
+                .block_on(pooled_client.perform_request(&prompt))
+                .unwrap()
+        })
+    });
+
+    group.bench_function("client_per_request", |b| {
+        b.iter(|| {
+            runtime
+                .block_on(client_for(&base_url).perform_request(&prompt))
+                .unwrap()
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, connection_reuse);
+criterion_main!(benches);
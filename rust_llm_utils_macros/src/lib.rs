@@ -0,0 +1,45 @@
+mod llm_prompt;
+mod llm_response;
+mod serde_attributes;
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput, LitStr};
+
+/// implements `rust_llm_utils::LlmResponse` for a struct or an enum, see the
+/// trait for the usage. The schema follows the serde attributes `rename`,
+/// `rename_all` and `skip`.
+#[proc_macro_derive(LlmResponse)]
+pub fn derive_llm_response(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    llm_response::expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// turns an async function signature into a prompt template. The placeholders
+/// of the template must be the parameters of the function, which is checked at
+/// compile time.
+///
+/// ```ignore
+/// #[llm_prompt("Fix this {lang} code: {code}")]
+/// async fn fix(lang: &str, code: &str) -> Result<Fixed, LlmError>;
+/// ```
+///
+/// generates
+/// * `FixPrompt`, with `FixPrompt::new(lang, code)` rendering the template and
+///   `query()` returning it. It implements `InnerPrompt`, where
+///   `new_from_prompt_template(input)` fills every placeholder with `input`.
+/// * `async fn fix(llm_client: &dyn LlmClient, lang: &str, code: &str)`,
+///   which sends the rendered template as a zero shot prompt to any backend.
+///   `Result<String, _>` returns the answer, `Result<ChatResponse, _>` the
+///   response and any other type goes through `chat_typed`.
+#[proc_macro_attribute]
+pub fn llm_prompt(attribute: TokenStream, item: TokenStream) -> TokenStream {
+    let template = parse_macro_input!(attribute as LitStr);
+    let function = parse_macro_input!(item as llm_prompt::PromptFunction);
+
+    llm_prompt::expand(&template, &function)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
@@ -0,0 +1,272 @@
+use crate::serde_attributes::apply_rename_all;
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::parse::{Parse, ParseStream};
+use syn::{
+    Attribute, Block, FnArg, GenericArgument, Ident, LitStr, Pat, PathArguments, ReturnType,
+    Signature, Token, Type, Visibility,
+};
+
+/// a function signature ending in `;`, with attributes and visibility
+pub struct PromptFunction {
+    attributes: Vec<Attribute>,
+    visibility: Visibility,
+    signature: Signature,
+    body_maybe: Option<Block>,
+}
+
+impl Parse for PromptFunction {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let attributes = input.call(Attribute::parse_outer)?;
+        let visibility = input.parse()?;
+        let signature = input.parse()?;
+        let body_maybe = if input.peek(Token![;]) {
+            input.parse::<Token![;]>()?;
+            None
+        } else {
+            Some(input.parse()?)
+        };
+
+        Ok(Self {
+            attributes,
+            visibility,
+            signature,
+            body_maybe,
+        })
+    }
+}
+
+/// what the generated function returns, decided by the `Ok` type
+enum Output {
+    /// the answer as text
+    Text,
+
+    /// the response as is
+    ChatResponse,
+
+    /// the answer deserialized into a `LlmResponse`
+    Typed,
+}
+
+pub fn expand(template: &LitStr, function: &PromptFunction) -> syn::Result<TokenStream> {
+    if let Some(body) = &function.body_maybe {
+        return Err(syn::Error::new_spanned(
+            body,
+            "the body of an llm_prompt function is generated, end the signature with `;`",
+        ));
+    }
+
+    let signature = &function.signature;
+    if signature.asyncness.is_none() {
+        return Err(syn::Error::new_spanned(
+            signature.fn_token,
+            "llm_prompt functions must be async",
+        ));
+    }
+
+    let mut parameters: Vec<(&Ident, &Type)> = vec![];
+    for input in &signature.inputs {
+        let FnArg::Typed(pat_type) = input else {
+            return Err(syn::Error::new_spanned(
+                input,
+                "llm_prompt functions cannot take self",
+            ));
+        };
+        let Pat::Ident(pat_ident) = pat_type.pat.as_ref() else {
+            return Err(syn::Error::new_spanned(
+                &pat_type.pat,
+                "llm_prompt parameters must be plain identifiers",
+            ));
+        };
+        parameters.push((&pat_ident.ident, pat_type.ty.as_ref()));
+    }
+
+    let placeholders = parse_placeholders(&template.value())
+        .map_err(|message| syn::Error::new_spanned(template, message))?;
+    for placeholder in &placeholders {
+        if !parameters.iter().any(|(ident, _)| *ident == placeholder) {
+            return Err(syn::Error::new_spanned(
+                template,
+                format!("placeholder {{{placeholder}}} is not a parameter of the function"),
+            ));
+        }
+    }
+    for (ident, _) in &parameters {
+        if !placeholders.iter().any(|placeholder| *ident == placeholder) {
+            return Err(syn::Error::new_spanned(
+                ident,
+                format!("parameter {ident} is not used in the template"),
+            ));
+        }
+    }
+
+    let output = output_of(&signature.output)?;
+
+    let visibility = &function.visibility;
+    let attributes = &function.attributes;
+    let function_ident = &signature.ident;
+    let prompt_ident = format_ident!(
+        "{}Prompt",
+        apply_rename_all(&function_ident.to_string(), Some("PascalCase"))?
+    );
+    let prompt_doc = format!("the prompt of [`{function_ident}`]: {}", template.value());
+    let (impl_generics, _, where_clause) = signature.generics.split_for_impl();
+    let return_type = &signature.output;
+
+    let parameter_idents: Vec<&Ident> = parameters.iter().map(|(ident, _)| *ident).collect();
+    let parameter_types: Vec<&Type> = parameters.iter().map(|(_, ty)| *ty).collect();
+
+    // the trait constructor takes a single input, which fills every
+    // placeholder
+    let inner_prompt_impl = quote! {
+        impl ::rust_llm_utils::InnerPrompt for #prompt_ident {
+            #[allow(unused_variables)]
+            fn new_from_prompt_template(input: ::std::string::String) -> Self {
+                Self {
+                    query: ::std::format!(#template, #(#parameter_idents = input),*),
+                }
+            }
+
+            fn query(&self) -> ::std::string::String {
+                self.query.clone()
+            }
+        }
+    };
+
+    let request = match output {
+        Output::Text => quote! {
+            llm_client
+                .chat(&prompt)
+                .await?
+                .answer
+                .ok_or(::rust_llm_utils::LlmError::EmptyChoices)
+        },
+        Output::ChatResponse => quote! {
+            llm_client.chat(&prompt).await
+        },
+        Output::Typed => quote! {
+            ::rust_llm_utils::chat_typed(llm_client, &prompt).await
+        },
+    };
+
+    Ok(quote! {
+        #[doc = #prompt_doc]
+        #visibility struct #prompt_ident {
+            query: ::std::string::String,
+        }
+
+        impl #prompt_ident {
+            #visibility fn new #impl_generics (#(#parameter_idents: #parameter_types),*) -> Self
+            #where_clause
+            {
+                Self {
+                    query: ::std::format!(#template, #(#parameter_idents = #parameter_idents),*),
+                }
+            }
+
+            #visibility fn query(&self) -> ::std::string::String {
+                self.query.clone()
+            }
+        }
+
+        #inner_prompt_impl
+
+        #(#attributes)*
+        #visibility async fn #function_ident #impl_generics (
+            llm_client: &dyn ::rust_llm_utils::LlmClient,
+            #(#parameter_idents: #parameter_types),*
+        ) #return_type
+        #where_clause
+        {
+            let prompt = ::rust_llm_utils::PromptType::new_zero_shot_prompt(
+                #prompt_ident::new(#(#parameter_idents),*).query(),
+            );
+            #request
+        }
+    })
+}
+
+/// the names in `{name}`, `{{` and `}}` are literal braces as in `format!`
+fn parse_placeholders(template: &str) -> Result<Vec<String>, String> {
+    let mut placeholders: Vec<String> = vec![];
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+            }
+            '}' => return Err("unmatched `}` in the template, use `}}`".to_string()),
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => name.push(c),
+                        None => return Err("unclosed `{` in the template, use `{{`".to_string()),
+                    }
+                }
+                let is_identifier = name
+                    .chars()
+                    .next()
+                    .is_some_and(|first| first.is_alphabetic() || first == '_')
+                    && name.chars().all(|c| c.is_alphanumeric() || c == '_');
+                if !is_identifier {
+                    return Err(format!(
+                        "placeholder {{{name}}} must be the name of a parameter"
+                    ));
+                }
+                if !placeholders.contains(&name) {
+                    placeholders.push(name);
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(placeholders)
+}
+
+/// the return type must be `Result<T, LlmError>`
+fn output_of(return_type: &ReturnType) -> syn::Result<Output> {
+    let error = || {
+        syn::Error::new(
+            match return_type {
+                ReturnType::Default => Span::call_site(),
+                ReturnType::Type(arrow, _) => arrow.spans[0],
+            },
+            "llm_prompt functions must return Result<T, LlmError>",
+        )
+    };
+
+    let ReturnType::Type(_, return_type) = return_type else {
+        return Err(error());
+    };
+    let Type::Path(type_path) = return_type.as_ref() else {
+        return Err(error());
+    };
+    let result_segment = type_path.path.segments.last().ok_or_else(error)?;
+    let PathArguments::AngleBracketed(arguments) = &result_segment.arguments else {
+        return Err(error());
+    };
+    if result_segment.ident != "Result" {
+        return Err(error());
+    }
+    let Some(GenericArgument::Type(Type::Path(ok_type))) = arguments.args.first() else {
+        return Err(error());
+    };
+
+    Ok(
+        match ok_type
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident.to_string())
+        {
+            Some(name) if name == "String" => Output::Text,
+            Some(name) if name == "ChatResponse" => Output::ChatResponse,
+            _ => Output::Typed,
+        },
+    )
+}
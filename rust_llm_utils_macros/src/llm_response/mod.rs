@@ -0,0 +1,166 @@
+use crate::serde_attributes::{apply_rename_all, doc_comment, SerdeAttributes};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DataEnum, DeriveInput, Fields, FieldsNamed, Variant};
+
+pub fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let ident = &input.ident;
+    let schema_name = ident.to_string();
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    let container_attributes = SerdeAttributes::parse(&input.attrs)?;
+    let container_doc = doc_comment(&input.attrs);
+
+    let schema = match &input.data {
+        Data::Struct(data_struct) => match &data_struct.fields {
+            Fields::Named(fields) => {
+                object_schema(fields, container_attributes.rename_all_maybe.as_deref())?
+            }
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                let field_type = &fields.unnamed[0].ty;
+                quote! { <#field_type as ::rust_llm_utils::LlmResponse>::json_schema() }
+            }
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    ident,
+                    "LlmResponse supports structs with named fields and newtype structs",
+                ))
+            }
+        },
+        Data::Enum(data_enum) => {
+            if container_attributes.enum_representation_maybe.is_some() {
+                return Err(syn::Error::new_spanned(
+                    ident,
+                    "LlmResponse supports externally tagged enums only",
+                ));
+            }
+            enum_schema(data_enum, container_attributes.rename_all_maybe.as_deref())?
+        }
+        Data::Union(_) => {
+            return Err(syn::Error::new_spanned(
+                ident,
+                "LlmResponse cannot be derived for unions",
+            ))
+        }
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::rust_llm_utils::LlmResponse for #ident #type_generics #where_clause {
+            fn json_schema() -> ::rust_llm_utils::__derive_support::JsonValue {
+                ::rust_llm_utils::__derive_support::with_description(#schema, #container_doc)
+            }
+
+            fn schema_name() -> ::std::string::String {
+                ::std::string::String::from(#schema_name)
+            }
+        }
+    })
+}
+
+/// an object with a property per field that is not skipped
+fn object_schema(fields: &FieldsNamed, rename_all_maybe: Option<&str>) -> syn::Result<TokenStream> {
+    let mut properties = vec![];
+    for field in &fields.named {
+        let field_attributes = SerdeAttributes::parse(&field.attrs)?;
+        if field_attributes.skip {
+            continue;
+        }
+
+        let field_ident = field.ident.as_ref().expect("named fields have an ident");
+        let field_name = match field_attributes.rename_maybe {
+            Some(rename) => rename,
+            None => apply_rename_all(
+                field_ident.to_string().trim_start_matches("r#"),
+                rename_all_maybe,
+            )?,
+        };
+        let field_type = &field.ty;
+        let field_doc = doc_comment(&field.attrs);
+
+        properties.push(quote! {
+            (
+                #field_name,
+                ::rust_llm_utils::__derive_support::with_description(
+                    <#field_type as ::rust_llm_utils::LlmResponse>::json_schema(),
+                    #field_doc,
+                ),
+            )
+        });
+    }
+
+    Ok(quote! {
+        ::rust_llm_utils::__derive_support::object(::std::vec![#(#properties),*])
+    })
+}
+
+/// a string enum when all variants are unit variants, otherwise any of the
+/// variants as serde tags them externally, `"Unit"` or `{"Variant": ...}`
+fn enum_schema(data_enum: &DataEnum, rename_all_maybe: Option<&str>) -> syn::Result<TokenStream> {
+    let mut variants: Vec<(&Variant, String)> = vec![];
+    for variant in &data_enum.variants {
+        let variant_attributes = SerdeAttributes::parse(&variant.attrs)?;
+        if variant_attributes.skip {
+            continue;
+        }
+        let variant_name = match variant_attributes.rename_maybe {
+            Some(rename) => rename,
+            None => apply_rename_all(&variant.ident.to_string(), rename_all_maybe)?,
+        };
+        variants.push((variant, variant_name));
+    }
+
+    let all_unit = variants
+        .iter()
+        .all(|(variant, _)| matches!(variant.fields, Fields::Unit));
+    if all_unit {
+        let names = variants.iter().map(|(_, variant_name)| variant_name);
+        return Ok(quote! {
+            ::rust_llm_utils::__derive_support::string_enum(&[#(#names),*])
+        });
+    }
+
+    let mut variant_schemas = vec![];
+    for (variant, variant_name) in variants {
+        let variant_doc = doc_comment(&variant.attrs);
+        let variant_attributes = SerdeAttributes::parse(&variant.attrs)?;
+
+        let variant_schema = match &variant.fields {
+            Fields::Unit => {
+                quote! { ::rust_llm_utils::__derive_support::string_enum(&[#variant_name]) }
+            }
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                let field_type = &fields.unnamed[0].ty;
+                quote! {
+                    ::rust_llm_utils::__derive_support::object(::std::vec![(
+                        #variant_name,
+                        <#field_type as ::rust_llm_utils::LlmResponse>::json_schema(),
+                    )])
+                }
+            }
+            Fields::Named(fields) => {
+                let payload_schema =
+                    object_schema(fields, variant_attributes.rename_all_maybe.as_deref())?;
+                quote! {
+                    ::rust_llm_utils::__derive_support::object(::std::vec![(
+                        #variant_name,
+                        #payload_schema,
+                    )])
+                }
+            }
+            Fields::Unnamed(_) => {
+                return Err(syn::Error::new_spanned(
+                    &variant.ident,
+                    "LlmResponse supports tuple variants with a single field only",
+                ))
+            }
+        };
+
+        variant_schemas.push(quote! {
+            ::rust_llm_utils::__derive_support::with_description(#variant_schema, #variant_doc)
+        });
+    }
+
+    Ok(quote! {
+        ::rust_llm_utils::__derive_support::any_of(::std::vec![#(#variant_schemas),*])
+    })
+}
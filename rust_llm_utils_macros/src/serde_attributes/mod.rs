@@ -0,0 +1,156 @@
+use syn::meta::ParseNestedMeta;
+use syn::{Attribute, Expr, Lit, LitStr, Meta, Token};
+
+/// the serde attributes that change the shape of the serialized form
+#[derive(Default)]
+pub struct SerdeAttributes {
+    pub rename_maybe: Option<String>,
+    pub rename_all_maybe: Option<String>,
+    pub skip: bool,
+
+    /// `tag`, `content` or `untagged`, enum representations other than the
+    /// default externally tagged one
+    pub enum_representation_maybe: Option<String>,
+}
+
+impl SerdeAttributes {
+    pub fn parse(attributes: &[Attribute]) -> syn::Result<Self> {
+        let mut serde_attributes = Self::default();
+
+        for attribute in attributes {
+            if !attribute.path().is_ident("serde") {
+                continue;
+            }
+            attribute.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    serde_attributes.rename_maybe = Some(deserialize_name(&meta)?);
+                } else if meta.path.is_ident("rename_all") {
+                    serde_attributes.rename_all_maybe = Some(deserialize_name(&meta)?);
+                } else if meta.path.is_ident("skip") || meta.path.is_ident("skip_deserializing") {
+                    serde_attributes.skip = true;
+                } else if ["tag", "content", "untagged"]
+                    .iter()
+                    .any(|name| meta.path.is_ident(name))
+                {
+                    serde_attributes.enum_representation_maybe =
+                        meta.path.get_ident().map(|ident| ident.to_string());
+                    skip_value(&meta)?;
+                } else {
+                    skip_value(&meta)?;
+                }
+                Ok(())
+            })?;
+        }
+
+        Ok(serde_attributes)
+    }
+}
+
+/// `rename = "name"` or `rename(deserialize = "name")`
+fn deserialize_name(meta: &ParseNestedMeta) -> syn::Result<String> {
+    if meta.input.peek(Token![=]) {
+        let name: LitStr = meta.value()?.parse()?;
+        return Ok(name.value());
+    }
+
+    let mut name_maybe = None;
+    meta.parse_nested_meta(|nested_meta| {
+        let name: LitStr = nested_meta.value()?.parse()?;
+        if nested_meta.path.is_ident("deserialize") {
+            name_maybe = Some(name.value());
+        }
+        Ok(())
+    })?;
+    name_maybe.ok_or_else(|| meta.error("expected a deserialize name"))
+}
+
+fn skip_value(meta: &ParseNestedMeta) -> syn::Result<()> {
+    if meta.input.peek(Token![=]) {
+        meta.value()?.parse::<Expr>()?;
+    } else if meta.input.peek(syn::token::Paren) {
+        meta.parse_nested_meta(|nested_meta| skip_value(&nested_meta))?;
+    }
+    Ok(())
+}
+
+/// the doc comment lines joined by spaces
+pub fn doc_comment(attributes: &[Attribute]) -> String {
+    attributes
+        .iter()
+        .filter(|attribute| attribute.path().is_ident("doc"))
+        .filter_map(|attribute| match &attribute.meta {
+            Meta::NameValue(name_value) => match &name_value.value {
+                Expr::Lit(expr_lit) => match &expr_lit.lit {
+                    Lit::Str(line) => Some(line.value().trim().to_string()),
+                    _ => None,
+                },
+                _ => None,
+            },
+            _ => None,
+        })
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// the name serde uses for a field or variant under `rename_all`
+pub fn apply_rename_all(name: &str, rename_all_maybe: Option<&str>) -> syn::Result<String> {
+    let Some(rename_all) = rename_all_maybe else {
+        return Ok(name.to_string());
+    };
+
+    let words = split_words(name);
+    let capitalize = |word: &str| {
+        let mut chars = word.chars();
+        chars
+            .next()
+            .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+            .unwrap_or_default()
+    };
+
+    let renamed = match rename_all {
+        "lowercase" => name.to_lowercase(),
+        "UPPERCASE" => name.to_uppercase(),
+        "snake_case" => words.join("_"),
+        "SCREAMING_SNAKE_CASE" => words.join("_").to_uppercase(),
+        "kebab-case" => words.join("-"),
+        "SCREAMING-KEBAB-CASE" => words.join("-").to_uppercase(),
+        "PascalCase" => words.iter().map(|word| capitalize(word)).collect(),
+        "camelCase" => words
+            .iter()
+            .enumerate()
+            .map(|(index, word)| {
+                if index == 0 {
+                    word.clone()
+                } else {
+                    capitalize(word)
+                }
+            })
+            .collect(),
+        other => {
+            return Err(syn::Error::new(
+                proc_macro2::Span::call_site(),
+                format!("unsupported rename_all rule {other}"),
+            ))
+        }
+    };
+    Ok(renamed)
+}
+
+/// lowercase words of a `snake_case` or `PascalCase` identifier
+fn split_words(name: &str) -> Vec<String> {
+    let mut words: Vec<String> = vec![];
+    for part in name.split('_').filter(|part| !part.is_empty()) {
+        let mut word = String::new();
+        for c in part.chars() {
+            if c.is_uppercase() && !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            word.extend(c.to_lowercase());
+        }
+        if !word.is_empty() {
+            words.push(word);
+        }
+    }
+    words
+}
@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+type SelectFn = dyn Fn(&[String]) -> Option<usize> + Send + Sync;
+
+/// picks the answer of a response with several choices, see
+/// [`crate::GenerationParams::n`]. Sampling a classification several times
+/// and taking the majority (self-consistency) reduces its variance.
+///
+/// # Usage
+/// ```no_run
+/// let open_ai_client = OpenAiClient::builder()
+///     .generation_params(GenerationParams::default().temperature(0.7).n(5))
+///     .answer_selector(AnswerSelector::MajorityVote)
+///     .build()?;
+///
+/// let response = open_ai_client.perform_request(&prompt).await?;
+/// let longest_answer = response.select(&AnswerSelector::Longest);
+/// ```
+#[derive(Clone, Default)]
+pub enum AnswerSelector {
+    /// the choice with index 0
+    #[default]
+    First,
+
+    /// the answer given most often after [`normalize_answer`], ties go to
+    /// the one given first
+    MajorityVote,
+
+    /// the answer with the most characters, ties go to the first one
+    Longest,
+
+    /// a closure returning the index of the selected answer, see
+    /// [`AnswerSelector::custom`]
+    Custom(Arc<SelectFn>),
+}
+
+impl fmt::Debug for AnswerSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::First => write!(f, "First"),
+            Self::MajorityVote => write!(f, "MajorityVote"),
+            Self::Longest => write!(f, "Longest"),
+            Self::Custom(_) => write!(f, "Custom"),
+        }
+    }
+}
+
+impl AnswerSelector {
+    pub fn custom<F>(select: F) -> Self
+    where
+        F: Fn(&[String]) -> Option<usize> + Send + Sync + 'static,
+    {
+        Self::Custom(Arc::new(select))
+    }
+
+    /// the selected answer, `None` if there are no answers or a custom
+    /// selector returned none or an index out of range
+    pub fn select<'s>(&self, answers: &'s [String]) -> Option<&'s str> {
+        let index = match self {
+            Self::First => 0,
+            Self::MajorityVote => majority_vote(answers)?,
+            Self::Longest => longest(answers)?,
+            Self::Custom(select) => select(answers)?,
+        };
+        answers.get(index).map(String::as_str)
+    }
+}
+
+/// the form answers are compared in by [`AnswerSelector::MajorityVote`]:
+/// lowercase, whitespace collapsed and surrounding punctuation removed, so
+/// that `"Yes."` and `" yes"` count as the same answer
+pub fn normalize_answer(answer: &str) -> String {
+    answer
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .trim_matches(|c: char| c.is_ascii_punctuation() || c.is_whitespace())
+        .to_lowercase()
+}
+
+fn majority_vote(answers: &[String]) -> Option<usize> {
+    // normalized answer to its first index and its votes
+    let mut votes: HashMap<String, (usize, usize)> = HashMap::new();
+    for (index, answer) in answers.iter().enumerate() {
+        votes
+            .entry(normalize_answer(answer))
+            .or_insert((index, 0))
+            .1 += 1;
+    }
+
+    votes
+        .into_values()
+        .max_by(|(index_a, votes_a), (index_b, votes_b)| {
+            votes_a.cmp(votes_b).then(index_b.cmp(index_a))
+        })
+        .map(|(index, _)| index)
+}
+
+fn longest(answers: &[String]) -> Option<usize> {
+    answers
+        .iter()
+        .enumerate()
+        .max_by(|(index_a, a), (index_b, b)| {
+            a.chars()
+                .count()
+                .cmp(&b.chars().count())
+                .then(index_b.cmp(index_a))
+        })
+        .map(|(index, _)| index)
+}
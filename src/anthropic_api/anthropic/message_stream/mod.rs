@@ -0,0 +1,309 @@
+use super::{
+    AnthropicContentBlock, AnthropicMessagesResponseBody, AnthropicStopReason, AnthropicUsage,
+};
+use crate::server_sent_events::{ServerSentEvent, ServerSentEventParser};
+use crate::{
+    ChatResponse, ContentFilterStage, CostLedger, LlmError, ModelRegistry, ProviderError,
+    TimeoutKind, Usage,
+};
+use futures::{Future, Stream};
+use hyper::Body;
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::time::{sleep, Sleep};
+
+/// an event of a streamed message as sent with `stream: true`
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AnthropicStreamEvent {
+    /// the message without content, carries the model and the input tokens
+    MessageStart {
+        message: AnthropicMessagesResponseBody,
+    },
+    ContentBlockStart {
+        index: usize,
+        content_block: AnthropicContentBlock,
+    },
+    ContentBlockDelta {
+        index: usize,
+        delta: AnthropicContentDelta,
+    },
+    ContentBlockStop {
+        index: usize,
+    },
+
+    /// the stop reason and the output tokens, sent once before the end
+    MessageDelta {
+        delta: AnthropicMessageDelta,
+        usage: AnthropicUsage,
+    },
+    MessageStop,
+    Ping,
+
+    /// event types the client does not know yet
+    #[serde(other)]
+    Other,
+}
+
+/// the part of a content block that arrived with an event
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AnthropicContentDelta {
+    TextDelta {
+        text: String,
+    },
+
+    /// a piece of the JSON input of a tool call
+    InputJsonDelta {
+        partial_json: String,
+    },
+    ThinkingDelta {
+        thinking: String,
+    },
+
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct AnthropicMessageDelta {
+    pub stop_reason: Option<AnthropicStopReason>,
+    pub stop_sequence: Option<String>,
+}
+
+/// the `error` event, which can arrive after a success status
+#[derive(Deserialize)]
+struct ErrorEvent {
+    error: ProviderError,
+}
+
+/// a streamed message, yields the events as they arrive and keeps the
+/// aggregated text around.
+///
+/// # Usage
+/// ```no_run
+/// use futures::StreamExt;
+///
+/// let mut message_stream = anthropic_client.perform_request_stream(&prompt).await?;
+/// while let Some(event) = message_stream.next().await {
+///     if let AnthropicStreamEvent::ContentBlockDelta {
+///         delta: AnthropicContentDelta::TextDelta { text },
+///         ..
+///     } = event?
+///     {
+///         print!("{text}");
+///     }
+/// }
+/// let chat_response = message_stream.response();
+/// ```
+pub struct AnthropicMessageStream {
+    body: Body,
+    parser: ServerSentEventParser,
+    pending_events: VecDeque<AnthropicStreamEvent>,
+    answer: String,
+    model_name: Option<String>,
+    usage: AnthropicUsage,
+    stop_reason: Option<AnthropicStopReason>,
+    done: bool,
+
+    /// ledger the usage is recorded to once the stream completed
+    cost_ledger_maybe: Option<CostLedger>,
+    model_registry: Arc<ModelRegistry>,
+
+    /// how long to wait for the next piece of the body
+    read_timeout: Option<Duration>,
+    read_deadline: Option<Pin<Box<Sleep>>>,
+}
+
+impl AnthropicMessageStream {
+    pub(crate) fn new(
+        body: Body,
+        read_timeout: Option<Duration>,
+        cost_ledger_maybe: Option<CostLedger>,
+        model_registry: Arc<ModelRegistry>,
+    ) -> Self {
+        Self {
+            body,
+            parser: ServerSentEventParser::default(),
+            pending_events: VecDeque::new(),
+            answer: String::new(),
+            model_name: None,
+            usage: AnthropicUsage::default(),
+            stop_reason: None,
+            done: false,
+            cost_ledger_maybe,
+            model_registry,
+            read_timeout,
+            read_deadline: None,
+        }
+    }
+
+    /// the text received so far, the output tokens are only known once the
+    /// stream completed
+    pub fn response(&self) -> ChatResponse {
+        let usage: Usage = self.usage.into();
+        ChatResponse {
+            answer: Some(self.answer.clone()),
+            answers: vec![self.answer.clone()],
+            usage: Some(usage),
+            cost_usd: self.cost_usd(&usage),
+            content_filter_results: vec![],
+            tokens: vec![],
+        }
+    }
+
+    pub fn stop_reason(&self) -> Option<AnthropicStopReason> {
+        self.stop_reason
+    }
+
+    /// reads the rest of the stream and returns the aggregated answer
+    pub async fn into_response(mut self) -> Result<ChatResponse, LlmError> {
+        use futures::StreamExt;
+
+        while let Some(event_result) = self.next().await {
+            event_result?;
+        }
+
+        Ok(self.response())
+    }
+
+    fn cost_usd(&self, usage: &Usage) -> Option<f64> {
+        self.model_registry
+            .cost_usd(self.model_name.as_deref()?, usage)
+    }
+
+    /// records the usage once `message_stop` arrived
+    fn complete(&mut self) {
+        self.done = true;
+
+        if let (Some(cost_ledger), Some(model_name)) =
+            (self.cost_ledger_maybe.take(), &self.model_name)
+        {
+            let usage: Usage = self.usage.into();
+            cost_ledger.record(model_name, None, &usage, self.cost_usd(&usage));
+        }
+    }
+
+    /// handles a completed event, returns true once `message_stop` arrived
+    fn process_event(&mut self, event: ServerSentEvent) -> Result<bool, LlmError> {
+        let data = event.data.trim();
+        if data.is_empty() {
+            return Ok(false);
+        }
+
+        if let Ok(error_event) = serde_json::from_str::<ErrorEvent>(data) {
+            return Err(LlmError::Provider {
+                status: 200,
+                error: error_event.error,
+            });
+        }
+
+        let stream_event: AnthropicStreamEvent = serde_json::from_str(data)
+            .map_err(|error| LlmError::Decode(format!("invalid stream event {data}: {error}")))?;
+
+        let mut terminated = false;
+        match &stream_event {
+            AnthropicStreamEvent::MessageStart { message } => {
+                self.model_name = Some(message.model.clone());
+                self.usage = message.usage;
+            }
+            AnthropicStreamEvent::ContentBlockDelta {
+                delta: AnthropicContentDelta::TextDelta { text },
+                ..
+            } => self.answer.push_str(text),
+            AnthropicStreamEvent::MessageDelta { delta, usage } => {
+                self.stop_reason = delta.stop_reason;
+                self.usage.output_tokens = usage.output_tokens;
+                if delta.stop_reason == Some(AnthropicStopReason::Refusal) {
+                    return Err(LlmError::ContentFiltered {
+                        stage: ContentFilterStage::Completion,
+                        results: vec![],
+                    });
+                }
+            }
+            AnthropicStreamEvent::MessageStop => terminated = true,
+            _ => {}
+        }
+
+        self.pending_events.push_back(stream_event);
+        Ok(terminated)
+    }
+}
+
+impl Stream for AnthropicMessageStream {
+    type Item = Result<AnthropicStreamEvent, LlmError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            if let Some(event) = self.pending_events.pop_front() {
+                return Poll::Ready(Some(Ok(event)));
+            }
+
+            if self.done {
+                return Poll::Ready(None);
+            }
+
+            let body_poll = Pin::new(&mut self.body).poll_next(cx);
+            if !body_poll.is_pending() {
+                self.read_deadline = None;
+            }
+
+            match body_poll {
+                Poll::Pending => {
+                    if let Some(read_timeout) = self.read_timeout {
+                        let read_deadline = self
+                            .read_deadline
+                            .get_or_insert_with(|| Box::pin(sleep(read_timeout)));
+                        if read_deadline.as_mut().poll(cx).is_ready() {
+                            self.done = true;
+                            return Poll::Ready(Some(Err(LlmError::Timeout(TimeoutKind::Read))));
+                        }
+                    }
+                    return Poll::Pending;
+                }
+                Poll::Ready(Some(Err(error))) => {
+                    self.done = true;
+                    return Poll::Ready(Some(Err(error.into())));
+                }
+                Poll::Ready(Some(Ok(bytes))) => {
+                    let events = self.parser.feed(&bytes);
+                    for event in events {
+                        match self.process_event(event) {
+                            Ok(false) => {}
+                            Ok(true) => {
+                                self.complete();
+                                break;
+                            }
+                            Err(error) => {
+                                self.done = true;
+                                return Poll::Ready(Some(Err(error)));
+                            }
+                        }
+                    }
+                }
+                Poll::Ready(None) => {
+                    self.done = true;
+                    let terminated = match self.parser.finish() {
+                        Some(event) => match self.process_event(event) {
+                            Ok(terminated) => terminated,
+                            Err(error) => return Poll::Ready(Some(Err(error))),
+                        },
+                        None => false,
+                    };
+
+                    if terminated {
+                        self.complete();
+                    } else {
+                        return Poll::Ready(Some(Err(LlmError::Transport(
+                            "stream ended before the message_stop event".to_string(),
+                        ))));
+                    }
+                }
+            }
+        }
+    }
+}
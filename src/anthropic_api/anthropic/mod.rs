@@ -0,0 +1,448 @@
+mod client_builder;
+mod message_stream;
+
+pub use client_builder::AnthropicClientBuilder;
+pub use message_stream::{
+    AnthropicContentDelta, AnthropicMessageDelta, AnthropicMessageStream, AnthropicStreamEvent,
+};
+
+use crate::http_client::HttpClient;
+use crate::rate_limiter::estimate_request_tokens;
+use crate::retry_policy::send_with_retries;
+use crate::timeouts::{read_body, with_timeout};
+use crate::usage::record_usage;
+use crate::{
+    ChatMessage, ChatResponse, ChatRole, ChatStream, ContentFilterStage, CostLedger,
+    CredentialProvider, GenerationLimits, GenerationParams, LlmClient, LlmError, ModelRegistry,
+    PromptTokensDetails, PromptType, RateLimiter, ResponseFormat, RetryPolicy, TimeoutKind,
+    Timeouts, Tokenizer, Usage,
+};
+use async_trait::async_trait;
+use futures::StreamExt;
+use hyper::{Body, Method, Request, Response};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+
+/// the upper bound for the generated tokens, which the Messages API requires,
+/// of models unknown to the registry
+const DEFAULT_MAX_TOKENS: u32 = 4_096;
+
+/// a block of the `content` of a message
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AnthropicContentBlock {
+    Text {
+        text: String,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        input: Value,
+    },
+    Thinking {
+        thinking: String,
+    },
+
+    /// block types the client does not know yet
+    #[serde(other)]
+    Other,
+}
+
+/// why the model stopped generating
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AnthropicStopReason {
+    EndTurn,
+    MaxTokens,
+    StopSequence,
+    ToolUse,
+
+    /// the model declined to answer, surfaced as
+    /// [`LlmError::ContentFiltered`]
+    Refusal,
+
+    #[serde(other)]
+    Other,
+}
+
+/// the `usage` block, the input tokens do not include the prompt cache
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub struct AnthropicUsage {
+    #[serde(default)]
+    pub input_tokens: u64,
+
+    #[serde(default)]
+    pub output_tokens: u64,
+
+    pub cache_creation_input_tokens: Option<u64>,
+    pub cache_read_input_tokens: Option<u64>,
+}
+
+impl From<AnthropicUsage> for Usage {
+    fn from(usage: AnthropicUsage) -> Self {
+        let cached_tokens = usage.cache_read_input_tokens.unwrap_or(0);
+        let prompt_tokens =
+            usage.input_tokens + usage.cache_creation_input_tokens.unwrap_or(0) + cached_tokens;
+
+        Usage {
+            prompt_tokens,
+            completion_tokens: usage.output_tokens,
+            total_tokens: prompt_tokens + usage.output_tokens,
+            prompt_tokens_details: usage
+                .cache_read_input_tokens
+                .map(|cached_tokens| PromptTokensDetails { cached_tokens }),
+        }
+    }
+}
+
+/// the answer of `/v1/messages`
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct AnthropicMessagesResponseBody {
+    pub id: String,
+    pub model: String,
+    pub role: String,
+    pub content: Vec<AnthropicContentBlock>,
+    pub stop_reason: Option<AnthropicStopReason>,
+    pub stop_sequence: Option<String>,
+    pub usage: AnthropicUsage,
+}
+
+impl AnthropicMessagesResponseBody {
+    /// the text blocks joined, without tool calls and thinking
+    pub fn text(&self) -> String {
+        self.content
+            .iter()
+            .filter_map(|block| match block {
+                AnthropicContentBlock::Text { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct MessagesRequest {
+    model: String,
+    max_tokens: u32,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<String>,
+
+    messages: Vec<AnthropicMessage>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop_sequences: Option<Vec<String>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metadata: Option<Value>,
+
+    stream: bool,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+struct AnthropicMessage {
+    role: &'static str,
+    content: String,
+}
+
+/// runs prompts on Claude models through Anthropic's Messages API. Cheap to
+/// clone, clones share the connection pool and the rate limiter.
+///
+/// # Usage
+/// ```no_run
+/// let anthropic_client = AnthropicClient::builder()
+///     .model("claude-3-5-sonnet-latest")
+///     .build()?;
+///
+/// let chat_response = anthropic_client.perform_request(&prompt).await?;
+/// ```
+#[derive(Clone)]
+pub struct AnthropicClient {
+    model: String,
+    credential_provider: Arc<dyn CredentialProvider>,
+    base_url: String,
+    anthropic_version: String,
+    retry_policy: RetryPolicy,
+    timeouts: Timeouts,
+    http_client: HttpClient,
+    rate_limiter_maybe: Option<RateLimiter>,
+    generation_params: GenerationParams,
+    tokenizer: Tokenizer,
+    cost_ledger_maybe: Option<CostLedger>,
+    model_registry: Arc<ModelRegistry>,
+}
+
+impl AnthropicClient {
+    pub fn builder() -> AnthropicClientBuilder {
+        AnthropicClientBuilder::default()
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    pub async fn perform_request(&self, prompt: &PromptType) -> Result<ChatResponse, LlmError> {
+        self.perform_request_with_params(prompt, &GenerationParams::default())
+            .await
+    }
+
+    /// same as [`Self::perform_request`] with the parameters set in `params`
+    /// overriding the client's [`GenerationParams`]. The Messages API has no
+    /// JSON mode, a [`ResponseFormat`] is asked for in the system prompt and
+    /// an answer not matching it is an error.
+    pub async fn perform_request_with_params(
+        &self,
+        prompt: &PromptType,
+        params: &GenerationParams,
+    ) -> Result<ChatResponse, LlmError> {
+        let response_body = self.perform_messages_request(prompt, params).await?;
+
+        let answer = response_body.text();
+        if let Some(response_format) = prompt.response_format() {
+            response_format.validate_answer(&answer)?;
+        }
+
+        let usage: Usage = response_body.usage.into();
+        Ok(ChatResponse {
+            answer: Some(answer.clone()),
+            answers: vec![answer],
+            cost_usd: record_usage(
+                &self.model_registry,
+                self.cost_ledger_maybe.as_ref(),
+                None,
+                &response_body.model,
+                Some(&usage),
+            ),
+            usage: Some(usage),
+            content_filter_results: vec![],
+            tokens: vec![],
+        })
+    }
+
+    /// the response with its content blocks and stop reason, a refusal is
+    /// [`LlmError::ContentFiltered`]
+    pub async fn perform_messages_request(
+        &self,
+        prompt: &PromptType,
+        params: &GenerationParams,
+    ) -> Result<AnthropicMessagesResponseBody, LlmError> {
+        let body = self.build_body(prompt, params, false)?;
+        let max_tokens = body.max_tokens;
+        let body = serialize_body(&body)?;
+        let estimated_tokens = estimate_request_tokens(&self.tokenizer, &body, Some(max_tokens));
+
+        let read_timeout = self.timeouts.read;
+        let response_body = send_with_retries(
+            &self.retry_policy,
+            &self.timeouts,
+            self.rate_limiter_maybe.as_ref(),
+            estimated_tokens,
+            || self.send(body.clone()),
+            |mut resp| async move {
+                let body = read_body(resp.body_mut(), read_timeout).await?;
+                Ok(serde_json::from_slice::<AnthropicMessagesResponseBody>(
+                    &body,
+                )?)
+            },
+        )
+        .await?;
+
+        if response_body.stop_reason == Some(AnthropicStopReason::Refusal) {
+            return Err(LlmError::ContentFiltered {
+                stage: ContentFilterStage::Completion,
+                results: vec![],
+            });
+        }
+
+        Ok(response_body)
+    }
+
+    /// same as [`Self::perform_request`] but returns the events as they
+    /// arrive, see [`AnthropicMessageStream`]. Failed attempts to open the
+    /// stream are retried, a stream that broke off is not.
+    pub async fn perform_request_stream(
+        &self,
+        prompt: &PromptType,
+    ) -> Result<AnthropicMessageStream, LlmError> {
+        let body = self.build_body(prompt, &GenerationParams::default(), true)?;
+        let max_tokens = body.max_tokens;
+        let body = serialize_body(&body)?;
+        let estimated_tokens = estimate_request_tokens(&self.tokenizer, &body, Some(max_tokens));
+
+        // the overall timeout only covers opening the stream
+        let resp = send_with_retries(
+            &self.retry_policy,
+            &self.timeouts,
+            self.rate_limiter_maybe.as_ref(),
+            estimated_tokens,
+            || self.send(body.clone()),
+            |resp| async move { Ok(resp) },
+        )
+        .await?;
+
+        Ok(AnthropicMessageStream::new(
+            resp.into_body(),
+            self.timeouts.read,
+            self.cost_ledger_maybe.clone(),
+            self.model_registry.clone(),
+        ))
+    }
+
+    fn build_body(
+        &self,
+        prompt: &PromptType,
+        params: &GenerationParams,
+        stream: bool,
+    ) -> Result<MessagesRequest, LlmError> {
+        let params = self.generation_params.merge(params);
+        params.validate(
+            &GenerationLimits::ANTHROPIC,
+            self.model_registry.lookup(&self.model),
+        )?;
+
+        let unsupported = [
+            ("n", params.n.is_some_and(|n| n > 1)),
+            ("seed", params.seed.is_some()),
+            ("presence_penalty", params.presence_penalty.is_some()),
+            ("frequency_penalty", params.frequency_penalty.is_some()),
+            ("logit_bias", params.logit_bias.is_some()),
+        ];
+        if let Some((name, _)) = unsupported.iter().find(|(_, is_set)| *is_set) {
+            return Err(LlmError::InvalidRequest(format!(
+                "the Messages API does not support {name}"
+            )));
+        }
+
+        let messages = prompt.messages();
+        let mut system_parts: Vec<String> = messages
+            .iter()
+            .filter(|message| matches!(message.role, ChatRole::System | ChatRole::Developer))
+            .filter_map(|message| message.content.clone())
+            .collect();
+        if let Some(response_format) = prompt.response_format() {
+            system_parts.push(response_format_instruction(response_format));
+        }
+
+        let max_tokens = params.max_tokens.unwrap_or_else(|| {
+            self.model_registry
+                .lookup(&self.model)
+                .map_or(DEFAULT_MAX_TOKENS, |descriptor| {
+                    descriptor.max_output_tokens as u32
+                })
+        });
+
+        Ok(MessagesRequest {
+            model: self.model.clone(),
+            max_tokens,
+            system: (!system_parts.is_empty()).then(|| system_parts.join("\n\n")),
+            messages: anthropic_messages(&messages),
+            temperature: params.temperature,
+            top_p: params.top_p,
+            stop_sequences: params.stop,
+            metadata: params
+                .user
+                .map(|user| serde_json::json!({ "user_id": user })),
+            stream,
+        })
+    }
+
+    /// a single attempt of posting the body to `/messages`, returns once the
+    /// response headers arrived
+    async fn send(&self, body: String) -> Result<Response<Body>, LlmError> {
+        let credentials = self.credential_provider.credentials()?;
+
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri(format!("{}/messages", self.base_url))
+            .header("x-api-key", &credentials.api_key)
+            .header("anthropic-version", &self.anthropic_version)
+            .header("content-type", "application/json")
+            .body(Body::from(body))?;
+
+        with_timeout(self.timeouts.read, TimeoutKind::Read, async {
+            Ok(self.http_client.request(request).await?)
+        })
+        .await
+    }
+}
+
+/// the Messages API only knows user and assistant turns, which have to
+/// alternate. Tool outputs are sent as user text and consecutive messages of
+/// the same role are joined.
+fn anthropic_messages(messages: &[ChatMessage]) -> Vec<AnthropicMessage> {
+    let mut anthropic_messages: Vec<AnthropicMessage> = vec![];
+
+    for message in messages {
+        let role = match message.role {
+            ChatRole::System | ChatRole::Developer => continue,
+            ChatRole::User | ChatRole::Tool => "user",
+            ChatRole::Assistant => "assistant",
+        };
+        let content = message.content.clone().unwrap_or_default();
+
+        match anthropic_messages.last_mut() {
+            Some(last) if last.role == role => {
+                last.content.push_str("\n\n");
+                last.content.push_str(&content);
+            }
+            _ => anthropic_messages.push(AnthropicMessage { role, content }),
+        }
+    }
+
+    anthropic_messages
+}
+
+fn response_format_instruction(response_format: &ResponseFormat) -> String {
+    match response_format {
+        ResponseFormat::JsonObject => {
+            "Answer with a single JSON object and nothing else.".to_string()
+        }
+        ResponseFormat::JsonSchema(json_schema) => format!(
+            "Answer with a single JSON object matching this JSON schema and nothing else:\n{}",
+            json_schema.schema
+        ),
+    }
+}
+
+fn serialize_body(body: &MessagesRequest) -> Result<String, LlmError> {
+    serde_json::to_string(body).map_err(|error| LlmError::InvalidRequest(error.to_string()))
+}
+
+#[async_trait]
+impl LlmClient for AnthropicClient {
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+
+    async fn chat(&self, prompt: &PromptType) -> Result<ChatResponse, LlmError> {
+        self.perform_request(prompt).await
+    }
+
+    async fn chat_stream(&self, prompt: &PromptType) -> Result<ChatStream, LlmError> {
+        let message_stream = self.perform_request_stream(prompt).await?;
+
+        let text_stream = message_stream.filter_map(|event_result| async move {
+            match event_result {
+                Ok(AnthropicStreamEvent::ContentBlockDelta {
+                    delta: AnthropicContentDelta::TextDelta { text },
+                    ..
+                }) if !text.is_empty() => Some(Ok(text)),
+                Ok(_) => None,
+                Err(error) => Some(Err(error)),
+            }
+        });
+        Ok(text_stream.boxed())
+    }
+
+    fn count_tokens(&self, prompt: &PromptType) -> usize {
+        let messages = prompt.messages();
+        self.tokenizer.count_chat_tokens(&messages)
+    }
+}
@@ -0,0 +1,7 @@
+mod anthropic;
+
+pub use anthropic::{
+    AnthropicClient, AnthropicClientBuilder, AnthropicContentBlock, AnthropicContentDelta,
+    AnthropicMessageDelta, AnthropicMessageStream, AnthropicMessagesResponseBody,
+    AnthropicStopReason, AnthropicStreamEvent, AnthropicUsage,
+};
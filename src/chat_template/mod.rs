@@ -0,0 +1,153 @@
+use crate::{ChatMessage, ChatRole};
+
+/// how the messages of a chat are rendered into the single prompt of a raw
+/// completion endpoint, which has to match the format the model was trained
+/// on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChatTemplate {
+    /// `<s>[INST] <<SYS>>\n{system}\n<</SYS>>\n\n{user} [/INST] {assistant} </s>`
+    #[default]
+    Llama2,
+
+    /// `<|im_start|>{role}\n{content}<|im_end|>\n`, e.g. Qwen, Yi or
+    /// OpenHermes
+    ChatMl,
+
+    /// `<s>[INST] {user} [/INST]{assistant}</s>`, Mistral and Mixtral
+    /// instruct. There is no system role, the system prompt is put in front
+    /// of the first user message.
+    MistralInstruct,
+}
+
+/// a user turn and the answer to it, if already given
+struct Turn {
+    user: String,
+    assistant_maybe: Option<String>,
+}
+
+impl ChatTemplate {
+    /// the prompt ending where the model continues with its answer
+    pub fn render(&self, messages: &[ChatMessage]) -> String {
+        match self {
+            Self::Llama2 => render_llama2(messages),
+            Self::ChatMl => render_chat_ml(messages),
+            Self::MistralInstruct => render_mistral_instruct(messages),
+        }
+    }
+
+    /// the sequences that end an answer besides the end of sequence token,
+    /// which servers should stop at
+    pub fn stop_sequences(&self) -> &'static [&'static str] {
+        match self {
+            Self::ChatMl => &["<|im_end|>"],
+            Self::Llama2 | Self::MistralInstruct => &[],
+        }
+    }
+}
+
+fn content(message: &ChatMessage) -> String {
+    message.content.as_deref().unwrap_or("").trim().to_string()
+}
+
+/// the system messages joined and the user turns with their answers. Tool
+/// outputs count as user messages, consecutive user messages are joined.
+fn system_prompt_and_turns(messages: &[ChatMessage]) -> (String, Vec<Turn>) {
+    let system_prompt = messages
+        .iter()
+        .filter(|message| matches!(message.role, ChatRole::System | ChatRole::Developer))
+        .map(content)
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    let mut turns: Vec<Turn> = vec![];
+    for message in messages {
+        match message.role {
+            ChatRole::System | ChatRole::Developer => {}
+            ChatRole::User | ChatRole::Tool => match turns.last_mut() {
+                Some(turn) if turn.assistant_maybe.is_none() => {
+                    turn.user.push_str("\n\n");
+                    turn.user.push_str(&content(message));
+                }
+                _ => turns.push(Turn {
+                    user: content(message),
+                    assistant_maybe: None,
+                }),
+            },
+            ChatRole::Assistant => match turns.last_mut() {
+                Some(turn) if turn.assistant_maybe.is_none() => {
+                    turn.assistant_maybe = Some(content(message));
+                }
+                _ => turns.push(Turn {
+                    user: String::new(),
+                    assistant_maybe: Some(content(message)),
+                }),
+            },
+        }
+    }
+    if turns.is_empty() {
+        turns.push(Turn {
+            user: String::new(),
+            assistant_maybe: None,
+        });
+    }
+
+    (system_prompt, turns)
+}
+
+/// Llama 2 has no roles beyond system, user and assistant, the system
+/// messages go into the first user turn
+fn render_llama2(messages: &[ChatMessage]) -> String {
+    let (system_prompt, turns) = system_prompt_and_turns(messages);
+
+    let mut prompt = String::new();
+    for (index, turn) in turns.iter().enumerate() {
+        prompt.push_str("<s>[INST] ");
+        if index == 0 && !system_prompt.is_empty() {
+            prompt.push_str(&format!("<<SYS>>\n{system_prompt}\n<</SYS>>\n\n"));
+        }
+        prompt.push_str(&format!("{} [/INST]", turn.user));
+        if let Some(assistant) = &turn.assistant_maybe {
+            prompt.push_str(&format!(" {assistant} </s>"));
+        }
+    }
+    prompt
+}
+
+/// every message in its own block, developer messages are sent as system
+/// messages
+fn render_chat_ml(messages: &[ChatMessage]) -> String {
+    let mut prompt = String::new();
+    for message in messages {
+        let role = match message.role {
+            ChatRole::Developer => ChatRole::System,
+            role => role,
+        };
+        prompt.push_str(&format!(
+            "<|im_start|>{role}\n{}<|im_end|>\n",
+            content(message)
+        ));
+    }
+    prompt.push_str("<|im_start|>assistant\n");
+    prompt
+}
+
+fn render_mistral_instruct(messages: &[ChatMessage]) -> String {
+    let (system_prompt, mut turns) = system_prompt_and_turns(messages);
+    if !system_prompt.is_empty() {
+        let first_turn = &mut turns[0];
+        first_turn.user = if first_turn.user.is_empty() {
+            system_prompt
+        } else {
+            format!("{system_prompt}\n\n{}", first_turn.user)
+        };
+    }
+
+    let mut prompt = String::from("<s>");
+    for turn in &turns {
+        prompt.push_str(&format!("[INST] {} [/INST]", turn.user));
+        if let Some(assistant) = &turn.assistant_maybe {
+            prompt.push_str(&format!("{assistant}</s>"));
+        }
+    }
+    prompt
+}
@@ -0,0 +1,108 @@
+use crate::LlmError;
+use serde_json::Value;
+use std::fmt::{Display, Formatter};
+
+/// which side of the exchange a content filter flagged
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentFilterStage {
+    /// the request was rejected before generating
+    Prompt,
+
+    /// the generated answer was withheld
+    Completion,
+}
+
+impl Display for ContentFilterStage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Prompt => write!(f, "prompt"),
+            Self::Completion => write!(f, "completion"),
+        }
+    }
+}
+
+/// a category the content filter flagged, e.g. `hate` filtered with severity
+/// `high` or `jailbreak` detected
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContentFilterResult {
+    pub category: String,
+
+    /// whether the category blocked the content, `false` for categories
+    /// that are only detected and annotated
+    pub filtered: bool,
+
+    /// e.g. `safe`, `low`, `medium` or `high` for the harm categories
+    pub severity_maybe: Option<String>,
+}
+
+impl Display for ContentFilterResult {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.category)?;
+        if let Some(severity) = &self.severity_maybe {
+            write!(f, " ({severity})")?;
+        }
+        Ok(())
+    }
+}
+
+/// the flagged categories of a `content_filter_results` object such as
+///
+/// ```json
+/// {"hate": {"filtered": true, "severity": "high"}, "jailbreak": {"filtered": false, "detected": true}}
+/// ```
+///
+/// ordered by category
+pub(crate) fn flagged_categories(content_filter_results: &Value) -> Vec<ContentFilterResult> {
+    let Some(categories) = content_filter_results.as_object() else {
+        return vec![];
+    };
+
+    categories
+        .iter()
+        .filter_map(|(category, result)| {
+            let filtered = result["filtered"].as_bool().unwrap_or(false);
+            let detected = result["detected"].as_bool().unwrap_or(false);
+            (filtered || detected).then(|| ContentFilterResult {
+                category: category.clone(),
+                filtered,
+                severity_maybe: result["severity"].as_str().map(str::to_string),
+            })
+        })
+        .collect()
+}
+
+/// a returned choice Azure may have annotated or withheld
+pub(crate) trait AnnotatedChoice {
+    /// `content_filter` when the answer was withheld
+    fn finish_reason(&self) -> Option<&str>;
+
+    /// the `content_filter_results` of the choice
+    fn content_filter_results(&self) -> Option<&Value>;
+}
+
+/// the choices that were not withheld with the categories flagged in them.
+/// It is an error only if all choices were withheld.
+pub(crate) fn unfiltered_choices<C: AnnotatedChoice>(
+    choices: Vec<C>,
+) -> Result<(Vec<C>, Vec<ContentFilterResult>), LlmError> {
+    let (filtered_choices, choices): (Vec<C>, Vec<C>) = choices
+        .into_iter()
+        .partition(|choice| choice.finish_reason() == Some("content_filter"));
+    if choices.is_empty() {
+        return Err(LlmError::ContentFiltered {
+            stage: ContentFilterStage::Completion,
+            results: filtered_choices
+                .first()
+                .and_then(AnnotatedChoice::content_filter_results)
+                .map(flagged_categories)
+                .unwrap_or_default(),
+        });
+    }
+
+    let content_filter_results = choices
+        .iter()
+        .filter_map(AnnotatedChoice::content_filter_results)
+        .flat_map(flagged_categories)
+        .collect();
+    Ok((choices, content_filter_results))
+}
@@ -0,0 +1,179 @@
+use crate::{ResponseFormat, Tokenizer, ToolCall};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+
+/// who a message of a chat is from
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum ChatRole {
+    /// instructions for the whole conversation
+    System,
+
+    /// instructions for the whole conversation, replaces `system` for
+    /// reasoning models
+    Developer,
+    User,
+    Assistant,
+
+    /// the output of a tool call
+    Tool,
+}
+
+impl ChatRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::System => "system",
+            Self::Developer => "developer",
+            Self::User => "user",
+            Self::Assistant => "assistant",
+            Self::Tool => "tool",
+        }
+    }
+}
+
+impl Display for ChatRole {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// a message of a chat, as sent to and received from the chat completions
+/// endpoint
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct ChatMessage {
+    pub role: ChatRole,
+
+    /// `None` for assistant messages that only call tools
+    #[serde(default)]
+    pub content: Option<String>,
+
+    /// the tools the assistant asks to be called
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_calls: Option<Vec<ToolCall>>,
+
+    /// the call a `tool` message answers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+}
+
+impl ChatMessage {
+    pub fn new(role: ChatRole, content: impl Into<String>) -> Self {
+        Self {
+            role,
+            content: Some(content.into()),
+            tool_calls: None,
+            tool_call_id: None,
+        }
+    }
+
+    pub fn system(content: impl Into<String>) -> Self {
+        Self::new(ChatRole::System, content)
+    }
+
+    pub fn developer(content: impl Into<String>) -> Self {
+        Self::new(ChatRole::Developer, content)
+    }
+
+    pub fn user(content: impl Into<String>) -> Self {
+        Self::new(ChatRole::User, content)
+    }
+
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self::new(ChatRole::Assistant, content)
+    }
+
+    /// the output of a tool handler for the call with the id
+    pub fn tool(tool_call_id: impl Into<String>, content: impl Into<String>) -> Self {
+        Self {
+            tool_call_id: Some(tool_call_id.into()),
+            ..Self::new(ChatRole::Tool, content)
+        }
+    }
+
+    /// tokens of the message in a chat request, including the chat format
+    /// overhead
+    pub fn count_tokens(&self, tokenizer: &Tokenizer) -> usize {
+        let tool_call_tokens: usize = self
+            .tool_calls
+            .iter()
+            .flatten()
+            .map(|tool_call| {
+                tokenizer.count_tokens(&tool_call.function.name)
+                    + tokenizer.count_tokens(&tool_call.function.arguments)
+            })
+            .sum();
+
+        tokenizer.count_message_tokens(self.role.as_str(), self.content.as_deref().unwrap_or(""))
+            + tool_call_tokens
+    }
+}
+
+/// the messages of a chat so far, sent as a whole with every request. The
+/// client appends the answer to it, see
+/// [`crate::OpenAiClient::perform_conversation_request`].
+///
+/// # Usage
+/// ```no_run
+/// let mut conversation = Conversation::with_system_prompt("You answer in one sentence.");
+///
+/// conversation.push_user("What is Rust?");
+/// open_ai_client.perform_conversation_request(&mut conversation).await?;
+///
+/// conversation.push_user("And who maintains it?");
+/// let response = open_ai_client.perform_conversation_request(&mut conversation).await?;
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Conversation {
+    messages: Vec<ChatMessage>,
+    response_format_maybe: Option<ResponseFormat>,
+}
+
+impl Conversation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// a conversation starting with the system message
+    pub fn with_system_prompt(system_prompt: impl Into<String>) -> Self {
+        let mut conversation = Self::new();
+        conversation.push(ChatMessage::system(system_prompt));
+        conversation
+    }
+
+    pub fn push(&mut self, message: ChatMessage) {
+        self.messages.push(message);
+    }
+
+    pub fn push_user(&mut self, content: impl Into<String>) {
+        self.push(ChatMessage::user(content));
+    }
+
+    pub fn messages(&self) -> &[ChatMessage] {
+        &self.messages
+    }
+
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    /// the content of the latest assistant message
+    pub fn last_answer(&self) -> Option<&str> {
+        self.messages
+            .iter()
+            .rev()
+            .find(|message| message.role == ChatRole::Assistant)
+            .and_then(|message| message.content.as_deref())
+    }
+
+    pub fn response_format(&self) -> Option<&ResponseFormat> {
+        self.response_format_maybe.as_ref()
+    }
+
+    pub fn set_response_format(&mut self, response_format: ResponseFormat) {
+        self.response_format_maybe = Some(response_format);
+    }
+}
@@ -0,0 +1,248 @@
+use crate::LlmError;
+use std::fmt::{Debug, Formatter};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Once};
+
+/// variable of the API key when not configured otherwise
+const DEFAULT_API_KEY_VAR: &str = "OPEN_AI_TOKEN";
+const DEFAULT_ORGANIZATION_VAR: &str = "OPEN_AI_ORGANIZATION";
+const DEFAULT_PROJECT_VAR: &str = "OPEN_AI_PROJECT";
+
+/// where the client gets the API key from, asked before every attempt of a
+/// request so that keys can change while the client is running
+///
+/// # Usage
+/// ```no_run
+/// let open_ai_client = OpenAiClient::builder()
+///     .credential_provider(FileCredentials::new("/run/secrets/open_ai_key"))
+///     .build()?;
+/// ```
+pub trait CredentialProvider: Send + Sync {
+    fn credentials(&self) -> Result<Credentials, LlmError>;
+}
+
+/// the API key and the optional `OpenAI-Organization` and `OpenAI-Project`
+/// headers. Is a provider of itself for a static key.
+#[derive(Clone, PartialEq)]
+pub struct Credentials {
+    pub api_key: String,
+    pub organization_maybe: Option<String>,
+    pub project_maybe: Option<String>,
+}
+
+impl Credentials {
+    pub fn new(api_key: impl Into<String>) -> Self {
+        Self {
+            api_key: api_key.into(),
+            organization_maybe: None,
+            project_maybe: None,
+        }
+    }
+
+    /// sent as the `OpenAI-Organization` header
+    pub fn organization(mut self, organization: impl Into<String>) -> Self {
+        self.organization_maybe = Some(organization.into());
+        self
+    }
+
+    /// sent as the `OpenAI-Project` header
+    pub fn project(mut self, project: impl Into<String>) -> Self {
+        self.project_maybe = Some(project.into());
+        self
+    }
+}
+
+/// the key is left out so that it does not end up in logs
+impl Debug for Credentials {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Credentials")
+            .field("api_key", &"***")
+            .field("organization_maybe", &self.organization_maybe)
+            .field("project_maybe", &self.project_maybe)
+            .finish()
+    }
+}
+
+impl CredentialProvider for Credentials {
+    fn credentials(&self) -> Result<Credentials, LlmError> {
+        Ok(self.clone())
+    }
+}
+
+impl<P: CredentialProvider + ?Sized> CredentialProvider for Arc<P> {
+    fn credentials(&self) -> Result<Credentials, LlmError> {
+        (**self).credentials()
+    }
+}
+
+/// reads the key from an environment variable, `OPEN_AI_TOKEN` by default.
+/// The organization and the project are read from `OPEN_AI_ORGANIZATION` and
+/// `OPEN_AI_PROJECT` when set.
+#[derive(Debug, Clone)]
+pub struct EnvCredentials {
+    api_key_var: String,
+    organization_var: String,
+    project_var: String,
+}
+
+impl Default for EnvCredentials {
+    fn default() -> Self {
+        Self::new(DEFAULT_API_KEY_VAR)
+    }
+}
+
+impl EnvCredentials {
+    pub fn new(api_key_var: impl Into<String>) -> Self {
+        Self {
+            api_key_var: api_key_var.into(),
+            organization_var: DEFAULT_ORGANIZATION_VAR.to_string(),
+            project_var: DEFAULT_PROJECT_VAR.to_string(),
+        }
+    }
+
+    pub fn organization_var(mut self, organization_var: impl Into<String>) -> Self {
+        self.organization_var = organization_var.into();
+        self
+    }
+
+    pub fn project_var(mut self, project_var: impl Into<String>) -> Self {
+        self.project_var = project_var.into();
+        self
+    }
+}
+
+impl CredentialProvider for EnvCredentials {
+    fn credentials(&self) -> Result<Credentials, LlmError> {
+        let api_key = std::env::var(&self.api_key_var).map_err(|_| {
+            LlmError::Credentials(format!(
+                "environment variable {} is not set",
+                self.api_key_var
+            ))
+        })?;
+
+        Ok(Credentials {
+            api_key,
+            organization_maybe: std::env::var(&self.organization_var).ok(),
+            project_maybe: std::env::var(&self.project_var).ok(),
+        })
+    }
+}
+
+/// loads a `.env` file into the environment once and then reads it like
+/// [`EnvCredentials`], the default of the client. Variables already set in
+/// the environment take precedence over the file.
+#[derive(Debug)]
+pub struct DotEnvCredentials {
+    /// `.env` in the working directory or its parents when not set
+    path_maybe: Option<PathBuf>,
+    env_credentials: EnvCredentials,
+    load_once: Once,
+}
+
+impl Default for DotEnvCredentials {
+    fn default() -> Self {
+        Self {
+            path_maybe: None,
+            env_credentials: EnvCredentials::default(),
+            load_once: Once::new(),
+        }
+    }
+}
+
+impl DotEnvCredentials {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path_maybe: Some(path.into()),
+            ..Self::default()
+        }
+    }
+
+    /// the variables read after loading the file
+    pub fn env_credentials(mut self, env_credentials: EnvCredentials) -> Self {
+        self.env_credentials = env_credentials;
+        self
+    }
+}
+
+impl CredentialProvider for DotEnvCredentials {
+    fn credentials(&self) -> Result<Credentials, LlmError> {
+        // a missing file is fine, the variables may be set without it
+        self.load_once.call_once(|| match &self.path_maybe {
+            Some(path) => {
+                dotenv::from_path(path).ok();
+            }
+            None => {
+                dotenv::dotenv().ok();
+            }
+        });
+
+        self.env_credentials.credentials()
+    }
+}
+
+/// reads the key from a file on every request, e.g. a mounted secret that is
+/// rotated. Surrounding whitespace is ignored.
+#[derive(Debug, Clone)]
+pub struct FileCredentials {
+    path: PathBuf,
+}
+
+impl FileCredentials {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl CredentialProvider for FileCredentials {
+    fn credentials(&self) -> Result<Credentials, LlmError> {
+        let content = std::fs::read_to_string(&self.path).map_err(|error| {
+            LlmError::Credentials(format!("could not read {}: {error}", self.path.display()))
+        })?;
+
+        let api_key = content.trim();
+        if api_key.is_empty() {
+            return Err(LlmError::Credentials(format!(
+                "{} is empty",
+                self.path.display()
+            )));
+        }
+
+        Ok(Credentials::new(api_key))
+    }
+}
+
+/// hands out the keys in turn, spreading the requests over the rate limits of
+/// several keys
+#[derive(Debug)]
+pub struct RotatingCredentials {
+    credentials: Vec<Credentials>,
+    next_index: AtomicUsize,
+}
+
+impl RotatingCredentials {
+    pub fn new(credentials: Vec<Credentials>) -> Self {
+        Self {
+            credentials,
+            next_index: AtomicUsize::new(0),
+        }
+    }
+
+    /// keys without organization and project
+    pub fn from_keys<K: Into<String>>(api_keys: impl IntoIterator<Item = K>) -> Self {
+        Self::new(api_keys.into_iter().map(Credentials::new).collect())
+    }
+}
+
+impl CredentialProvider for RotatingCredentials {
+    fn credentials(&self) -> Result<Credentials, LlmError> {
+        if self.credentials.is_empty() {
+            return Err(LlmError::Credentials(
+                "no keys to rotate through".to_string(),
+            ));
+        }
+
+        let index = self.next_index.fetch_add(1, Ordering::Relaxed) % self.credentials.len();
+        Ok(self.credentials[index].clone())
+    }
+}
@@ -0,0 +1,200 @@
+use super::GeminiClient;
+use crate::http_client::build_http_client;
+use crate::{
+    CostLedger, CredentialProvider, Credentials, DotEnvCredentials, EnvCredentials,
+    GenerationLimits, GenerationParams, LlmError, ModelRegistry, RateLimiter, RetryPolicy,
+    Timeouts, Tokenizer,
+};
+use std::sync::Arc;
+
+const GEMINI_BASE_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
+
+const DEFAULT_MODEL: &str = "gemini-2.0-flash";
+
+/// environment variable of the default credentials
+const GEMINI_API_KEY_VAR: &str = "GEMINI_API_KEY";
+
+/// builds a [`GeminiClient`].
+///
+/// # Usage
+/// ```no_run
+/// let gemini_client = GeminiClient::builder()
+///     .model("gemini-2.5-flash")
+///     .generation_params(GenerationParams::default().temperature(0.2))
+///     .build()?;
+/// ```
+pub struct GeminiClientBuilder {
+    model_maybe: Option<String>,
+    credential_provider_maybe: Option<Arc<dyn CredentialProvider>>,
+    base_url: String,
+    allow_http: bool,
+    retry_policy: RetryPolicy,
+    timeouts: Timeouts,
+    rate_limiter_maybe: Option<RateLimiter>,
+    generation_params: GenerationParams,
+    tokenizer: Tokenizer,
+    cost_ledger_maybe: Option<CostLedger>,
+    model_registry: ModelRegistry,
+}
+
+impl Default for GeminiClientBuilder {
+    fn default() -> Self {
+        Self {
+            model_maybe: None,
+            credential_provider_maybe: None,
+            base_url: GEMINI_BASE_URL.to_string(),
+            allow_http: false,
+            retry_policy: RetryPolicy::default(),
+            timeouts: Timeouts::default(),
+            rate_limiter_maybe: None,
+            generation_params: GenerationParams::default(),
+            tokenizer: Tokenizer::default(),
+            cost_ledger_maybe: None,
+            model_registry: ModelRegistry::default(),
+        }
+    }
+}
+
+impl GeminiClientBuilder {
+    /// model used for the requests, defaults to `gemini-2.0-flash`
+    pub fn model(mut self, model: impl Into<String>) -> Self {
+        self.model_maybe = Some(model.into());
+        self
+    }
+
+    /// API key, shorthand for a static [`Credentials`]. Defaults to
+    /// `GEMINI_API_KEY` from the environment or `.env`.
+    pub fn token(self, token: impl Into<String>) -> Self {
+        self.credential_provider(Credentials::new(token))
+    }
+
+    /// where the API key comes from, asked before every attempt
+    pub fn credential_provider(
+        mut self,
+        credential_provider: impl CredentialProvider + 'static,
+    ) -> Self {
+        self.credential_provider_maybe = Some(Arc::new(credential_provider));
+        self
+    }
+
+    /// base URL the model paths are appended to, defaults to
+    /// `https://generativelanguage.googleapis.com/v1beta`
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// allows `http://` base URLs, which are rejected by default so that the
+    /// API key is not sent unencrypted by accident
+    pub fn allow_http(mut self, allow_http: bool) -> Self {
+        self.allow_http = allow_http;
+        self
+    }
+
+    /// how failed requests are retried, defaults to [`RetryPolicy::default`]
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// connect, read and overall timeouts, defaults to [`Timeouts::default`]
+    pub fn timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// waits for the RPM and TPM budget before every attempt, pass a clone to
+    /// share the budget with other clients
+    pub fn rate_limiter(mut self, rate_limiter: RateLimiter) -> Self {
+        self.rate_limiter_maybe = Some(rate_limiter);
+        self
+    }
+
+    /// sampling parameters of every request, which a request can override.
+    /// They are sent as the `generationConfig`, `logit_bias` is not supported
+    /// and `user` is not sent.
+    pub fn generation_params(mut self, generation_params: GenerationParams) -> Self {
+        self.generation_params = generation_params;
+        self
+    }
+
+    /// upper bound for the generated tokens of every request, shorthand for
+    /// the `max_tokens` of the [`GenerationParams`]
+    pub fn max_tokens(mut self, max_tokens: u32) -> Self {
+        self.generation_params.max_tokens = Some(max_tokens);
+        self
+    }
+
+    /// tokenizer for the TPM estimate and [`crate::LlmClient::count_tokens`],
+    /// defaults to an estimate
+    pub fn tokenizer(mut self, tokenizer: impl Into<Tokenizer>) -> Self {
+        self.tokenizer = tokenizer.into();
+        self
+    }
+
+    /// records usage and cost of every successful request, pass a clone to
+    /// aggregate the spend of several clients
+    pub fn cost_ledger(mut self, cost_ledger: CostLedger) -> Self {
+        self.cost_ledger_maybe = Some(cost_ledger);
+        self
+    }
+
+    /// models the client knows the limits and pricing of, defaults to
+    /// [`ModelRegistry::default`]
+    pub fn model_registry(mut self, model_registry: ModelRegistry) -> Self {
+        self.model_registry = model_registry;
+        self
+    }
+
+    pub fn build(self) -> Result<GeminiClient, LlmError> {
+        let base_url = self.base_url.trim_end_matches('/').to_string();
+
+        let uri: hyper::Uri = base_url.parse().map_err(|error| {
+            LlmError::InvalidRequest(format!("invalid base url {base_url}: {error}"))
+        })?;
+
+        match uri.scheme_str() {
+            Some("https") => {}
+            Some("http") if self.allow_http => {}
+            Some("http") => {
+                return Err(LlmError::InvalidRequest(format!(
+                    "plain HTTP base url {base_url} requires allow_http(true)"
+                )))
+            }
+            _ => {
+                return Err(LlmError::InvalidRequest(format!(
+                    "base url {base_url} must start with http:// or https://"
+                )))
+            }
+        }
+
+        let model = self
+            .model_maybe
+            .unwrap_or_else(|| DEFAULT_MODEL.to_string());
+        self.generation_params.validate(
+            &GenerationLimits::GEMINI,
+            self.model_registry.lookup(&model),
+        )?;
+
+        let credential_provider = self.credential_provider_maybe.unwrap_or_else(|| {
+            Arc::new(
+                DotEnvCredentials::default()
+                    .env_credentials(EnvCredentials::new(GEMINI_API_KEY_VAR)),
+            )
+        });
+
+        Ok(GeminiClient {
+            model,
+            credential_provider,
+            base_url,
+            retry_policy: self.retry_policy,
+            timeouts: self.timeouts,
+            http_client: build_http_client(self.allow_http, &self.timeouts),
+            rate_limiter_maybe: self.rate_limiter_maybe,
+            generation_params: self.generation_params,
+            tokenizer: self.tokenizer,
+            cost_ledger_maybe: self.cost_ledger_maybe,
+            model_registry: Arc::new(self.model_registry),
+        })
+    }
+}
@@ -0,0 +1,126 @@
+use super::GeminiGenerateContentResponseBody;
+use crate::server_sent_events::ServerSentEventParser;
+use crate::timeouts::with_timeout;
+use crate::{ChatStream, ContentFilterStage, LlmError, TimeoutKind};
+use futures::StreamExt;
+use hyper::body::HttpBody;
+use hyper::Body;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+struct StreamState {
+    body: Body,
+    parser: ServerSentEventParser,
+    pending: VecDeque<Result<String, LlmError>>,
+
+    /// a finish reason arrived, the stream has no terminator of its own
+    finished: bool,
+    done: bool,
+    read_timeout: Option<Duration>,
+}
+
+impl StreamState {
+    /// queues the text of the first candidate of a chunk, a blocked prompt
+    /// or candidate ends the stream
+    fn process(&mut self, data: &str) {
+        let data = data.trim();
+        if data.is_empty() || self.done {
+            return;
+        }
+
+        let chunk = match serde_json::from_str::<GeminiGenerateContentResponseBody>(data) {
+            Ok(chunk) => chunk,
+            Err(error) => {
+                self.fail(
+                    LlmError::provider_error_from_body(200, data.as_bytes()).unwrap_or_else(|| {
+                        LlmError::Decode(format!("invalid stream chunk {data}: {error}"))
+                    }),
+                );
+                return;
+            }
+        };
+
+        if let Some(error) = chunk.prompt_blocked_error() {
+            self.fail(error);
+            return;
+        }
+
+        let Some(candidate) = chunk
+            .candidates
+            .iter()
+            .find(|candidate| candidate.index == 0)
+        else {
+            return;
+        };
+        if candidate.is_blocked() {
+            self.fail(LlmError::ContentFiltered {
+                stage: ContentFilterStage::Completion,
+                results: candidate.flagged_categories().collect(),
+            });
+            return;
+        }
+
+        let text = candidate.text();
+        if !text.is_empty() {
+            self.pending.push_back(Ok(text));
+        }
+        if candidate.finish_reason.is_some() {
+            self.finished = true;
+        }
+    }
+
+    fn fail(&mut self, error: LlmError) {
+        self.pending.push_back(Err(error));
+        self.done = true;
+    }
+}
+
+/// the text of the first candidate as Gemini generates it. A stream that
+/// ends before a finish reason arrived fails with [`LlmError::Transport`].
+pub(super) fn text_stream(body: Body, read_timeout: Option<Duration>) -> ChatStream {
+    let state = StreamState {
+        body,
+        parser: ServerSentEventParser::default(),
+        pending: VecDeque::new(),
+        finished: false,
+        done: false,
+        read_timeout,
+    };
+
+    futures::stream::unfold(state, |mut state| async move {
+        loop {
+            if let Some(item) = state.pending.pop_front() {
+                return Some((item, state));
+            }
+            if state.done {
+                return None;
+            }
+
+            let read_result = with_timeout(state.read_timeout, TimeoutKind::Read, async {
+                state.body.data().await.transpose().map_err(LlmError::from)
+            })
+            .await;
+
+            match read_result {
+                Ok(Some(bytes)) => {
+                    for event in state.parser.feed(&bytes) {
+                        state.process(&event.data);
+                    }
+                }
+                Ok(None) => {
+                    if let Some(event) = state.parser.finish() {
+                        state.process(&event.data);
+                    }
+                    if !state.done && !state.finished {
+                        state.fail(LlmError::Transport(
+                            "Gemini stream ended before a finish reason".to_string(),
+                        ));
+                    }
+                    state.done = true;
+                }
+                Err(error) => state.fail(error),
+            }
+        }
+    })
+    .boxed()
+}
@@ -0,0 +1,543 @@
+mod client_builder;
+mod content_stream;
+
+pub use client_builder::GeminiClientBuilder;
+
+use crate::http_client::HttpClient;
+use crate::rate_limiter::estimate_request_tokens;
+use crate::retry_policy::send_with_retries;
+use crate::timeouts::{read_body, with_timeout};
+use crate::usage::record_usage;
+use crate::{
+    ChatMessage, ChatResponse, ChatRole, ChatStream, ContentFilterResult, ContentFilterStage,
+    CostLedger, CredentialProvider, GenerationLimits, GenerationParams, LlmClient, LlmError,
+    ModelRegistry, PromptTokensDetails, PromptType, RateLimiter, ResponseFormat, RetryPolicy,
+    TimeoutKind, Timeouts, Tokenizer, Usage,
+};
+use async_trait::async_trait;
+use hyper::{Body, Method, Request, Response};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+
+/// finish reasons of candidates withheld by a safety filter
+const BLOCKED_FINISH_REASONS: [&str; 5] = [
+    "SAFETY",
+    "BLOCKLIST",
+    "PROHIBITED_CONTENT",
+    "SPII",
+    "IMAGE_SAFETY",
+];
+
+/// a message of the `contents`, the role is `user` or `model`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GeminiContent {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+
+    #[serde(default)]
+    pub parts: Vec<GeminiPart>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiPart {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+
+    /// a summary of the model's thinking rather than the answer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thought: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub function_call: Option<Value>,
+}
+
+/// how likely the content is harmful in a category, e.g.
+/// `HARM_CATEGORY_HARASSMENT` with probability `NEGLIGIBLE`
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiSafetyRating {
+    pub category: String,
+    pub probability: String,
+
+    #[serde(default)]
+    pub blocked: bool,
+}
+
+impl GeminiSafetyRating {
+    /// blocked or rated at least a medium probability
+    pub fn is_flagged(&self) -> bool {
+        self.blocked || matches!(self.probability.as_str(), "MEDIUM" | "HIGH")
+    }
+
+    /// the rating in the crate's terms, `HARM_CATEGORY_HATE_SPEECH` with
+    /// probability `HIGH` becomes `hate_speech` with severity `high`
+    pub fn content_filter_result(&self) -> ContentFilterResult {
+        ContentFilterResult {
+            category: self
+                .category
+                .trim_start_matches("HARM_CATEGORY_")
+                .to_lowercase(),
+            filtered: self.blocked,
+            severity_maybe: Some(self.probability.to_lowercase()),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiCandidate {
+    /// missing when the candidate was blocked
+    pub content: Option<GeminiContent>,
+    pub finish_reason: Option<String>,
+
+    #[serde(default)]
+    pub index: u32,
+
+    #[serde(default)]
+    pub safety_ratings: Vec<GeminiSafetyRating>,
+}
+
+impl GeminiCandidate {
+    /// the text parts joined, without thoughts and function calls
+    pub fn text(&self) -> String {
+        self.content
+            .iter()
+            .flat_map(|content| &content.parts)
+            .filter(|part| part.thought != Some(true))
+            .filter_map(|part| part.text.as_deref())
+            .collect()
+    }
+
+    /// whether a safety filter withheld the candidate
+    pub fn is_blocked(&self) -> bool {
+        self.finish_reason
+            .as_deref()
+            .is_some_and(|finish_reason| BLOCKED_FINISH_REASONS.contains(&finish_reason))
+    }
+
+    fn flagged_categories(&self) -> impl Iterator<Item = ContentFilterResult> + '_ {
+        self.safety_ratings
+            .iter()
+            .filter(|safety_rating| safety_rating.is_flagged())
+            .map(GeminiSafetyRating::content_filter_result)
+    }
+}
+
+/// set instead of the candidates when the prompt was blocked
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiPromptFeedback {
+    pub block_reason: Option<String>,
+
+    #[serde(default)]
+    pub safety_ratings: Vec<GeminiSafetyRating>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiUsageMetadata {
+    #[serde(default)]
+    pub prompt_token_count: u64,
+
+    #[serde(default)]
+    pub candidates_token_count: u64,
+
+    #[serde(default)]
+    pub total_token_count: u64,
+
+    pub cached_content_token_count: Option<u64>,
+
+    /// billed as output tokens
+    pub thoughts_token_count: Option<u64>,
+}
+
+impl From<GeminiUsageMetadata> for Usage {
+    fn from(usage_metadata: GeminiUsageMetadata) -> Self {
+        let completion_tokens = usage_metadata.candidates_token_count
+            + usage_metadata.thoughts_token_count.unwrap_or(0);
+
+        Usage {
+            prompt_tokens: usage_metadata.prompt_token_count,
+            completion_tokens,
+            total_tokens: usage_metadata.prompt_token_count + completion_tokens,
+            prompt_tokens_details: usage_metadata
+                .cached_content_token_count
+                .map(|cached_tokens| PromptTokensDetails { cached_tokens }),
+        }
+    }
+}
+
+/// the answer of `generateContent`, also the shape of every streamed chunk
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiGenerateContentResponseBody {
+    #[serde(default)]
+    pub candidates: Vec<GeminiCandidate>,
+    pub prompt_feedback: Option<GeminiPromptFeedback>,
+    pub usage_metadata: Option<GeminiUsageMetadata>,
+    pub model_version: Option<String>,
+}
+
+impl GeminiGenerateContentResponseBody {
+    /// the error of a blocked prompt
+    fn prompt_blocked_error(&self) -> Option<LlmError> {
+        let prompt_feedback = self.prompt_feedback.as_ref()?;
+        prompt_feedback.block_reason.as_ref()?;
+
+        Some(LlmError::ContentFiltered {
+            stage: ContentFilterStage::Prompt,
+            results: prompt_feedback
+                .safety_ratings
+                .iter()
+                .filter(|safety_rating| safety_rating.is_flagged())
+                .map(GeminiSafetyRating::content_filter_result)
+                .collect(),
+        })
+    }
+}
+
+impl TryFrom<GeminiGenerateContentResponseBody> for ChatResponse {
+    type Error = LlmError;
+
+    /// the answers of the candidates that were not blocked, fails when the
+    /// prompt or all candidates were blocked
+    fn try_from(mut body: GeminiGenerateContentResponseBody) -> Result<Self, Self::Error> {
+        if let Some(error) = body.prompt_blocked_error() {
+            return Err(error);
+        }
+
+        body.candidates.sort_by_key(|candidate| candidate.index);
+        let (blocked, answered): (Vec<_>, Vec<_>) = body
+            .candidates
+            .into_iter()
+            .partition(GeminiCandidate::is_blocked);
+
+        if answered.is_empty() {
+            if blocked.is_empty() {
+                return Err(LlmError::EmptyChoices);
+            }
+            return Err(LlmError::ContentFiltered {
+                stage: ContentFilterStage::Completion,
+                results: blocked
+                    .iter()
+                    .flat_map(GeminiCandidate::flagged_categories)
+                    .collect(),
+            });
+        }
+
+        let answers: Vec<String> = answered.iter().map(GeminiCandidate::text).collect();
+        Ok(ChatResponse {
+            answer: answers.first().cloned(),
+            answers,
+            usage: body.usage_metadata.map(Usage::from),
+            cost_usd: None,
+            content_filter_results: answered
+                .iter()
+                .flat_map(GeminiCandidate::flagged_categories)
+                .collect(),
+            tokens: vec![],
+        })
+    }
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct GenerateContentRequest {
+    contents: Vec<GeminiContent>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system_instruction: Option<GeminiContent>,
+
+    generation_config: GenerationConfig,
+}
+
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+struct GenerationConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_output_tokens: Option<u32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop_sequences: Option<Vec<String>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    candidate_count: Option<u32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<i64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    presence_penalty: Option<f32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frequency_penalty: Option<f32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_mime_type: Option<&'static str>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_json_schema: Option<Value>,
+}
+
+/// runs prompts on Gemini models through Google's `generateContent` API.
+/// Cheap to clone, clones share the connection pool and the rate limiter.
+///
+/// # Usage
+/// ```no_run
+/// let gemini_client = GeminiClient::builder()
+///     .model("gemini-2.0-flash")
+///     .build()?;
+///
+/// let chat_response = gemini_client.perform_request(&prompt).await?;
+/// ```
+#[derive(Clone)]
+pub struct GeminiClient {
+    model: String,
+    credential_provider: Arc<dyn CredentialProvider>,
+    base_url: String,
+    retry_policy: RetryPolicy,
+    timeouts: Timeouts,
+    http_client: HttpClient,
+    rate_limiter_maybe: Option<RateLimiter>,
+    generation_params: GenerationParams,
+    tokenizer: Tokenizer,
+    cost_ledger_maybe: Option<CostLedger>,
+    model_registry: Arc<ModelRegistry>,
+}
+
+impl GeminiClient {
+    pub fn builder() -> GeminiClientBuilder {
+        GeminiClientBuilder::default()
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    pub async fn perform_request(&self, prompt: &PromptType) -> Result<ChatResponse, LlmError> {
+        self.perform_request_with_params(prompt, &GenerationParams::default())
+            .await
+    }
+
+    /// same as [`Self::perform_request`] with the parameters set in `params`
+    /// overriding the client's [`GenerationParams`]. An answer not matching
+    /// the prompt's [`ResponseFormat`] is an error.
+    pub async fn perform_request_with_params(
+        &self,
+        prompt: &PromptType,
+        params: &GenerationParams,
+    ) -> Result<ChatResponse, LlmError> {
+        let response_body = self
+            .perform_generate_content_request(prompt, params)
+            .await?;
+        let model_name = response_body
+            .model_version
+            .clone()
+            .unwrap_or_else(|| self.model.clone());
+
+        let mut chat_response = ChatResponse::try_from(response_body)?;
+        if let (Some(response_format), Some(answer)) =
+            (prompt.response_format(), &chat_response.answer)
+        {
+            response_format.validate_answer(answer)?;
+        }
+        chat_response.cost_usd = record_usage(
+            &self.model_registry,
+            self.cost_ledger_maybe.as_ref(),
+            None,
+            &model_name,
+            chat_response.usage.as_ref(),
+        );
+
+        Ok(chat_response)
+    }
+
+    /// the response with its candidates, safety ratings and prompt feedback
+    /// as sent by the API
+    pub async fn perform_generate_content_request(
+        &self,
+        prompt: &PromptType,
+        params: &GenerationParams,
+    ) -> Result<GeminiGenerateContentResponseBody, LlmError> {
+        let (body, estimated_tokens) = self.build_body(prompt, params)?;
+
+        let read_timeout = self.timeouts.read;
+        send_with_retries(
+            &self.retry_policy,
+            &self.timeouts,
+            self.rate_limiter_maybe.as_ref(),
+            estimated_tokens,
+            || self.send("generateContent", body.clone()),
+            |mut resp| async move {
+                let body = read_body(resp.body_mut(), read_timeout).await?;
+                Ok(serde_json::from_slice::<GeminiGenerateContentResponseBody>(
+                    &body,
+                )?)
+            },
+        )
+        .await
+    }
+
+    /// streams the text of the first candidate as it is generated. Failed
+    /// attempts to open the stream are retried, a stream that broke off is
+    /// not.
+    pub async fn perform_request_stream(
+        &self,
+        prompt: &PromptType,
+    ) -> Result<ChatStream, LlmError> {
+        let (body, estimated_tokens) = self.build_body(prompt, &GenerationParams::default())?;
+
+        let resp = send_with_retries(
+            &self.retry_policy,
+            &self.timeouts,
+            self.rate_limiter_maybe.as_ref(),
+            estimated_tokens,
+            || self.send("streamGenerateContent?alt=sse", body.clone()),
+            |resp| async move { Ok(resp) },
+        )
+        .await?;
+
+        Ok(content_stream::text_stream(
+            resp.into_body(),
+            self.timeouts.read,
+        ))
+    }
+
+    /// the serialized request and the tokens it is estimated to take from
+    /// the TPM budget
+    fn build_body(
+        &self,
+        prompt: &PromptType,
+        params: &GenerationParams,
+    ) -> Result<(String, u32), LlmError> {
+        let params = self.generation_params.merge(params);
+        params.validate(
+            &GenerationLimits::GEMINI,
+            self.model_registry.lookup(&self.model),
+        )?;
+        if params.logit_bias.is_some() {
+            return Err(LlmError::InvalidRequest(
+                "Gemini does not support logit_bias".to_string(),
+            ));
+        }
+
+        let messages = prompt.messages();
+        let system_parts: Vec<GeminiPart> = messages
+            .iter()
+            .filter(|message| matches!(message.role, ChatRole::System | ChatRole::Developer))
+            .map(text_part)
+            .collect();
+
+        let (response_mime_type, response_json_schema) = match prompt.response_format() {
+            Some(ResponseFormat::JsonObject) => (Some("application/json"), None),
+            Some(ResponseFormat::JsonSchema(json_schema)) => {
+                (Some("application/json"), Some(json_schema.schema.clone()))
+            }
+            None => (None, None),
+        };
+
+        let request = GenerateContentRequest {
+            contents: gemini_contents(&messages),
+            system_instruction: (!system_parts.is_empty()).then_some(GeminiContent {
+                role: None,
+                parts: system_parts,
+            }),
+            generation_config: GenerationConfig {
+                temperature: params.temperature,
+                top_p: params.top_p,
+                max_output_tokens: params.max_tokens,
+                stop_sequences: params.stop,
+                candidate_count: params.n,
+                seed: params.seed,
+                presence_penalty: params.presence_penalty,
+                frequency_penalty: params.frequency_penalty,
+                response_mime_type,
+                response_json_schema,
+            },
+        };
+
+        let body = serde_json::to_string(&request)
+            .map_err(|error| LlmError::InvalidRequest(error.to_string()))?;
+        let estimated_tokens = estimate_request_tokens(&self.tokenizer, &body, params.max_tokens);
+        Ok((body, estimated_tokens))
+    }
+
+    /// a single attempt of posting the body to the method of the model, e.g.
+    /// `generateContent`, returns once the response headers arrived
+    async fn send(&self, method: &str, body: String) -> Result<Response<Body>, LlmError> {
+        let credentials = self.credential_provider.credentials()?;
+
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri(format!("{}/models/{}:{method}", self.base_url, self.model))
+            .header("x-goog-api-key", &credentials.api_key)
+            .header("content-type", "application/json")
+            .body(Body::from(body))?;
+
+        with_timeout(self.timeouts.read, TimeoutKind::Read, async {
+            Ok(self.http_client.request(request).await?)
+        })
+        .await
+    }
+}
+
+fn text_part(message: &ChatMessage) -> GeminiPart {
+    GeminiPart {
+        text: Some(message.content.clone().unwrap_or_default()),
+        ..GeminiPart::default()
+    }
+}
+
+/// the chat as `user` and `model` turns, tool outputs are sent as user text
+/// and consecutive messages of the same role become parts of one turn
+fn gemini_contents(messages: &[ChatMessage]) -> Vec<GeminiContent> {
+    let mut contents: Vec<GeminiContent> = vec![];
+
+    for message in messages {
+        let role = match message.role {
+            ChatRole::System | ChatRole::Developer => continue,
+            ChatRole::User | ChatRole::Tool => "user",
+            ChatRole::Assistant => "model",
+        };
+
+        match contents.last_mut() {
+            Some(content) if content.role.as_deref() == Some(role) => {
+                content.parts.push(text_part(message));
+            }
+            _ => contents.push(GeminiContent {
+                role: Some(role.to_string()),
+                parts: vec![text_part(message)],
+            }),
+        }
+    }
+
+    contents
+}
+
+#[async_trait]
+impl LlmClient for GeminiClient {
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+
+    async fn chat(&self, prompt: &PromptType) -> Result<ChatResponse, LlmError> {
+        self.perform_request(prompt).await
+    }
+
+    async fn chat_stream(&self, prompt: &PromptType) -> Result<ChatStream, LlmError> {
+        self.perform_request_stream(prompt).await
+    }
+
+    fn count_tokens(&self, prompt: &PromptType) -> usize {
+        let messages = prompt.messages();
+        self.tokenizer.count_chat_tokens(&messages)
+    }
+}
@@ -0,0 +1,7 @@
+mod gemini;
+
+pub use gemini::{
+    GeminiCandidate, GeminiClient, GeminiClientBuilder, GeminiContent,
+    GeminiGenerateContentResponseBody, GeminiPart, GeminiPromptFeedback, GeminiSafetyRating,
+    GeminiUsageMetadata,
+};
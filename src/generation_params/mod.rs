@@ -0,0 +1,273 @@
+use crate::{LlmError, ModelDescriptor};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// sampling parameters of a chat completion request. Unset parameters are
+/// not sent, so the server defaults apply. A client holds the defaults for
+/// all of its requests, which a request can override field by field.
+///
+/// # Usage
+/// ```no_run
+/// let open_ai_client = OpenAiClient::builder()
+///     .generation_params(GenerationParams::default().temperature(0.2).max_tokens(500))
+///     .build()?;
+///
+/// let response = open_ai_client
+///     .perform_request_with_params(&prompt, &GenerationParams::default().seed(42))
+///     .await?;
+/// ```
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct GenerationParams {
+    /// randomness of the answer, 0 is deterministic, the upper bound depends
+    /// on the provider, see [`GenerationLimits`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+
+    /// nucleus sampling, only the tokens within this probability mass are
+    /// considered
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+
+    /// upper bound for the generated tokens of every choice
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+
+    /// sequences the generation stops at, OpenAI takes up to 4
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop: Option<Vec<String>>,
+
+    /// between -2 and 2, positive values favour new topics
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f32>,
+
+    /// between -2 and 2, positive values penalize repeating the same line
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f32>,
+
+    /// best effort deterministic sampling for repeated requests
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+
+    /// number of choices to generate
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub n: Option<u32>,
+
+    /// token id to a bias added to its logit, between -100 and 100 for OpenAI
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logit_bias: Option<BTreeMap<u32, f32>>,
+
+    /// end user identifier for abuse monitoring
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+}
+
+impl GenerationParams {
+    pub fn temperature(mut self, temperature: f32) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    pub fn top_p(mut self, top_p: f32) -> Self {
+        self.top_p = Some(top_p);
+        self
+    }
+
+    pub fn max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    pub fn stop<I, S>(mut self, stop: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.stop = Some(stop.into_iter().map(Into::into).collect());
+        self
+    }
+
+    pub fn presence_penalty(mut self, presence_penalty: f32) -> Self {
+        self.presence_penalty = Some(presence_penalty);
+        self
+    }
+
+    pub fn frequency_penalty(mut self, frequency_penalty: f32) -> Self {
+        self.frequency_penalty = Some(frequency_penalty);
+        self
+    }
+
+    pub fn seed(mut self, seed: i64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn n(mut self, n: u32) -> Self {
+        self.n = Some(n);
+        self
+    }
+
+    pub fn logit_bias(mut self, token_id: u32, bias: f32) -> Self {
+        self.logit_bias
+            .get_or_insert_with(BTreeMap::new)
+            .insert(token_id, bias);
+        self
+    }
+
+    pub fn user(mut self, user: impl Into<String>) -> Self {
+        self.user = Some(user.into());
+        self
+    }
+
+    /// these parameters with the ones set in `overrides` replaced
+    pub fn merge(&self, overrides: &GenerationParams) -> GenerationParams {
+        GenerationParams {
+            temperature: overrides.temperature.or(self.temperature),
+            top_p: overrides.top_p.or(self.top_p),
+            max_tokens: overrides.max_tokens.or(self.max_tokens),
+            stop: overrides.stop.clone().or_else(|| self.stop.clone()),
+            presence_penalty: overrides.presence_penalty.or(self.presence_penalty),
+            frequency_penalty: overrides.frequency_penalty.or(self.frequency_penalty),
+            seed: overrides.seed.or(self.seed),
+            n: overrides.n.or(self.n),
+            logit_bias: overrides
+                .logit_bias
+                .clone()
+                .or_else(|| self.logit_bias.clone()),
+            user: overrides.user.clone().or_else(|| self.user.clone()),
+        }
+    }
+
+    /// checks the ranges the provider accepts and, for models in the
+    /// registry, the maximum completion tokens and whether sampling can be
+    /// configured at all
+    pub fn validate(
+        &self,
+        limits: &GenerationLimits,
+        model_descriptor_maybe: Option<&ModelDescriptor>,
+    ) -> Result<(), LlmError> {
+        check_range(
+            "temperature",
+            self.temperature,
+            0.0,
+            limits.max_temperature_maybe.unwrap_or(f32::INFINITY),
+        )?;
+        check_range("top_p", self.top_p, 0.0, 1.0)?;
+        check_range("presence_penalty", self.presence_penalty, -2.0, 2.0)?;
+        check_range("frequency_penalty", self.frequency_penalty, -2.0, 2.0)?;
+
+        if self.max_tokens == Some(0) {
+            return Err(invalid("max_tokens must be at least 1"));
+        }
+        if self.n == Some(0) {
+            return Err(invalid("n must be at least 1"));
+        }
+        if let (Some(stop), Some(max_stop_sequences)) =
+            (&self.stop, limits.max_stop_sequences_maybe)
+        {
+            if stop.len() > max_stop_sequences {
+                return Err(invalid(format!(
+                    "at most {max_stop_sequences} stop sequences are supported, got {}",
+                    stop.len()
+                )));
+            }
+        }
+        if let Some(max_logit_bias) = limits.max_logit_bias_maybe {
+            for (token_id, bias) in self.logit_bias.iter().flatten() {
+                if !(-max_logit_bias..=max_logit_bias).contains(bias) {
+                    return Err(invalid(format!(
+                        "logit_bias of token {token_id} must be between -{max_logit_bias} and {max_logit_bias}, got {bias}"
+                    )));
+                }
+            }
+        }
+
+        let Some(model_descriptor) = model_descriptor_maybe else {
+            return Ok(());
+        };
+        let model = &model_descriptor.name;
+
+        if let Some(max_tokens) = self.max_tokens {
+            if max_tokens as usize > model_descriptor.max_output_tokens {
+                return Err(invalid(format!(
+                    "max_tokens {max_tokens} exceeds the {} output tokens of {model}",
+                    model_descriptor.max_output_tokens
+                )));
+            }
+        }
+
+        if model_descriptor.features.reasoning {
+            let sampling_params = [
+                ("temperature", self.temperature.is_some()),
+                ("top_p", self.top_p.is_some()),
+                ("presence_penalty", self.presence_penalty.is_some()),
+                ("frequency_penalty", self.frequency_penalty.is_some()),
+                ("logit_bias", self.logit_bias.is_some()),
+            ];
+            for (name, is_set) in sampling_params {
+                if is_set {
+                    return Err(invalid(format!("{model} does not support {name}")));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// what a provider accepts for the parameters that are not the same
+/// everywhere, `None` where it sets no bound
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GenerationLimits {
+    pub max_temperature_maybe: Option<f32>,
+    pub max_stop_sequences_maybe: Option<usize>,
+
+    /// the largest bias in either direction
+    pub max_logit_bias_maybe: Option<f32>,
+}
+
+impl GenerationLimits {
+    pub const OPEN_AI: Self = Self {
+        max_temperature_maybe: Some(2.0),
+        max_stop_sequences_maybe: Some(4),
+        max_logit_bias_maybe: Some(100.0),
+    };
+
+    pub const ANTHROPIC: Self = Self {
+        max_temperature_maybe: Some(1.0),
+        max_stop_sequences_maybe: None,
+        max_logit_bias_maybe: None,
+    };
+
+    pub const GEMINI: Self = Self {
+        max_temperature_maybe: Some(2.0),
+        max_stop_sequences_maybe: Some(5),
+        max_logit_bias_maybe: None,
+    };
+
+    /// Ollama and llama.cpp server
+    pub const LLAMA: Self = Self {
+        max_temperature_maybe: None,
+        max_stop_sequences_maybe: None,
+        max_logit_bias_maybe: None,
+    };
+
+    /// the default `--max-stop-sequences` of the server
+    pub const TGI: Self = Self {
+        max_temperature_maybe: None,
+        max_stop_sequences_maybe: Some(4),
+        max_logit_bias_maybe: None,
+    };
+}
+
+fn check_range(name: &str, value_maybe: Option<f32>, min: f32, max: f32) -> Result<(), LlmError> {
+    match value_maybe {
+        Some(value) if !(min..=max).contains(&value) => Err(invalid(format!(
+            "{name} must be between {min} and {max}, got {value}"
+        ))),
+        _ => Ok(()),
+    }
+}
+
+fn invalid(message: impl Into<String>) -> LlmError {
+    LlmError::InvalidRequest(message.into())
+}
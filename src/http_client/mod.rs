@@ -0,0 +1,19 @@
+use crate::Timeouts;
+use hyper::client::HttpConnector;
+use hyper::Client;
+use hyper_tls::HttpsConnector;
+
+/// pooled HTTP(S) client, cloning it shares the connections
+pub(crate) type HttpClient = Client<HttpsConnector<HttpConnector>>;
+
+/// builds the pooled client used for all requests of a client and its clones
+pub(crate) fn build_http_client(allow_http: bool, timeouts: &Timeouts) -> HttpClient {
+    let mut http_connector = HttpConnector::new();
+    http_connector.enforce_http(false);
+    http_connector.set_connect_timeout(timeouts.connect);
+
+    let mut https_connector = HttpsConnector::new_with_connector(http_connector);
+    https_connector.https_only(!allow_http);
+
+    Client::builder().build(https_connector)
+}
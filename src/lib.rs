@@ -1,8 +1,75 @@
+mod answer_selector;
+mod anthropic_api;
+mod chat_template;
+mod content_filter;
+mod conversation;
+mod credentials;
+mod gemini_api;
+mod generation_params;
+mod http_client;
 mod inner_prompt_template;
+mod llama_api;
+mod llm_client;
+mod llm_error;
+mod llm_response;
+mod model_registry;
 mod open_ai_api;
 mod prompt_types;
+mod rate_limiter;
+mod response_format;
+mod retry_policy;
+mod server_sent_events;
+mod tgi_api;
+mod timeouts;
+mod tokenizer;
+mod tools;
+mod usage;
 
+pub use answer_selector::{normalize_answer, AnswerSelector};
+pub use anthropic_api::{
+    AnthropicClient, AnthropicClientBuilder, AnthropicContentBlock, AnthropicContentDelta,
+    AnthropicMessageDelta, AnthropicMessageStream, AnthropicMessagesResponseBody,
+    AnthropicStopReason, AnthropicStreamEvent, AnthropicUsage,
+};
+pub use chat_template::ChatTemplate;
+pub use content_filter::{ContentFilterResult, ContentFilterStage};
+pub use conversation::{ChatMessage, ChatRole, Conversation};
+pub use credentials::{
+    CredentialProvider, Credentials, DotEnvCredentials, EnvCredentials, FileCredentials,
+    RotatingCredentials,
+};
+pub use gemini_api::{
+    GeminiCandidate, GeminiClient, GeminiClientBuilder, GeminiContent,
+    GeminiGenerateContentResponseBody, GeminiPart, GeminiPromptFeedback, GeminiSafetyRating,
+    GeminiUsageMetadata,
+};
+pub use generation_params::{GenerationLimits, GenerationParams};
 pub use inner_prompt_template::InnerPrompt;
-pub use open_ai_api::{OpenAiClient, OpenAiCompletionsResponseBody, OpenAiSimplifiedResponse};
+pub use llama_api::{LlamaBackend, LlamaClient, LlamaClientBuilder};
+pub use llm_client::{chat_typed, ChatResponse, ChatStream, LlmClient, TokenLogprob};
+pub use llm_error::{LlmError, ProviderError};
+pub use llm_response::LlmResponse;
+pub use model_registry::{ModelDescriptor, ModelFeatures, ModelRegistry};
+pub use open_ai_api::{
+    AzureConfig, OpenAiClient, OpenAiClientBuilder, OpenAiCompletionChunk, OpenAiCompletionStream,
+    OpenAiCompletionsResponseBody, OpenAiLogprobs, OpenAiModel, OpenAiModelEntry,
+    OpenAiSimplifiedResponse, OpenAiStreamDelta, OpenAiTextChoice, OpenAiTextCompletionOptions,
+    OpenAiTextCompletionResponseBody,
+};
 pub use prompt_types::{MultiShotExampleCount, MultiShotPrompt, MultiShotQuestionsAndAnswers};
 pub use prompt_types::{PromptType, ZeroShotPrompt};
+pub use rate_limiter::RateLimiter;
+pub use response_format::{JsonSchema, ResponseFormat, SchemaViolation};
+pub use retry_policy::RetryPolicy;
+pub use rust_llm_utils_macros::{llm_prompt, LlmResponse};
+pub use tgi_api::{TgiClient, TgiClientBuilder, TgiDetails, TgiGenerateResponseBody, TgiToken};
+pub use timeouts::{TimeoutKind, Timeouts};
+pub use tokenizer::{BpeTokenizer, ContextWindowCheck, Tokenizer, TokenizerEncoding};
+pub use tools::{FunctionCall, ToolCall, ToolDefinition, ToolRegistry};
+pub use usage::{CostLedger, LedgerEntry, ModelPricing, PromptTokensDetails, Usage};
+
+// the generated code refers to the crate by name, also from within the crate
+extern crate self as rust_llm_utils;
+
+#[doc(hidden)]
+pub use llm_response::derive_support as __derive_support;
@@ -0,0 +1,180 @@
+use super::LlamaBackend;
+use crate::server_sent_events::ServerSentEventParser;
+use crate::timeouts::with_timeout;
+use crate::{ChatStream, LlmError, TimeoutKind};
+use futures::StreamExt;
+use hyper::body::HttpBody;
+use hyper::Body;
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// a piece of a streamed answer. Ollama sends one JSON object per line,
+/// llama.cpp server one per `data:` event.
+#[derive(Deserialize, Debug)]
+struct StreamPiece {
+    /// Ollama
+    message: Option<StreamMessage>,
+    done: Option<bool>,
+
+    /// llama.cpp
+    content: Option<String>,
+    stop: Option<bool>,
+
+    /// both, sent instead of a piece when generation failed
+    error: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct StreamMessage {
+    content: String,
+}
+
+struct StreamState {
+    body: Body,
+    backend: LlamaBackend,
+
+    /// the incomplete last line of an Ollama stream
+    line_buffer: Vec<u8>,
+    parser: ServerSentEventParser,
+    pending: VecDeque<Result<String, LlmError>>,
+    done: bool,
+    read_timeout: Option<Duration>,
+}
+
+impl StreamState {
+    /// the complete payloads in the bytes read so far
+    fn feed(&mut self, bytes: &[u8]) -> Vec<String> {
+        match self.backend {
+            LlamaBackend::Ollama => {
+                self.line_buffer.extend_from_slice(bytes);
+                let mut lines = vec![];
+                while let Some(position) = self.line_buffer.iter().position(|byte| *byte == b'\n') {
+                    let line: Vec<u8> = self.line_buffer.drain(..=position).collect();
+                    lines.push(String::from_utf8_lossy(&line).into_owned());
+                }
+                lines
+            }
+            LlamaBackend::LlamaCpp => self
+                .parser
+                .feed(bytes)
+                .into_iter()
+                .map(|event| event.data)
+                .collect(),
+        }
+    }
+
+    fn finish(&mut self) -> Vec<String> {
+        match self.backend {
+            LlamaBackend::Ollama => {
+                let line = std::mem::take(&mut self.line_buffer);
+                vec![String::from_utf8_lossy(&line).into_owned()]
+            }
+            LlamaBackend::LlamaCpp => self
+                .parser
+                .finish()
+                .map(|event| event.data)
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    /// queues the text of the payload, an error ends the stream
+    fn process(&mut self, payload: &str) {
+        let payload = payload.trim();
+        if payload.is_empty() || self.done {
+            return;
+        }
+
+        let piece = match serde_json::from_str::<StreamPiece>(payload) {
+            Ok(piece) => piece,
+            Err(error) => {
+                self.fail(LlmError::Decode(format!(
+                    "invalid stream piece {payload}: {error}"
+                )));
+                return;
+            }
+        };
+
+        if let Some(error) = piece.error {
+            self.fail(LlmError::HttpStatus {
+                status: 200,
+                body: error,
+            });
+            return;
+        }
+
+        let text = piece
+            .message
+            .map(|message| message.content)
+            .or(piece.content)
+            .unwrap_or_default();
+        if !text.is_empty() {
+            self.pending.push_back(Ok(text));
+        }
+
+        if piece.done.or(piece.stop).unwrap_or(false) {
+            self.done = true;
+        }
+    }
+
+    fn fail(&mut self, error: LlmError) {
+        self.pending.push_back(Err(error));
+        self.done = true;
+    }
+}
+
+/// the text of the answer as the server generates it. A stream that ends
+/// before the server marked it as done fails with [`LlmError::Transport`].
+pub(super) fn text_stream(
+    backend: LlamaBackend,
+    body: Body,
+    read_timeout: Option<Duration>,
+) -> ChatStream {
+    let state = StreamState {
+        body,
+        backend,
+        line_buffer: vec![],
+        parser: ServerSentEventParser::default(),
+        pending: VecDeque::new(),
+        done: false,
+        read_timeout,
+    };
+
+    futures::stream::unfold(state, |mut state| async move {
+        loop {
+            if let Some(item) = state.pending.pop_front() {
+                return Some((item, state));
+            }
+            if state.done {
+                return None;
+            }
+
+            let read_result = with_timeout(state.read_timeout, TimeoutKind::Read, async {
+                state.body.data().await.transpose().map_err(LlmError::from)
+            })
+            .await;
+
+            match read_result {
+                Ok(Some(bytes)) => {
+                    for payload in state.feed(&bytes) {
+                        state.process(&payload);
+                    }
+                }
+                Ok(None) => {
+                    for payload in state.finish() {
+                        state.process(&payload);
+                    }
+                    if !state.done {
+                        state.fail(LlmError::Transport(format!(
+                            "{} stream ended before the last piece",
+                            state.backend.name()
+                        )));
+                    }
+                }
+                Err(error) => state.fail(error),
+            }
+        }
+    })
+    .boxed()
+}
@@ -0,0 +1,114 @@
+use super::{LlamaBackend, LlamaClient};
+use crate::http_client::build_http_client;
+use crate::{ChatTemplate, GenerationLimits, GenerationParams, LlmError, Timeouts, Tokenizer};
+
+/// builds a [`LlamaClient`] for a local Ollama or llama.cpp server.
+///
+/// # Usage
+/// ```no_run
+/// let llama_client = LlamaClient::builder()
+///     .backend(LlamaBackend::LlamaCpp)
+///     .base_url("http://gpu-box:8080")
+///     .chat_template(ChatTemplate::Llama2)
+///     .build()?;
+/// ```
+#[derive(Default)]
+pub struct LlamaClientBuilder {
+    backend: LlamaBackend,
+    base_url_maybe: Option<String>,
+    model_maybe: Option<String>,
+    chat_template: ChatTemplate,
+    generation_params: GenerationParams,
+    timeouts: Timeouts,
+    tokenizer: Tokenizer,
+}
+
+impl LlamaClientBuilder {
+    /// server the requests go to, defaults to [`LlamaBackend::Ollama`]
+    pub fn backend(mut self, backend: LlamaBackend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// URL the endpoint paths are appended to, defaults to
+    /// [`LlamaBackend::default_base_url`]. Plain HTTP is allowed, there is no
+    /// token to protect.
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url_maybe = Some(base_url.into());
+        self
+    }
+
+    /// model Ollama runs, e.g. `llama2:13b-chat`, defaults to `llama2`.
+    /// llama.cpp server serves the model it was started with and only
+    /// reports the name.
+    pub fn model(mut self, model: impl Into<String>) -> Self {
+        self.model_maybe = Some(model.into());
+        self
+    }
+
+    /// how the chat is turned into the raw prompt of llama.cpp's
+    /// `/completion`, defaults to [`ChatTemplate::Llama2`]
+    pub fn chat_template(mut self, chat_template: ChatTemplate) -> Self {
+        self.chat_template = chat_template;
+        self
+    }
+
+    /// sampling parameters of every request, unset ones use the server's
+    /// defaults
+    pub fn generation_params(mut self, generation_params: GenerationParams) -> Self {
+        self.generation_params = generation_params;
+        self
+    }
+
+    /// upper bound for the generated tokens of every request, shorthand for
+    /// the `max_tokens` of the [`GenerationParams`]
+    pub fn max_tokens(mut self, max_tokens: u32) -> Self {
+        self.generation_params.max_tokens = Some(max_tokens);
+        self
+    }
+
+    /// connect, read and overall timeouts, defaults to [`Timeouts::default`]
+    pub fn timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// tokenizer for [`crate::LlmClient::count_tokens`], defaults to an
+    /// estimate
+    pub fn tokenizer(mut self, tokenizer: impl Into<Tokenizer>) -> Self {
+        self.tokenizer = tokenizer.into();
+        self
+    }
+
+    pub fn build(self) -> Result<LlamaClient, LlmError> {
+        let base_url = self
+            .base_url_maybe
+            .as_deref()
+            .unwrap_or(self.backend.default_base_url())
+            .trim_end_matches('/')
+            .to_string();
+
+        let uri: hyper::Uri = base_url.parse().map_err(|error| {
+            LlmError::InvalidRequest(format!("invalid base url {base_url}: {error}"))
+        })?;
+        if !matches!(uri.scheme_str(), Some("http") | Some("https")) {
+            return Err(LlmError::InvalidRequest(format!(
+                "base url {base_url} must start with http:// or https://"
+            )));
+        }
+
+        self.generation_params
+            .validate(&GenerationLimits::LLAMA, None)?;
+
+        Ok(LlamaClient {
+            backend: self.backend,
+            base_url,
+            model: self.model_maybe.unwrap_or_else(|| "llama2".to_string()),
+            chat_template: self.chat_template,
+            generation_params: self.generation_params,
+            timeouts: self.timeouts,
+            tokenizer: self.tokenizer,
+            http_client: build_http_client(true, &self.timeouts),
+        })
+    }
+}
@@ -0,0 +1,356 @@
+mod chat_stream;
+mod client_builder;
+
+pub use client_builder::LlamaClientBuilder;
+
+use crate::http_client::HttpClient;
+use crate::timeouts::{read_body, with_timeout};
+use crate::{
+    ChatMessage, ChatResponse, ChatRole, ChatStream, ChatTemplate, GenerationLimits,
+    GenerationParams, LlmClient, LlmError, PromptType, ResponseFormat, TimeoutKind, Timeouts,
+    Tokenizer, Usage,
+};
+use async_trait::async_trait;
+use hyper::{Body, Method, Request, Response};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+/// the server a [`LlamaClient`] talks to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LlamaBackend {
+    /// Ollama's `/api/chat`, which applies the model's chat template itself
+    #[default]
+    Ollama,
+
+    /// llama.cpp server's raw `/completion`, the chat is rendered with the
+    /// client's [`ChatTemplate`]
+    LlamaCpp,
+}
+
+impl LlamaBackend {
+    /// where the server listens when started with its defaults
+    pub fn default_base_url(&self) -> &'static str {
+        match self {
+            Self::Ollama => "http://localhost:11434",
+            Self::LlamaCpp => "http://localhost:8080",
+        }
+    }
+
+    fn endpoint_path(&self) -> &'static str {
+        match self {
+            Self::Ollama => "/api/chat",
+            Self::LlamaCpp => "/completion",
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Ollama => "Ollama",
+            Self::LlamaCpp => "llama.cpp",
+        }
+    }
+}
+
+/// the answer of `/api/chat` or `/completion`, which differ in where the
+/// content and the token counts are
+#[derive(Deserialize, Debug)]
+struct LlamaResponseBody {
+    /// Ollama
+    message: Option<OllamaMessage>,
+    prompt_eval_count: Option<u64>,
+    eval_count: Option<u64>,
+
+    /// llama.cpp
+    content: Option<String>,
+    tokens_evaluated: Option<u64>,
+    tokens_predicted: Option<u64>,
+}
+
+#[derive(Deserialize, Debug)]
+struct OllamaMessage {
+    content: String,
+}
+
+impl LlamaResponseBody {
+    /// the answer with the token counts, a body without content is
+    /// [`LlmError::EmptyChoices`]
+    fn answer(self) -> Result<(String, Option<Usage>), LlmError> {
+        let prompt_tokens_maybe = self.prompt_eval_count.or(self.tokens_evaluated);
+        let completion_tokens_maybe = self.eval_count.or(self.tokens_predicted);
+        let usage_maybe = prompt_tokens_maybe.zip(completion_tokens_maybe).map(
+            |(prompt_tokens, completion_tokens)| Usage {
+                prompt_tokens,
+                completion_tokens,
+                total_tokens: prompt_tokens + completion_tokens,
+                prompt_tokens_details: None,
+            },
+        );
+
+        let answer = self
+            .message
+            .map(|message| message.content)
+            .or(self.content)
+            .ok_or(LlmError::EmptyChoices)?;
+        Ok((answer, usage_maybe))
+    }
+}
+
+/// runs prompts on Llama models served locally by Ollama or llama.cpp server.
+/// Cheap to clone, clones share the connection pool. There are no retries or
+/// rate limits, the server is expected to be close by.
+///
+/// # Usage
+/// ```no_run
+/// let llama_client = LlamaClient::builder()
+///     .backend(LlamaBackend::Ollama)
+///     .model("llama2:13b-chat")
+///     .build()?;
+///
+/// let chat_response = llama_client.perform_request(&prompt).await?;
+/// ```
+#[derive(Clone)]
+pub struct LlamaClient {
+    backend: LlamaBackend,
+    base_url: String,
+    model: String,
+    chat_template: ChatTemplate,
+    generation_params: GenerationParams,
+    timeouts: Timeouts,
+    tokenizer: Tokenizer,
+    http_client: HttpClient,
+}
+
+impl LlamaClient {
+    pub fn builder() -> LlamaClientBuilder {
+        LlamaClientBuilder::default()
+    }
+
+    pub fn backend(&self) -> LlamaBackend {
+        self.backend
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    pub async fn perform_request(&self, prompt: &PromptType) -> Result<ChatResponse, LlmError> {
+        self.perform_request_with_params(prompt, &GenerationParams::default())
+            .await
+    }
+
+    /// same as [`Self::perform_request`] with the parameters set in `params`
+    /// overriding the client's [`GenerationParams`]. An answer not matching
+    /// the prompt's [`ResponseFormat`] is an error.
+    pub async fn perform_request_with_params(
+        &self,
+        prompt: &PromptType,
+        params: &GenerationParams,
+    ) -> Result<ChatResponse, LlmError> {
+        let body = self.build_body(prompt, params, false)?;
+
+        let read_timeout = self.timeouts.read;
+        let response_body = with_timeout(self.timeouts.overall, TimeoutKind::Overall, async {
+            let mut resp = self.send(body).await?;
+            let body = read_body(resp.body_mut(), read_timeout).await?;
+            Ok(serde_json::from_slice::<LlamaResponseBody>(&body)?)
+        })
+        .await?;
+
+        let (answer, usage) = response_body.answer()?;
+        if let Some(response_format) = prompt.response_format() {
+            response_format.validate_answer(&answer)?;
+        }
+
+        Ok(ChatResponse {
+            answer: Some(answer.clone()),
+            answers: vec![answer],
+            usage,
+            cost_usd: None,
+            content_filter_results: vec![],
+            tokens: vec![],
+        })
+    }
+
+    /// streams the text of the answer as it is generated
+    pub async fn perform_request_stream(
+        &self,
+        prompt: &PromptType,
+    ) -> Result<ChatStream, LlmError> {
+        let body = self.build_body(prompt, &GenerationParams::default(), true)?;
+        let resp = self.send(body).await?;
+
+        Ok(chat_stream::text_stream(
+            self.backend,
+            resp.into_body(),
+            self.timeouts.read,
+        ))
+    }
+
+    /// the request body of the backend, the raw prompt for llama.cpp is
+    /// rendered with the chat template and stops at its stop sequences
+    fn build_body(
+        &self,
+        prompt: &PromptType,
+        params: &GenerationParams,
+        stream: bool,
+    ) -> Result<Value, LlmError> {
+        let params = self.generation_params.merge(params);
+        params.validate(&GenerationLimits::LLAMA, None)?;
+        if params.n.is_some_and(|n| n > 1) {
+            return Err(LlmError::InvalidRequest(format!(
+                "{} answers with a single choice only",
+                self.backend.name()
+            )));
+        }
+
+        let messages = prompt.messages();
+        let mut body = Map::new();
+        match self.backend {
+            LlamaBackend::Ollama => {
+                if params.logit_bias.is_some() {
+                    return Err(LlmError::InvalidRequest(
+                        "Ollama does not support logit_bias".to_string(),
+                    ));
+                }
+
+                body.insert("model".to_string(), Value::from(self.model.clone()));
+                body.insert("messages".to_string(), ollama_messages(&messages));
+                body.insert(
+                    "options".to_string(),
+                    Value::Object(sampling_options(&params, "num_predict")),
+                );
+                if let Some(response_format) = prompt.response_format() {
+                    let format = match response_format {
+                        ResponseFormat::JsonObject => Value::from("json"),
+                        ResponseFormat::JsonSchema(json_schema) => json_schema.schema.clone(),
+                    };
+                    body.insert("format".to_string(), format);
+                }
+            }
+            LlamaBackend::LlamaCpp => {
+                body.insert(
+                    "prompt".to_string(),
+                    Value::from(self.chat_template.render(&messages)),
+                );
+                body.extend(sampling_options(&params, "n_predict"));
+
+                let mut stop = params.stop.clone().unwrap_or_default();
+                for stop_sequence in self.chat_template.stop_sequences() {
+                    if !stop.iter().any(|stop| stop == stop_sequence) {
+                        stop.push(stop_sequence.to_string());
+                    }
+                }
+                if !stop.is_empty() {
+                    body.insert("stop".to_string(), Value::from(stop));
+                }
+                if let Some(logit_bias) = &params.logit_bias {
+                    let logit_bias: Vec<Value> = logit_bias
+                        .iter()
+                        .map(|(token, bias)| serde_json::json!([token, bias]))
+                        .collect();
+                    body.insert("logit_bias".to_string(), Value::from(logit_bias));
+                }
+                if let Some(response_format) = prompt.response_format() {
+                    let json_schema = match response_format {
+                        ResponseFormat::JsonObject => serde_json::json!({"type": "object"}),
+                        ResponseFormat::JsonSchema(json_schema) => json_schema.schema.clone(),
+                    };
+                    body.insert("json_schema".to_string(), json_schema);
+                }
+            }
+        }
+        body.insert("stream".to_string(), Value::from(stream));
+
+        Ok(Value::Object(body))
+    }
+
+    /// posts the body to the endpoint of the backend, returns once the
+    /// response headers arrived with a success status
+    async fn send(&self, body: Value) -> Result<Response<Body>, LlmError> {
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri(format!("{}{}", self.base_url, self.backend.endpoint_path()))
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))?;
+
+        let mut resp = with_timeout(self.timeouts.read, TimeoutKind::Read, async {
+            Ok(self.http_client.request(request).await?)
+        })
+        .await?;
+
+        if !resp.status().is_success() {
+            let status = resp.status().as_u16();
+            let error_body = read_body(resp.body_mut(), self.timeouts.read).await?;
+            return Err(LlmError::from_status_and_body(status, &error_body));
+        }
+
+        Ok(resp)
+    }
+}
+
+/// Ollama knows the roles of OpenAI except `developer`
+fn ollama_messages(messages: &[ChatMessage]) -> Value {
+    messages
+        .iter()
+        .map(|message| {
+            let role = match message.role {
+                ChatRole::Developer => ChatRole::System,
+                role => role,
+            };
+            serde_json::json!({
+                "role": role,
+                "content": message.content.as_deref().unwrap_or(""),
+            })
+        })
+        .collect()
+}
+
+/// the sampling parameters under the names both servers use, except for the
+/// maximum of generated tokens
+fn sampling_options(params: &GenerationParams, max_tokens_name: &str) -> Map<String, Value> {
+    let mut options = Map::new();
+    let mut insert = |name: &str, value_maybe: Option<Value>| {
+        if let Some(value) = value_maybe {
+            options.insert(name.to_string(), value);
+        }
+    };
+
+    insert("temperature", params.temperature.map(Value::from));
+    insert("top_p", params.top_p.map(Value::from));
+    insert(max_tokens_name, params.max_tokens.map(Value::from));
+    insert("stop", params.stop.clone().map(Value::from));
+    insert("seed", params.seed.map(Value::from));
+    insert("presence_penalty", params.presence_penalty.map(Value::from));
+    insert(
+        "frequency_penalty",
+        params.frequency_penalty.map(Value::from),
+    );
+    options
+}
+
+#[async_trait]
+impl LlmClient for LlamaClient {
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+
+    async fn chat(&self, prompt: &PromptType) -> Result<ChatResponse, LlmError> {
+        self.perform_request(prompt).await
+    }
+
+    async fn chat_stream(&self, prompt: &PromptType) -> Result<ChatStream, LlmError> {
+        self.perform_request_stream(prompt).await
+    }
+
+    /// estimated with the client's tokenizer, the servers tokenize with the
+    /// model's own vocabulary
+    fn count_tokens(&self, prompt: &PromptType) -> usize {
+        let messages = prompt.messages();
+        match self.backend {
+            LlamaBackend::Ollama => self.tokenizer.count_chat_tokens(&messages),
+            LlamaBackend::LlamaCpp => self
+                .tokenizer
+                .count_tokens(&self.chat_template.render(&messages)),
+        }
+    }
+}
@@ -0,0 +1,3 @@
+mod llama;
+
+pub use llama::{LlamaBackend, LlamaClient, LlamaClientBuilder};
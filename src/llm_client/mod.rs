@@ -0,0 +1,155 @@
+use crate::response_format::repair_request;
+use crate::{
+    ChatMessage, ContentFilterResult, Conversation, LlmError, LlmResponse, PromptType,
+    ResponseFormat, SchemaViolation, Usage,
+};
+use async_trait::async_trait;
+use futures::stream::BoxStream;
+use serde::de::DeserializeOwned;
+
+/// the text of the answer as it arrives
+pub type ChatStream = BoxStream<'static, Result<String, LlmError>>;
+
+/// the answer of a chat request, whichever backend served it
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ChatResponse {
+    /// the answer picked by the client's [`crate::AnswerSelector`]
+    pub answer: Option<String>,
+
+    /// the answers of all choices, in the order of the choices
+    pub answers: Vec<String>,
+
+    /// tokens billed for the request, if the backend reported them
+    pub usage: Option<Usage>,
+
+    /// the cost of the request, if the pricing of the model is known
+    pub cost_usd: Option<f64>,
+
+    /// categories a content filter or safety rating flagged in the returned
+    /// answers without withholding them
+    pub content_filter_results: Vec<ContentFilterResult>,
+
+    /// the tokens of the answer with their log probabilities, empty unless
+    /// the backend returned them
+    pub tokens: Vec<TokenLogprob>,
+}
+
+/// a generated token and how likely the model found it
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TokenLogprob {
+    pub token: String,
+
+    /// natural logarithm of the probability of the token
+    pub logprob: f64,
+
+    /// the id in the model's vocabulary, if the backend reported it
+    pub token_id_maybe: Option<u32>,
+
+    /// the most likely tokens at this position with their log
+    /// probabilities, if requested
+    pub top_logprobs: Vec<(String, f64)>,
+}
+
+impl ChatResponse {
+    /// the answer deserialized from JSON, e.g. of a prompt with a
+    /// [`crate::ResponseFormat`]
+    pub fn answer_json<T: DeserializeOwned>(&self) -> Result<T, LlmError> {
+        let answer = self.answer.as_deref().ok_or(LlmError::EmptyChoices)?;
+        Ok(serde_json::from_str(answer)?)
+    }
+}
+
+/// a backend that answers prompts, implemented by every client so that code
+/// written against it runs on any provider. Provider specific features such
+/// as the tool loop of [`crate::OpenAiClient::perform_request_with_tools`]
+/// stay on the clients.
+///
+/// # Usage
+/// ```no_run
+/// async fn summarize(llm_client: &dyn LlmClient, text: &str) -> Result<String, LlmError> {
+///     let prompt = PromptType::new_zero_shot_prompt(format!("Summarize: {text}"));
+///     let chat_response = llm_client.chat(&prompt).await?;
+///     chat_response.answer.ok_or(LlmError::EmptyChoices)
+/// }
+///
+/// let answer = summarize(&open_ai_client, text).await?;
+/// ```
+#[async_trait]
+pub trait LlmClient: Send + Sync {
+    /// the model the prompts are sent to
+    fn model_name(&self) -> &str;
+
+    /// sends the prompt, an answer not matching the prompt's
+    /// [`crate::ResponseFormat`] is an error
+    async fn chat(&self, prompt: &PromptType) -> Result<ChatResponse, LlmError>;
+
+    /// sends the prompt and streams the text of the first answer
+    async fn chat_stream(&self, prompt: &PromptType) -> Result<ChatStream, LlmError>;
+
+    /// tokens of the prompt as the backend counts them, including the chat
+    /// format overhead
+    fn count_tokens(&self, prompt: &PromptType) -> usize;
+}
+
+/// the answer deserialized into `T`, see [`LlmResponse`]. The schema of `T`
+/// is sent as the response format, an answer that does not match it or does
+/// not deserialize is sent back once to be corrected. Takes a concrete client
+/// as well as a `&dyn LlmClient`.
+///
+/// # Usage
+/// ```no_run
+/// let city: City = chat_typed(&anthropic_client, &prompt).await?;
+/// ```
+pub async fn chat_typed<T, C>(llm_client: &C, prompt: &PromptType) -> Result<T, LlmError>
+where
+    T: LlmResponse,
+    C: LlmClient + ?Sized,
+{
+    let response_format = ResponseFormat::JsonSchema(T::response_json_schema());
+    let mut conversation = Conversation::new();
+    for message in prompt.messages() {
+        conversation.push(message);
+    }
+    conversation.set_response_format(response_format.clone());
+
+    let (violation, answer) = match llm_client
+        .chat(&PromptType::new_conversation(conversation.clone()))
+        .await
+    {
+        Ok(chat_response) => {
+            let answer = chat_response.answer.unwrap_or_default();
+            match parse_answer::<T>(&response_format, &answer) {
+                Ok(value) => return Ok(value),
+                Err(violation) => (violation, answer),
+            }
+        }
+        Err(LlmError::SchemaValidation {
+            path,
+            message,
+            answer,
+        }) => (SchemaViolation { path, message }, answer),
+        Err(error) => return Err(error),
+    };
+
+    log::warn!("answer does not match the response format, asking for a repair: {violation}");
+    conversation.push(ChatMessage::assistant(answer));
+    conversation.push_user(repair_request(&violation));
+
+    let chat_response = llm_client
+        .chat(&PromptType::new_conversation(conversation))
+        .await?;
+    let answer = chat_response.answer.unwrap_or_default();
+    parse_answer::<T>(&response_format, &answer).map_err(|violation| violation.into_error(answer))
+}
+
+/// the answer validated against the format and deserialized
+pub(crate) fn parse_answer<T: LlmResponse>(
+    response_format: &ResponseFormat,
+    answer: &str,
+) -> Result<T, SchemaViolation> {
+    let value = response_format.validate(answer)?;
+    serde_json::from_value(value).map_err(|error| SchemaViolation {
+        path: "$".to_string(),
+        message: error.to_string(),
+    })
+}
@@ -0,0 +1,262 @@
+use crate::content_filter::flagged_categories;
+use crate::{ContentFilterResult, ContentFilterStage, TimeoutKind};
+use serde::{Deserialize, Deserializer};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+/// error returned by every fallible call towards an LLM provider
+#[derive(Debug, Clone, PartialEq)]
+pub enum LlmError {
+    /// the request could not be constructed, e.g. an invalid header value
+    InvalidRequest(String),
+
+    /// the API key could not be obtained, see [`crate::CredentialProvider`]
+    Credentials(String),
+
+    /// the connection could not be established or broke while talking to the
+    /// provider
+    Transport(String),
+
+    /// the provider did not answer in time, see [`crate::Timeouts`]
+    Timeout(TimeoutKind),
+
+    /// the provider answered with a non success status code and a body that is
+    /// not a recognised error payload
+    HttpStatus { status: u16, body: String },
+
+    /// the provider answered with a structured error payload, e.g. OpenAI's
+    /// `{"error": {...}}`
+    Provider { status: u16, error: ProviderError },
+
+    /// the response body could not be decoded into the expected structure
+    Decode(String),
+
+    /// the response was well formed but did not contain any choices
+    EmptyChoices,
+
+    /// a content filter, e.g. Azure's, rejected the prompt or withheld all
+    /// answers
+    ContentFiltered {
+        stage: ContentFilterStage,
+
+        /// the flagged categories, empty when the provider did not say
+        results: Vec<ContentFilterResult>,
+    },
+
+    /// the vocabulary of a tokenizer could not be loaded
+    Tokenizer(String),
+
+    /// the prompt plus the requested completion tokens do not fit into the
+    /// context window of the model, detected before sending the request
+    ContextWindowExceeded {
+        model: String,
+        prompt_tokens: usize,
+        max_tokens: usize,
+        context_window: usize,
+    },
+
+    /// the answer is not JSON in the requested [`crate::ResponseFormat`],
+    /// also after the repair attempts
+    SchemaValidation {
+        /// where the answer violates the schema, e.g. `$.items[0].name`
+        path: String,
+        message: String,
+        answer: String,
+    },
+
+    /// the model still asked for tool calls after the maximum number of
+    /// steps of the tool loop, see [`crate::ToolRegistry::with_max_steps`]
+    ToolStepsExceeded { max_steps: usize },
+
+    /// the request was attempted more than once and the last attempt failed,
+    /// holds the error of every attempt in order
+    AttemptsFailed(Vec<LlmError>),
+}
+
+impl Display for LlmError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidRequest(message) => write!(f, "invalid request: {message}"),
+            Self::Credentials(message) => write!(f, "credentials error: {message}"),
+            Self::Transport(message) => write!(f, "transport error: {message}"),
+            Self::Timeout(kind) => write!(f, "{kind} timeout elapsed"),
+            Self::HttpStatus { status, body } => {
+                write!(f, "unexpected HTTP status {status}: {body}")
+            }
+            Self::Provider { status, error } => {
+                write!(f, "provider returned an error (HTTP {status}): {error}")
+            }
+            Self::Decode(message) => write!(f, "failed to decode response: {message}"),
+            Self::EmptyChoices => write!(f, "response did not contain any choices"),
+            Self::ContentFiltered { stage, results } => {
+                write!(f, "content filter flagged the {stage}")?;
+                for (index, result) in results.iter().enumerate() {
+                    let separator = if index == 0 { ": " } else { ", " };
+                    write!(f, "{separator}{result}")?;
+                }
+                Ok(())
+            }
+            Self::Tokenizer(message) => write!(f, "tokenizer error: {message}"),
+            Self::ContextWindowExceeded {
+                model,
+                prompt_tokens,
+                max_tokens,
+                context_window,
+            } => write!(
+                f,
+                "prompt of {prompt_tokens} tokens plus {max_tokens} completion tokens exceeds the context window of {context_window} tokens of {model}"
+            ),
+            Self::SchemaValidation { path, message, .. } => {
+                write!(f, "answer does not match the response format at {path}: {message}")
+            }
+            Self::ToolStepsExceeded { max_steps } => {
+                write!(f, "no final answer after {max_steps} tool steps")
+            }
+            Self::AttemptsFailed(attempts) => {
+                write!(f, "all {} attempts failed", attempts.len())?;
+                for (index, attempt) in attempts.iter().enumerate() {
+                    write!(f, "; attempt {}: {attempt}", index + 1)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for LlmError {}
+
+/// the `error` object of an OpenAI error payload:
+///
+/// ```json
+/// {"error": {"message": "...", "type": "invalid_request_error", "param": null, "code": "invalid_api_key"}}
+/// ```
+///
+/// Google's payloads have a numeric `code` and the type in `status`, which
+/// end up in `code` and `error_type`.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct ProviderError {
+    pub message: String,
+
+    #[serde(rename = "type")]
+    pub error_type: Option<String>,
+
+    pub param: Option<String>,
+
+    #[serde(default, deserialize_with = "string_or_number")]
+    pub code: Option<String>,
+}
+
+fn string_or_number<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(
+        match Option::<serde_json::Value>::deserialize(deserializer)? {
+            Some(serde_json::Value::String(code)) => Some(code),
+            Some(serde_json::Value::Number(code)) => Some(code.to_string()),
+            _ => None,
+        },
+    )
+}
+
+impl Display for ProviderError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)?;
+        if let Some(error_type) = &self.error_type {
+            write!(f, " (type: {error_type})")?;
+        }
+        if let Some(code) = &self.code {
+            write!(f, " (code: {code})")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+struct ProviderErrorEnvelope {
+    error: ProviderError,
+}
+
+impl LlmError {
+    /// the error of the final attempt, which is the error itself unless the
+    /// request was retried
+    pub fn last_attempt(&self) -> &LlmError {
+        match self {
+            Self::AttemptsFailed(attempts) => attempts.last().map_or(self, LlmError::last_attempt),
+            _ => self,
+        }
+    }
+
+    /// failures that say nothing about the request itself and may go away on
+    /// their own
+    pub(crate) fn is_transient(&self) -> bool {
+        matches!(self, Self::Transport(_) | Self::Timeout(_))
+    }
+
+    /// builds the error for a non success response, parsing the provider error
+    /// payload when there is one
+    pub(crate) fn from_status_and_body(status: u16, body: &[u8]) -> Self {
+        Self::provider_error_from_body(status, body).unwrap_or_else(|| Self::HttpStatus {
+            status,
+            body: String::from_utf8_lossy(body).into_owned(),
+        })
+    }
+
+    /// the provider error if the body is an error payload, which can also
+    /// arrive with a success status, e.g. in the middle of a stream
+    pub(crate) fn provider_error_from_body(status: u16, body: &[u8]) -> Option<Self> {
+        let envelope = serde_json::from_slice::<ProviderErrorEnvelope>(body).ok()?;
+
+        // Azure rejects prompts with the flagged categories in the inner error
+        if envelope.error.code.as_deref() == Some("content_filter") {
+            let payload: serde_json::Value = serde_json::from_slice(body).ok()?;
+            return Some(Self::ContentFiltered {
+                stage: ContentFilterStage::Prompt,
+                results: flagged_categories(
+                    &payload["error"]["innererror"]["content_filter_result"],
+                ),
+            });
+        }
+
+        let mut error = envelope.error;
+        if error.error_type.is_none() {
+            let payload: serde_json::Value = serde_json::from_slice(body).ok()?;
+            error.error_type = payload["error"]["status"].as_str().map(str::to_string);
+        }
+
+        Some(Self::Provider { status, error })
+    }
+}
+
+impl From<hyper::Error> for LlmError {
+    fn from(error: hyper::Error) -> Self {
+        // the connector reports its connect timeout as a timed out io error
+        let mut source_maybe = error.source();
+        while let Some(source) = source_maybe {
+            if let Some(io_error) = source.downcast_ref::<std::io::Error>() {
+                if io_error.kind() == std::io::ErrorKind::TimedOut {
+                    return Self::Timeout(TimeoutKind::Connect);
+                }
+            }
+            source_maybe = source.source();
+        }
+
+        if error.is_timeout() {
+            Self::Timeout(TimeoutKind::Read)
+        } else {
+            Self::Transport(error.to_string())
+        }
+    }
+}
+
+impl From<hyper::http::Error> for LlmError {
+    fn from(error: hyper::http::Error) -> Self {
+        Self::InvalidRequest(error.to_string())
+    }
+}
+
+impl From<serde_json::Error> for LlmError {
+    fn from(error: serde_json::Error) -> Self {
+        Self::Decode(error.to_string())
+    }
+}
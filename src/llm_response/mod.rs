@@ -0,0 +1,127 @@
+use crate::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+/// a Rust type the answer of a prompt can be deserialized into, see
+/// [`crate::OpenAiClient::perform_request_typed`]. Derive it together with
+/// `Deserialize`, doc comments become the descriptions in the schema.
+///
+/// # Usage
+/// ```no_run
+/// #[derive(Deserialize, LlmResponse)]
+/// /// the sentiment of a review
+/// struct Sentiment {
+///     /// how the review feels overall
+///     label: SentimentLabel,
+///     /// between 0 and 1
+///     confidence: f64,
+/// }
+///
+/// #[derive(Deserialize, LlmResponse)]
+/// enum SentimentLabel {
+///     Positive,
+///     Negative,
+/// }
+///
+/// let sentiment: Sentiment = open_ai_client.perform_request_typed(&prompt).await?;
+/// ```
+pub trait LlmResponse: DeserializeOwned {
+    /// JSON schema of the serialized form of the type
+    fn json_schema() -> Value;
+
+    /// name of the schema in the request, letters, digits, `_` and `-` only
+    fn schema_name() -> String {
+        let type_name = std::any::type_name::<Self>();
+        let type_name = type_name.split('<').next().unwrap_or(type_name);
+        type_name
+            .rsplit("::")
+            .next()
+            .unwrap_or(type_name)
+            .to_string()
+    }
+
+    /// the strict [`JsonSchema`] sent as response format
+    fn response_json_schema() -> JsonSchema {
+        JsonSchema::new(Self::schema_name(), Self::json_schema())
+    }
+}
+
+macro_rules! impl_llm_response {
+    ($json_type:literal: $($rust_type:ty),+) => {
+        $(
+            impl LlmResponse for $rust_type {
+                fn json_schema() -> Value {
+                    serde_json::json!({"type": $json_type})
+                }
+            }
+        )+
+    };
+}
+
+impl_llm_response!("string": String, char);
+impl_llm_response!("boolean": bool);
+impl_llm_response!("integer": i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+impl_llm_response!("number": f32, f64);
+
+impl<T: LlmResponse> LlmResponse for Vec<T> {
+    fn json_schema() -> Value {
+        serde_json::json!({"type": "array", "items": T::json_schema()})
+    }
+}
+
+/// strict structured outputs require every property, optional ones are
+/// nullable instead
+impl<T: LlmResponse> LlmResponse for Option<T> {
+    fn json_schema() -> Value {
+        serde_json::json!({"anyOf": [T::json_schema(), {"type": "null"}]})
+    }
+}
+
+impl<T: LlmResponse> LlmResponse for Box<T> {
+    fn json_schema() -> Value {
+        T::json_schema()
+    }
+}
+
+/// building blocks of the code generated by `#[derive(LlmResponse)]`
+#[doc(hidden)]
+pub mod derive_support {
+    use serde_json::{Map, Value};
+
+    pub use serde_json::Value as JsonValue;
+
+    /// an object requiring all of the properties and no others
+    pub fn object(properties: Vec<(&str, Value)>) -> Value {
+        let required: Vec<Value> = properties
+            .iter()
+            .map(|(name, _)| Value::from(*name))
+            .collect();
+        let properties: Map<String, Value> = properties
+            .into_iter()
+            .map(|(name, schema)| (name.to_string(), schema))
+            .collect();
+
+        serde_json::json!({
+            "type": "object",
+            "properties": properties,
+            "required": required,
+            "additionalProperties": false
+        })
+    }
+
+    pub fn string_enum(values: &[&str]) -> Value {
+        serde_json::json!({"type": "string", "enum": values})
+    }
+
+    pub fn any_of(schemas: Vec<Value>) -> Value {
+        serde_json::json!({ "anyOf": schemas })
+    }
+
+    /// adds the description, empty ones are left out
+    pub fn with_description(mut schema: Value, description: &str) -> Value {
+        if let (Some(schema), false) = (schema.as_object_mut(), description.is_empty()) {
+            schema.insert("description".to_string(), Value::from(description));
+        }
+        schema
+    }
+}
@@ -0,0 +1,502 @@
+use crate::{ModelPricing, TokenizerEncoding, Usage};
+use std::collections::HashMap;
+
+/// what a model can do beyond plain chat completions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ModelFeatures {
+    /// function / tool calling
+    pub tools: bool,
+
+    /// `response_format` of type `json_object` or `json_schema`
+    pub json_mode: bool,
+
+    /// images as part of the messages
+    pub vision: bool,
+
+    /// server-sent event streaming
+    pub streaming: bool,
+
+    /// reasoning models such as `o1` only accept the default sampling
+    /// parameters and take `max_completion_tokens` instead of `max_tokens`
+    pub reasoning: bool,
+}
+
+/// everything the crate needs to know about a model
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelDescriptor {
+    /// name as sent to the API, e.g. `gpt-4o`
+    pub name: String,
+
+    /// maximum number of prompt plus completion tokens
+    pub context_window: usize,
+
+    /// maximum number of completion tokens
+    pub max_output_tokens: usize,
+
+    /// list prices, `None` for models without known pricing such as self
+    /// hosted ones
+    pub pricing: Option<ModelPricing>,
+
+    pub features: ModelFeatures,
+
+    pub tokenizer_encoding: TokenizerEncoding,
+}
+
+impl ModelDescriptor {
+    /// a descriptor for a model nothing is known about, with a conservative
+    /// context window, no pricing and only streaming support
+    pub fn with_defaults(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            context_window: 8_192,
+            max_output_tokens: 4_096,
+            pricing: None,
+            features: ModelFeatures {
+                streaming: true,
+                ..ModelFeatures::default()
+            },
+            tokenizer_encoding: TokenizerEncoding::Cl100kBase,
+        }
+    }
+}
+
+/// the known models by name. The default registry holds the chat models of
+/// OpenAI, Anthropic and Google,
+/// more can be registered, e.g. fine-tuned or self hosted models, and
+/// registering a name again replaces its descriptor.
+///
+/// # Usage
+/// ```no_run
+/// let mut model_registry = ModelRegistry::default();
+/// model_registry.register(ModelDescriptor {
+///     context_window: 32_768,
+///     ..ModelDescriptor::with_defaults("mistral-7b-instruct")
+/// });
+///
+/// let open_ai_client = OpenAiClient::builder()
+///     .model(OpenAiModel::Custom("mistral-7b-instruct".to_string()))
+///     .model_registry(model_registry)
+///     .build()?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct ModelRegistry {
+    descriptors: HashMap<String, ModelDescriptor>,
+}
+
+impl Default for ModelRegistry {
+    fn default() -> Self {
+        let mut model_registry = Self::empty();
+        for descriptor in open_ai_descriptors()
+            .into_iter()
+            .chain(anthropic_descriptors())
+            .chain(gemini_descriptors())
+        {
+            model_registry.register(descriptor);
+        }
+        model_registry
+    }
+}
+
+impl ModelRegistry {
+    /// a registry without any models
+    pub fn empty() -> Self {
+        Self {
+            descriptors: HashMap::new(),
+        }
+    }
+
+    pub fn register(&mut self, descriptor: ModelDescriptor) {
+        self.descriptors.insert(descriptor.name.clone(), descriptor);
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.descriptors.contains_key(name)
+    }
+
+    /// the descriptor registered under exactly this name
+    pub fn get(&self, name: &str) -> Option<&ModelDescriptor> {
+        self.descriptors.get(name)
+    }
+
+    /// the descriptor for a name as reported in responses, which may carry a
+    /// snapshot suffix, e.g. `gpt-4o-mini-2024-07-18` resolves to
+    /// `gpt-4o-mini`. Other suffixes name other models, `o1-mini` does not
+    /// resolve to `o1`.
+    pub fn lookup(&self, name: &str) -> Option<&ModelDescriptor> {
+        self.get(name)
+            .or_else(|| self.get(strip_snapshot_suffix(name)?))
+    }
+
+    /// the cost of the usage at the list prices of the model, which is
+    /// looked up the same way, `None` for models without known pricing
+    pub fn cost_usd(&self, model_name: &str, usage: &Usage) -> Option<f64> {
+        let pricing = self.lookup(model_name)?.pricing?;
+        Some(pricing.cost(usage))
+    }
+
+    pub fn descriptors(&self) -> impl Iterator<Item = &ModelDescriptor> {
+        self.descriptors.values()
+    }
+
+    /// registers the names not known yet, e.g. the ones listed by
+    /// [`crate::OpenAiClient::list_models`]. A dated snapshot such as
+    /// `gpt-4o-2024-08-06` inherits the descriptor of `gpt-4o`, anything else
+    /// gets [`ModelDescriptor::with_defaults`]. Returns the added names.
+    pub fn merge_model_names<I, S>(&mut self, names: I) -> Vec<String>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut added_names = vec![];
+        for name in names {
+            let name = name.into();
+            if self.contains(&name) {
+                continue;
+            }
+
+            let descriptor = match self.lookup(&name) {
+                Some(descriptor) => ModelDescriptor {
+                    name: name.clone(),
+                    ..descriptor.clone()
+                },
+                None => ModelDescriptor::with_defaults(name.clone()),
+            };
+            self.register(descriptor);
+            added_names.push(name);
+        }
+        added_names
+    }
+}
+
+/// the name without a snapshot suffix, which is `-YYYY-MM-DD`, `-YYYYMMDD`,
+/// `-MMDD`, `-NNN` or `-latest`
+fn strip_snapshot_suffix(name: &str) -> Option<&str> {
+    let is_digits = |part: &str, len: usize| {
+        part.len() == len && part.bytes().all(|byte| byte.is_ascii_digit())
+    };
+
+    let mut parts = name.rsplitn(4, '-');
+    let last = parts.next()?;
+    if let [Some(month), Some(year), Some(base)] = [parts.next(), parts.next(), parts.next()] {
+        if is_digits(year, 4) && is_digits(month, 2) && is_digits(last, 2) {
+            return Some(base);
+        }
+    }
+
+    let (base, suffix) = name.rsplit_once('-')?;
+    let is_snapshot =
+        suffix == "latest" || is_digits(suffix, 8) || is_digits(suffix, 4) || is_digits(suffix, 3);
+    is_snapshot.then_some(base)
+}
+
+fn priced_descriptor(
+    name: &str,
+    context_window: usize,
+    max_output_tokens: usize,
+    (input_per_million, cached_input_per_million, output_per_million): (f64, Option<f64>, f64),
+    features: ModelFeatures,
+    tokenizer_encoding: TokenizerEncoding,
+) -> ModelDescriptor {
+    ModelDescriptor {
+        name: name.to_string(),
+        context_window,
+        max_output_tokens,
+        pricing: Some(ModelPricing {
+            input_per_million,
+            cached_input_per_million,
+            output_per_million,
+        }),
+        features,
+        tokenizer_encoding,
+    }
+}
+
+/// https://platform.openai.com/docs/models and https://openai.com/api/pricing
+fn open_ai_descriptors() -> Vec<ModelDescriptor> {
+    use TokenizerEncoding::{Cl100kBase, O200kBase};
+
+    let completion = ModelFeatures {
+        streaming: true,
+        ..ModelFeatures::default()
+    };
+    let tools = ModelFeatures {
+        tools: true,
+        streaming: true,
+        ..ModelFeatures::default()
+    };
+    let tools_json = ModelFeatures {
+        json_mode: true,
+        ..tools
+    };
+    let tools_json_vision = ModelFeatures {
+        vision: true,
+        ..tools_json
+    };
+    let reasoning_json = ModelFeatures {
+        reasoning: true,
+        ..tools_json
+    };
+    let reasoning_json_vision = ModelFeatures {
+        reasoning: true,
+        ..tools_json_vision
+    };
+
+    vec![
+        priced_descriptor(
+            "gpt-3.5-turbo",
+            16_385,
+            4_096,
+            (0.5, None, 1.5),
+            tools_json,
+            Cl100kBase,
+        ),
+        priced_descriptor(
+            "gpt-3.5-turbo-16k",
+            16_385,
+            4_096,
+            (3.0, None, 4.0),
+            tools,
+            Cl100kBase,
+        ),
+        priced_descriptor(
+            "gpt-3.5-turbo-instruct",
+            4_096,
+            4_096,
+            (1.5, None, 2.0),
+            completion,
+            Cl100kBase,
+        ),
+        priced_descriptor(
+            "davinci-002",
+            16_384,
+            16_384,
+            (2.0, None, 2.0),
+            completion,
+            Cl100kBase,
+        ),
+        priced_descriptor(
+            "babbage-002",
+            16_384,
+            16_384,
+            (0.4, None, 0.4),
+            completion,
+            Cl100kBase,
+        ),
+        priced_descriptor("gpt-4", 8_192, 8_192, (30.0, None, 60.0), tools, Cl100kBase),
+        priced_descriptor(
+            "gpt-4-32k",
+            32_768,
+            8_192,
+            (60.0, None, 120.0),
+            tools,
+            Cl100kBase,
+        ),
+        priced_descriptor(
+            "gpt-4-turbo",
+            128_000,
+            4_096,
+            (10.0, None, 30.0),
+            tools_json_vision,
+            Cl100kBase,
+        ),
+        priced_descriptor(
+            "gpt-4o",
+            128_000,
+            16_384,
+            (2.5, Some(1.25), 10.0),
+            tools_json_vision,
+            O200kBase,
+        ),
+        priced_descriptor(
+            "gpt-4o-mini",
+            128_000,
+            16_384,
+            (0.15, Some(0.075), 0.6),
+            tools_json_vision,
+            O200kBase,
+        ),
+        priced_descriptor(
+            "gpt-4.1",
+            1_047_576,
+            32_768,
+            (2.0, Some(0.5), 8.0),
+            tools_json_vision,
+            O200kBase,
+        ),
+        priced_descriptor(
+            "gpt-4.1-mini",
+            1_047_576,
+            32_768,
+            (0.4, Some(0.1), 1.6),
+            tools_json_vision,
+            O200kBase,
+        ),
+        priced_descriptor(
+            "o1",
+            200_000,
+            100_000,
+            (15.0, Some(7.5), 60.0),
+            reasoning_json_vision,
+            O200kBase,
+        ),
+        priced_descriptor(
+            "o3-mini",
+            200_000,
+            100_000,
+            (1.1, Some(0.55), 4.4),
+            reasoning_json,
+            O200kBase,
+        ),
+    ]
+}
+
+/// https://docs.anthropic.com/en/docs/about-claude/models, the prompt cache
+/// price is the one of cache reads. Claude's tokenizer is not public, counts
+/// are estimated with `cl100k_base`.
+fn anthropic_descriptors() -> Vec<ModelDescriptor> {
+    use TokenizerEncoding::Cl100kBase;
+
+    let tools = ModelFeatures {
+        tools: true,
+        streaming: true,
+        ..ModelFeatures::default()
+    };
+    let tools_vision = ModelFeatures {
+        vision: true,
+        ..tools
+    };
+
+    vec![
+        priced_descriptor(
+            "claude-3-haiku",
+            200_000,
+            4_096,
+            (0.25, Some(0.03), 1.25),
+            tools_vision,
+            Cl100kBase,
+        ),
+        priced_descriptor(
+            "claude-3-opus",
+            200_000,
+            4_096,
+            (15.0, Some(1.5), 75.0),
+            tools_vision,
+            Cl100kBase,
+        ),
+        priced_descriptor(
+            "claude-3-5-haiku",
+            200_000,
+            8_192,
+            (0.8, Some(0.08), 4.0),
+            tools,
+            Cl100kBase,
+        ),
+        priced_descriptor(
+            "claude-3-5-sonnet",
+            200_000,
+            8_192,
+            (3.0, Some(0.3), 15.0),
+            tools_vision,
+            Cl100kBase,
+        ),
+        priced_descriptor(
+            "claude-sonnet-4",
+            200_000,
+            64_000,
+            (3.0, Some(0.3), 15.0),
+            tools_vision,
+            Cl100kBase,
+        ),
+        priced_descriptor(
+            "claude-opus-4",
+            200_000,
+            32_000,
+            (15.0, Some(1.5), 75.0),
+            tools_vision,
+            Cl100kBase,
+        ),
+    ]
+}
+
+/// https://ai.google.dev/gemini-api/docs/models and
+/// https://ai.google.dev/gemini-api/docs/pricing, the prices of prompts up to
+/// 128k tokens where they are tiered. Gemini's tokenizer is not public, counts
+/// are estimated with `cl100k_base`.
+fn gemini_descriptors() -> Vec<ModelDescriptor> {
+    use TokenizerEncoding::Cl100kBase;
+
+    let tools_json_vision = ModelFeatures {
+        tools: true,
+        json_mode: true,
+        vision: true,
+        streaming: true,
+        ..ModelFeatures::default()
+    };
+
+    vec![
+        priced_descriptor(
+            "gemini-1.5-flash",
+            1_048_576,
+            8_192,
+            (0.075, Some(0.01875), 0.3),
+            tools_json_vision,
+            Cl100kBase,
+        ),
+        priced_descriptor(
+            "gemini-1.5-flash-8b",
+            1_048_576,
+            8_192,
+            (0.0375, Some(0.01), 0.15),
+            tools_json_vision,
+            Cl100kBase,
+        ),
+        priced_descriptor(
+            "gemini-1.5-pro",
+            2_097_152,
+            8_192,
+            (1.25, Some(0.3125), 5.0),
+            tools_json_vision,
+            Cl100kBase,
+        ),
+        priced_descriptor(
+            "gemini-2.0-flash",
+            1_048_576,
+            8_192,
+            (0.1, Some(0.025), 0.4),
+            tools_json_vision,
+            Cl100kBase,
+        ),
+        priced_descriptor(
+            "gemini-2.0-flash-lite",
+            1_048_576,
+            8_192,
+            (0.075, None, 0.3),
+            tools_json_vision,
+            Cl100kBase,
+        ),
+        priced_descriptor(
+            "gemini-2.5-flash",
+            1_048_576,
+            65_536,
+            (0.3, Some(0.075), 2.5),
+            tools_json_vision,
+            Cl100kBase,
+        ),
+        priced_descriptor(
+            "gemini-2.5-flash-lite",
+            1_048_576,
+            65_536,
+            (0.1, Some(0.025), 0.4),
+            tools_json_vision,
+            Cl100kBase,
+        ),
+        priced_descriptor(
+            "gemini-2.5-pro",
+            1_048_576,
+            65_536,
+            (1.25, Some(0.31), 10.0),
+            tools_json_vision,
+            Cl100kBase,
+        ),
+    ]
+}
@@ -1,3 +1,8 @@
 mod open_ai;
 
-pub use open_ai::{OpenAiClient, OpenAiCompletionsResponseBody, OpenAiSimplifiedResponse};
+pub use open_ai::{
+    AzureConfig, OpenAiClient, OpenAiClientBuilder, OpenAiCompletionChunk, OpenAiCompletionStream,
+    OpenAiCompletionsResponseBody, OpenAiLogprobs, OpenAiModel, OpenAiModelEntry,
+    OpenAiSimplifiedResponse, OpenAiStreamDelta, OpenAiTextChoice, OpenAiTextCompletionOptions,
+    OpenAiTextCompletionResponseBody,
+};
@@ -0,0 +1,72 @@
+use super::OpenAiModel;
+use crate::LlmError;
+use std::collections::BTreeMap;
+
+/// sends the requests to Azure OpenAI deployments instead, see
+/// [`super::OpenAiClientBuilder::azure`]. The base URL is the endpoint of the
+/// resource, the key is sent as the `api-key` header.
+///
+/// # Usage
+/// ```no_run
+/// let open_ai_client = OpenAiClient::builder()
+///     .base_url("https://my-resource.openai.azure.com")
+///     .model(OpenAiModel::Gpt4o)
+///     .azure(AzureConfig::new("2024-10-21").deployment(OpenAiModel::Gpt4o, "gpt-4o-prod"))
+///     .credential_provider(EnvCredentials::new("AZURE_OPENAI_API_KEY"))
+///     .build()?;
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct AzureConfig {
+    api_version: String,
+
+    /// deployment names by model name
+    deployments: BTreeMap<String, String>,
+}
+
+impl AzureConfig {
+    /// `api-version` query parameter of every request, e.g. `2024-10-21`
+    pub fn new(api_version: impl Into<String>) -> Self {
+        Self {
+            api_version: api_version.into(),
+            deployments: BTreeMap::new(),
+        }
+    }
+
+    /// the deployment requests for the model are sent to
+    pub fn deployment(mut self, model: OpenAiModel, deployment_name: impl Into<String>) -> Self {
+        self.deployments
+            .insert(model.name().to_string(), deployment_name.into());
+        self
+    }
+
+    pub fn deployment_for(&self, model: &OpenAiModel) -> Option<&str> {
+        self.deployments.get(model.name()).map(String::as_str)
+    }
+
+    pub fn api_version(&self) -> &str {
+        &self.api_version
+    }
+
+    /// the Azure URL of an endpoint path such as `/chat/completions`, which
+    /// belongs to the deployment of the model except for `/models`
+    pub(super) fn endpoint_url(
+        &self,
+        base_url: &str,
+        endpoint_path: &str,
+        model: &OpenAiModel,
+    ) -> Result<String, LlmError> {
+        let api_version = &self.api_version;
+        if endpoint_path == "/models" {
+            return Ok(format!(
+                "{base_url}/openai/models?api-version={api_version}"
+            ));
+        }
+
+        let deployment_name = self.deployment_for(model).ok_or_else(|| {
+            LlmError::InvalidRequest(format!("no Azure deployment for {}", model.name()))
+        })?;
+        Ok(format!(
+            "{base_url}/openai/deployments/{deployment_name}{endpoint_path}?api-version={api_version}"
+        ))
+    }
+}
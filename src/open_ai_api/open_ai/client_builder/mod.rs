@@ -0,0 +1,277 @@
+use super::{AzureConfig, OpenAiClient, OpenAiModel, OPEN_AI_BASE_URL};
+use crate::http_client::build_http_client;
+use crate::{
+    AnswerSelector, ContextWindowCheck, CostLedger, CredentialProvider, Credentials,
+    DotEnvCredentials, GenerationLimits, GenerationParams, LlmError, ModelRegistry, RateLimiter,
+    RetryPolicy, Timeouts, Tokenizer,
+};
+use hyper::header::{HeaderMap, HeaderName, HeaderValue};
+use std::sync::Arc;
+
+/// builds an [`OpenAiClient`] for the OpenAI API or any server speaking the
+/// same protocol, e.g. vLLM, llama.cpp server or an internal gateway.
+///
+/// # Usage
+/// ```no_run
+/// let open_ai_client = OpenAiClient::builder()
+///     .base_url("http://localhost:8000/v1")
+///     .allow_http(true)
+///     .default_header("x-gateway-team", "search")
+///     .token("not-needed-locally")
+///     .build()?;
+/// ```
+pub struct OpenAiClientBuilder {
+    model_maybe: Option<OpenAiModel>,
+    credential_provider_maybe: Option<Arc<dyn CredentialProvider>>,
+    base_url: String,
+    default_headers: Vec<(String, String)>,
+    allow_http: bool,
+    retry_policy: RetryPolicy,
+    timeouts: Timeouts,
+    rate_limiter_maybe: Option<RateLimiter>,
+    generation_params: GenerationParams,
+    answer_selector: AnswerSelector,
+    json_repair_attempts: usize,
+    tokenizer: Tokenizer,
+    context_window_check: ContextWindowCheck,
+    cost_ledger_maybe: Option<CostLedger>,
+    model_registry: ModelRegistry,
+    azure_config_maybe: Option<AzureConfig>,
+}
+
+impl Default for OpenAiClientBuilder {
+    fn default() -> Self {
+        Self {
+            model_maybe: None,
+            credential_provider_maybe: None,
+            base_url: OPEN_AI_BASE_URL.to_string(),
+            default_headers: vec![],
+            allow_http: false,
+            retry_policy: RetryPolicy::default(),
+            timeouts: Timeouts::default(),
+            rate_limiter_maybe: None,
+            generation_params: GenerationParams::default(),
+            answer_selector: AnswerSelector::default(),
+            json_repair_attempts: 0,
+            tokenizer: Tokenizer::default(),
+            context_window_check: ContextWindowCheck::default(),
+            cost_ledger_maybe: None,
+            model_registry: ModelRegistry::default(),
+            azure_config_maybe: None,
+        }
+    }
+}
+
+impl OpenAiClientBuilder {
+    /// model used for the requests, defaults to [`OpenAiModel::Gpt35_16k`]
+    pub fn model(mut self, model: OpenAiModel) -> Self {
+        self.model_maybe = Some(model);
+        self
+    }
+
+    /// bearer token, shorthand for a static [`Credentials`]. Defaults to
+    /// [`DotEnvCredentials`], `OPEN_AI_TOKEN` from the environment or `.env`.
+    pub fn token(self, token: impl Into<String>) -> Self {
+        self.credential_provider(Credentials::new(token))
+    }
+
+    /// where the API key and the organization and project headers come from,
+    /// asked before every attempt
+    pub fn credential_provider(
+        mut self,
+        credential_provider: impl CredentialProvider + 'static,
+    ) -> Self {
+        self.credential_provider_maybe = Some(Arc::new(credential_provider));
+        self
+    }
+
+    /// base URL the endpoint paths are appended to, e.g.
+    /// `https://api.openai.com/v1`, which is also the default
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// header sent with every request on top of the authorization and content
+    /// type headers
+    pub fn default_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.default_headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// allows `http://` base URLs, which are rejected by default so that a
+    /// token is not sent unencrypted by accident
+    pub fn allow_http(mut self, allow_http: bool) -> Self {
+        self.allow_http = allow_http;
+        self
+    }
+
+    /// how failed requests are retried, defaults to [`RetryPolicy::default`]
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// connect, read and overall timeouts, defaults to [`Timeouts::default`]
+    pub fn timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// waits for the RPM and TPM budget before every attempt, pass a clone to
+    /// share the budget with other clients
+    pub fn rate_limiter(mut self, rate_limiter: RateLimiter) -> Self {
+        self.rate_limiter_maybe = Some(rate_limiter);
+        self
+    }
+
+    /// sampling parameters of every request, which a request can override.
+    /// The temperature defaults to 0.01 except for reasoning models.
+    pub fn generation_params(mut self, generation_params: GenerationParams) -> Self {
+        self.generation_params = generation_params;
+        self
+    }
+
+    /// upper bound for the generated tokens of every request, shorthand for
+    /// the `max_tokens` of the [`GenerationParams`]
+    pub fn max_tokens(mut self, max_tokens: u32) -> Self {
+        self.generation_params.max_tokens = Some(max_tokens);
+        self
+    }
+
+    /// picks the answer of responses with several choices, defaults to
+    /// [`AnswerSelector::First`]
+    pub fn answer_selector(mut self, answer_selector: AnswerSelector) -> Self {
+        self.answer_selector = answer_selector;
+        self
+    }
+
+    /// how often an answer not matching the prompt's
+    /// [`crate::ResponseFormat`] is sent back to the model with the violation
+    /// to be corrected, defaults to 0
+    pub fn json_repair_attempts(mut self, json_repair_attempts: usize) -> Self {
+        self.json_repair_attempts = json_repair_attempts;
+        self
+    }
+
+    /// tokenizer for the context window check, defaults to an estimate, pass
+    /// a [`crate::BpeTokenizer`] with the encoding of the model for exact
+    /// counts
+    pub fn tokenizer(mut self, tokenizer: impl Into<Tokenizer>) -> Self {
+        self.tokenizer = tokenizer.into();
+        self
+    }
+
+    /// what to do with prompts that do not fit into the model's context
+    /// window, defaults to [`ContextWindowCheck::Warn`]
+    pub fn context_window_check(mut self, context_window_check: ContextWindowCheck) -> Self {
+        self.context_window_check = context_window_check;
+        self
+    }
+
+    /// records usage and cost of every successful request, pass a clone to
+    /// aggregate the spend of several clients
+    pub fn cost_ledger(mut self, cost_ledger: CostLedger) -> Self {
+        self.cost_ledger_maybe = Some(cost_ledger);
+        self
+    }
+
+    /// models the client knows the context window, pricing and features of,
+    /// defaults to [`ModelRegistry::default`]
+    pub fn model_registry(mut self, model_registry: ModelRegistry) -> Self {
+        self.model_registry = model_registry;
+        self
+    }
+
+    /// talks to Azure OpenAI, the base URL is then the endpoint of the
+    /// resource, e.g. `https://my-resource.openai.azure.com`
+    pub fn azure(mut self, azure_config: AzureConfig) -> Self {
+        self.azure_config_maybe = Some(azure_config);
+        self
+    }
+
+    pub fn build(self) -> Result<OpenAiClient, LlmError> {
+        let base_url = self.base_url.trim_end_matches('/').to_string();
+
+        let uri: hyper::Uri = base_url.parse().map_err(|error| {
+            LlmError::InvalidRequest(format!("invalid base url {base_url}: {error}"))
+        })?;
+
+        match uri.scheme_str() {
+            Some("https") => {}
+            Some("http") if self.allow_http => {}
+            Some("http") => {
+                return Err(LlmError::InvalidRequest(format!(
+                    "plain HTTP base url {base_url} requires allow_http(true)"
+                )))
+            }
+            _ => {
+                return Err(LlmError::InvalidRequest(format!(
+                    "base url {base_url} must start with http:// or https://"
+                )))
+            }
+        }
+
+        let mut default_headers = HeaderMap::new();
+        for (name, value) in self.default_headers {
+            let header_name = HeaderName::from_bytes(name.as_bytes()).map_err(|error| {
+                LlmError::InvalidRequest(format!("invalid header name {name}: {error}"))
+            })?;
+            let header_value = HeaderValue::from_str(&value).map_err(|error| {
+                LlmError::InvalidRequest(format!("invalid value for header {name}: {error}"))
+            })?;
+            default_headers.append(header_name, header_value);
+        }
+
+        let model = self.model_maybe.unwrap_or(OpenAiModel::Gpt35_16k);
+        let model_descriptor_maybe = self.model_registry.get(model.name());
+        self.generation_params
+            .validate(&GenerationLimits::OPEN_AI, model_descriptor_maybe)?;
+
+        if let (Tokenizer::Bpe(bpe_tokenizer), Some(model_descriptor)) =
+            (&self.tokenizer, model_descriptor_maybe)
+        {
+            if bpe_tokenizer.encoding() != model_descriptor.tokenizer_encoding {
+                return Err(LlmError::InvalidRequest(format!(
+                    "{} is tokenized with {:?}, not {:?}",
+                    model_descriptor.name,
+                    model_descriptor.tokenizer_encoding,
+                    bpe_tokenizer.encoding()
+                )));
+            }
+        }
+
+        if let Some(azure_config) = &self.azure_config_maybe {
+            if azure_config.deployment_for(&model).is_none() {
+                return Err(LlmError::InvalidRequest(format!(
+                    "no Azure deployment for {}",
+                    model.name()
+                )));
+            }
+        }
+
+        let credential_provider = self
+            .credential_provider_maybe
+            .unwrap_or_else(|| Arc::new(DotEnvCredentials::default()));
+
+        Ok(OpenAiClient {
+            model,
+            credential_provider,
+            base_url,
+            default_headers,
+            retry_policy: self.retry_policy,
+            timeouts: self.timeouts,
+            http_client: build_http_client(self.allow_http, &self.timeouts),
+            rate_limiter_maybe: self.rate_limiter_maybe,
+            generation_params: self.generation_params,
+            answer_selector: self.answer_selector,
+            json_repair_attempts: self.json_repair_attempts,
+            tokenizer: self.tokenizer,
+            context_window_check: self.context_window_check,
+            cost_ledger_maybe: self.cost_ledger_maybe,
+            tag_maybe: None,
+            model_registry: Arc::new(self.model_registry),
+            azure_config_maybe: self.azure_config_maybe,
+        })
+    }
+}
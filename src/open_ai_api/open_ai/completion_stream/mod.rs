@@ -0,0 +1,265 @@
+use super::OpenAiSimplifiedResponse;
+use crate::server_sent_events::{ServerSentEvent, ServerSentEventParser};
+use crate::{AnswerSelector, CostLedger, LlmError, ModelRegistry, TimeoutKind, Usage};
+use futures::{Future, Stream};
+use hyper::Body;
+use serde::Deserialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::time::{sleep, Sleep};
+
+/// `data:` payload that terminates an OpenAI stream
+const DONE_MARKER: &str = "[DONE]";
+
+/// a single `chat.completion.chunk` as sent with `stream: true`
+#[derive(Deserialize, Debug)]
+pub struct OpenAiCompletionChunk {
+    pub id: String,
+    pub object: String,
+    pub created: u64,
+    pub model: String,
+    pub choices: Vec<ChunkChoice>,
+
+    /// only set on the last chunk, which has no choices
+    pub usage: Option<Usage>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ChunkChoice {
+    pub index: u64,
+    pub delta: ChunkDelta,
+    pub finish_reason: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ChunkDelta {
+    pub role: Option<String>,
+    pub content: Option<String>,
+}
+
+/// the part of the answer of one choice that arrived with a chunk
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpenAiStreamDelta {
+    pub choice_index: u64,
+    pub role: Option<String>,
+    pub content: Option<String>,
+    pub finish_reason: Option<String>,
+}
+
+/// a streamed chat completion, yields the deltas as they arrive and keeps the
+/// aggregated answer around.
+///
+/// # Usage
+/// ```no_run
+/// use futures::StreamExt;
+///
+/// let mut completion_stream = open_ai_client.perform_request_stream(&prompt).await?;
+/// while let Some(delta) = completion_stream.next().await {
+///     print!("{}", delta?.content.unwrap_or_default());
+/// }
+/// let simplified_response = completion_stream.response();
+/// ```
+pub struct OpenAiCompletionStream {
+    body: Body,
+    parser: ServerSentEventParser,
+    pending_deltas: VecDeque<OpenAiStreamDelta>,
+    answers: BTreeMap<u64, String>,
+    model_name: Option<String>,
+    usage: Option<Usage>,
+    done: bool,
+
+    /// ledger and tag the usage is recorded to once the stream completed
+    cost_ledger_maybe: Option<(CostLedger, Option<String>)>,
+    model_registry: Arc<ModelRegistry>,
+    answer_selector: AnswerSelector,
+
+    /// how long to wait for the next piece of the body
+    read_timeout: Option<Duration>,
+    read_deadline: Option<Pin<Box<Sleep>>>,
+}
+
+impl OpenAiCompletionStream {
+    pub(crate) fn new(
+        body: Body,
+        read_timeout: Option<Duration>,
+        cost_ledger_maybe: Option<(CostLedger, Option<String>)>,
+        model_registry: Arc<ModelRegistry>,
+        answer_selector: AnswerSelector,
+    ) -> Self {
+        Self {
+            body,
+            parser: ServerSentEventParser::default(),
+            pending_deltas: VecDeque::new(),
+            answers: BTreeMap::new(),
+            model_name: None,
+            usage: None,
+            done: false,
+            cost_ledger_maybe,
+            model_registry,
+            answer_selector,
+            read_timeout,
+            read_deadline: None,
+        }
+    }
+
+    /// the answer aggregated from the deltas received so far, the usage is
+    /// only known once the stream completed
+    pub fn response(&self) -> OpenAiSimplifiedResponse {
+        let answers: Vec<String> = self.answers.values().cloned().collect();
+        OpenAiSimplifiedResponse {
+            answer: self.answer_selector.select(&answers).map(str::to_string),
+            answers,
+            follow_up_query: None,
+            usage: self.usage,
+            cost_usd: self.cost_usd(),
+            content_filter_results: vec![],
+        }
+    }
+
+    fn cost_usd(&self) -> Option<f64> {
+        self.model_registry
+            .cost_usd(self.model_name.as_deref()?, self.usage.as_ref()?)
+    }
+
+    /// records the usage once the terminator arrived
+    fn complete(&mut self) {
+        self.done = true;
+
+        if let (Some((cost_ledger, tag_maybe)), Some(usage), Some(model_name)) =
+            (self.cost_ledger_maybe.take(), &self.usage, &self.model_name)
+        {
+            cost_ledger.record(model_name, tag_maybe.as_deref(), usage, self.cost_usd());
+        }
+    }
+
+    /// reads the rest of the stream and returns the aggregated answer
+    pub async fn into_response(mut self) -> Result<OpenAiSimplifiedResponse, LlmError> {
+        use futures::StreamExt;
+
+        while let Some(delta_result) = self.next().await {
+            delta_result?;
+        }
+
+        if self.answers.is_empty() {
+            return Err(LlmError::EmptyChoices);
+        }
+
+        Ok(self.response())
+    }
+
+    /// handles a completed event, returns true once the terminator arrived
+    fn process_event(&mut self, event: ServerSentEvent) -> Result<bool, LlmError> {
+        let data = event.data.trim();
+        if data == DONE_MARKER {
+            return Ok(true);
+        }
+
+        let chunk: OpenAiCompletionChunk = serde_json::from_str(data).map_err(|error| {
+            LlmError::provider_error_from_body(200, data.as_bytes()).unwrap_or_else(|| {
+                LlmError::Decode(format!("invalid stream chunk {data}: {error}"))
+            })
+        })?;
+
+        self.model_name = Some(chunk.model);
+        if chunk.usage.is_some() {
+            self.usage = chunk.usage;
+        }
+
+        for choice in chunk.choices {
+            if let Some(content) = &choice.delta.content {
+                self.answers
+                    .entry(choice.index)
+                    .or_default()
+                    .push_str(content);
+            } else {
+                self.answers.entry(choice.index).or_default();
+            }
+
+            self.pending_deltas.push_back(OpenAiStreamDelta {
+                choice_index: choice.index,
+                role: choice.delta.role,
+                content: choice.delta.content,
+                finish_reason: choice.finish_reason,
+            });
+        }
+
+        Ok(false)
+    }
+}
+
+impl Stream for OpenAiCompletionStream {
+    type Item = Result<OpenAiStreamDelta, LlmError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            if let Some(delta) = self.pending_deltas.pop_front() {
+                return Poll::Ready(Some(Ok(delta)));
+            }
+
+            if self.done {
+                return Poll::Ready(None);
+            }
+
+            let body_poll = Pin::new(&mut self.body).poll_next(cx);
+            if !body_poll.is_pending() {
+                self.read_deadline = None;
+            }
+
+            match body_poll {
+                Poll::Pending => {
+                    if let Some(read_timeout) = self.read_timeout {
+                        let read_deadline = self
+                            .read_deadline
+                            .get_or_insert_with(|| Box::pin(sleep(read_timeout)));
+                        if read_deadline.as_mut().poll(cx).is_ready() {
+                            self.done = true;
+                            return Poll::Ready(Some(Err(LlmError::Timeout(TimeoutKind::Read))));
+                        }
+                    }
+                    return Poll::Pending;
+                }
+                Poll::Ready(Some(Err(error))) => {
+                    self.done = true;
+                    return Poll::Ready(Some(Err(error.into())));
+                }
+                Poll::Ready(Some(Ok(bytes))) => {
+                    let events = self.parser.feed(&bytes);
+                    for event in events {
+                        match self.process_event(event) {
+                            Ok(false) => {}
+                            Ok(true) => {
+                                self.complete();
+                                break;
+                            }
+                            Err(error) => {
+                                self.done = true;
+                                return Poll::Ready(Some(Err(error)));
+                            }
+                        }
+                    }
+                }
+                Poll::Ready(None) => {
+                    self.done = true;
+                    let terminated = match self.parser.finish() {
+                        Some(event) => match self.process_event(event) {
+                            Ok(terminated) => terminated,
+                            Err(error) => return Poll::Ready(Some(Err(error))),
+                        },
+                        None => false,
+                    };
+
+                    if terminated {
+                        self.complete();
+                    } else {
+                        return Poll::Ready(Some(Err(LlmError::Transport(format!(
+                            "stream ended before the {DONE_MARKER} terminator"
+                        )))));
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use super::{OpenAiClient, OpenAiSimplifiedResponse};
+use crate::{ChatResponse, ChatStream, LlmClient, LlmError, PromptType};
+use async_trait::async_trait;
+use futures::StreamExt;
+
+impl From<OpenAiSimplifiedResponse> for ChatResponse {
+    fn from(simplified_response: OpenAiSimplifiedResponse) -> Self {
+        Self {
+            answer: simplified_response.answer,
+            answers: simplified_response.answers,
+            usage: simplified_response.usage,
+            cost_usd: simplified_response.cost_usd,
+            content_filter_results: simplified_response.content_filter_results,
+            tokens: vec![],
+        }
+    }
+}
+
+#[async_trait]
+impl LlmClient for OpenAiClient {
+    fn model_name(&self) -> &str {
+        self.model.name()
+    }
+
+    async fn chat(&self, prompt: &PromptType) -> Result<ChatResponse, LlmError> {
+        Ok(self.perform_request(prompt).await?.into())
+    }
+
+    async fn chat_stream(&self, prompt: &PromptType) -> Result<ChatStream, LlmError> {
+        let completion_stream = self.perform_request_stream(prompt).await?;
+
+        let text_stream = completion_stream.filter_map(|delta_result| async move {
+            match delta_result {
+                Ok(delta) if delta.choice_index == 0 => {
+                    delta.content.filter(|content| !content.is_empty()).map(Ok)
+                }
+                Ok(_) => None,
+                Err(error) => Some(Err(error)),
+            }
+        });
+        Ok(text_stream.boxed())
+    }
+
+    fn count_tokens(&self, prompt: &PromptType) -> usize {
+        let messages = prompt.messages();
+        self.tokenizer.count_chat_tokens(&messages)
+    }
+}
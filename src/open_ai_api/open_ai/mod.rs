@@ -1,17 +1,45 @@
-use crate::PromptType;
-use dotenv::dotenv;
-use hyper::body::to_bytes;
-use hyper::{Body, Client, Method, Request};
-use hyper_tls::HttpsConnector;
-use lazy_static::lazy_static;
+mod azure;
+mod client_builder;
+mod completion_stream;
+mod llm_client;
+mod model_list;
+mod structured_output;
+mod text_completion;
+mod tool_loop;
+
+pub use azure::AzureConfig;
+pub use client_builder::OpenAiClientBuilder;
+pub use completion_stream::{OpenAiCompletionChunk, OpenAiCompletionStream, OpenAiStreamDelta};
+pub use model_list::OpenAiModelEntry;
+pub use text_completion::{
+    OpenAiLogprobs, OpenAiTextChoice, OpenAiTextCompletionOptions, OpenAiTextCompletionResponseBody,
+};
+
+use crate::content_filter::{unfiltered_choices, AnnotatedChoice};
+use crate::http_client::{build_http_client, HttpClient};
+use crate::rate_limiter::estimate_request_tokens;
+use crate::retry_policy::send_with_retries;
+use crate::timeouts::{read_body, with_timeout};
+use crate::usage::record_usage;
+use crate::{
+    AnswerSelector, ChatMessage, ContentFilterResult, ContextWindowCheck, Conversation, CostLedger,
+    CredentialProvider, Credentials, DotEnvCredentials, GenerationLimits, GenerationParams,
+    LlmError, ModelDescriptor, ModelRegistry, PromptType, RateLimiter, ResponseFormat, RetryPolicy,
+    TimeoutKind, Timeouts, Tokenizer, ToolDefinition, Usage,
+};
+use hyper::header::HeaderMap;
+use hyper::{Body, Method, Request, Response};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use std::env::var;
+use std::future::Future;
+use std::sync::Arc;
 
-// OpenAI token from .env, panics if not found
-lazy_static! {
-    static ref OPEN_AI_TOKEN: String =
-        var("OPEN_AI_TOKEN").expect("could not find OPEN_AI_TOKEN environment variable");
-}
+/// default base URL, endpoint paths such as `/chat/completions` are appended
+const OPEN_AI_BASE_URL: &str = "https://api.openai.com/v1";
+
+/// temperature of the requests that do not set one, reasoning models only
+/// accept their default and get none
+const DEFAULT_TEMPERATURE: f32 = 0.01;
 
 #[derive(Deserialize, Debug)]
 pub struct OpenAiCompletionsResponseBody {
@@ -20,50 +48,144 @@ pub struct OpenAiCompletionsResponseBody {
     pub created: u64,
     pub model: String,
     pub choices: Vec<Choice>,
+    pub usage: Option<Usage>,
 }
 
 #[derive(Deserialize, Debug)]
 pub struct OpenAiSimplifiedResponse {
+    /// the answer picked by the client's [`AnswerSelector`], the first one
+    /// by default
     pub answer: Option<String>,
+
+    /// the answers of all choices in the order of their index
+    #[serde(default)]
+    pub answers: Vec<String>,
+
     pub follow_up_query: Option<String>,
+
+    /// tokens billed for the request, if the server reported them
+    pub usage: Option<Usage>,
+
+    /// USD, if the usage was reported and the pricing of the model is known
+    /// to the client's [`ModelRegistry`]
+    pub cost_usd: Option<f64>,
+
+    /// categories Azure's content filter flagged in the returned answers
+    /// without withholding them
+    #[serde(skip)]
+    pub content_filter_results: Vec<ContentFilterResult>,
 }
 
 impl TryFrom<OpenAiCompletionsResponseBody> for OpenAiSimplifiedResponse {
-    type Error = String;
-    fn try_from(value: OpenAiCompletionsResponseBody) -> Result<Self, Self::Error> {
-        let message_content = &value
-            .choices
-            .first()
-            .ok_or_else(|| "failed to get the choice from response".to_string())?
-            .message
-            .content;
+    type Error = LlmError;
+    fn try_from(mut value: OpenAiCompletionsResponseBody) -> Result<Self, Self::Error> {
+        if value.choices.is_empty() {
+            return Err(LlmError::EmptyChoices);
+        }
+        value.choices.sort_by_key(|choice| choice.index);
+
+        let (choices, content_filter_results) = unfiltered_choices(value.choices)?;
+        let answers: Vec<String> = choices
+            .into_iter()
+            .map(|choice| choice.message.content.unwrap_or_default())
+            .collect();
 
+        // the cost depends on the model registry and the selected answer on
+        // the client's selector, both are filled in by the client
         Ok(Self {
-            answer: Some(message_content.clone()),
+            answer: answers.first().cloned(),
+            answers,
             follow_up_query: None,
+            usage: value.usage,
+            cost_usd: None,
+            content_filter_results,
         })
     }
 }
 
+impl OpenAiSimplifiedResponse {
+    /// the answer the selector picks among all choices
+    pub fn select(&self, answer_selector: &AnswerSelector) -> Option<&str> {
+        answer_selector.select(&self.answers)
+    }
+
+    /// the answer deserialized from JSON, e.g. of a prompt with a
+    /// [`ResponseFormat`]
+    pub fn answer_json<T: DeserializeOwned>(&self) -> Result<T, LlmError> {
+        let answer = self.answer.as_deref().ok_or(LlmError::EmptyChoices)?;
+        Ok(serde_json::from_str(answer)?)
+    }
+}
+
 #[derive(Deserialize, Debug)]
 pub struct Choice {
     pub index: u64,
-    pub message: Message,
+    pub message: ChatMessage,
+
+    /// `stop`, `length`, `content_filter` or `tool_calls`
+    #[serde(default)]
+    pub finish_reason: Option<String>,
+
+    /// Azure's annotations of the answer per category, see
+    /// [`crate::ContentFilterResult`]
+    #[serde(default)]
+    pub content_filter_results: Option<serde_json::Value>,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
-pub struct Message {
-    pub role: String,
-    pub content: String,
+impl AnnotatedChoice for Choice {
+    fn finish_reason(&self) -> Option<&str> {
+        self.finish_reason.as_deref()
+    }
+
+    fn content_filter_results(&self) -> Option<&serde_json::Value> {
+        self.content_filter_results.as_ref()
+    }
 }
 
-#[derive(Debug, Clone, Copy)]
+/// the model a request is sent to, its context window, pricing and features
+/// are looked up in the client's [`ModelRegistry`]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum OpenAiModel {
+    /// https://platform.openai.com/docs/models/gpt-3-5
+    Gpt35Turbo,
+
     /// https://platform.openai.com/docs/models/gpt-3-5
     Gpt35_16k,
 
+    /// https://platform.openai.com/docs/models/gpt-3-5-turbo-instruct, a
+    /// completion model for [`OpenAiClient::perform_text_completion`]
+    Gpt35TurboInstruct,
+
+    /// https://platform.openai.com/docs/models/gpt-4
+    Gpt40,
+
     /// https://platform.openai.com/docs/models/gpt-4
     Gpt40_32k,
+
+    /// https://platform.openai.com/docs/models/gpt-4-turbo
+    Gpt4Turbo,
+
+    /// https://platform.openai.com/docs/models/gpt-4o
+    Gpt4o,
+
+    /// https://platform.openai.com/docs/models/gpt-4o-mini
+    Gpt4oMini,
+
+    /// https://platform.openai.com/docs/models/gpt-4.1
+    Gpt41,
+
+    /// https://platform.openai.com/docs/models/gpt-4.1-mini
+    Gpt41Mini,
+
+    /// https://platform.openai.com/docs/models/o1
+    O1,
+
+    /// https://platform.openai.com/docs/models/o3-mini
+    O3Mini,
+
+    /// any other model by the name the API expects, e.g. a fine-tuned model
+    /// or one served by an OpenAI compatible server
+    Custom(String),
 }
 
 impl Serialize for OpenAiModel {
@@ -71,17 +193,27 @@ impl Serialize for OpenAiModel {
     where
         S: serde::Serializer,
     {
-        serializer.serialize_str(self.value().as_str())
+        serializer.serialize_str(self.name())
     }
 }
 
 impl OpenAiModel {
-    fn value(&self) -> String {
+    /// name of the model as the API expects it
+    pub fn name(&self) -> &str {
         match self {
-            Self::Gpt35_16k => "gpt-3.5-turbo-16k".to_string(),
-            Self::Gpt40_32k => {
-                panic!("gpt 4.0 is not available yet through the API even thought it was announced in the first week of July")
-            }
+            Self::Gpt35Turbo => "gpt-3.5-turbo",
+            Self::Gpt35_16k => "gpt-3.5-turbo-16k",
+            Self::Gpt35TurboInstruct => "gpt-3.5-turbo-instruct",
+            Self::Gpt40 => "gpt-4",
+            Self::Gpt40_32k => "gpt-4-32k",
+            Self::Gpt4Turbo => "gpt-4-turbo",
+            Self::Gpt4o => "gpt-4o",
+            Self::Gpt4oMini => "gpt-4o-mini",
+            Self::Gpt41 => "gpt-4.1",
+            Self::Gpt41Mini => "gpt-4.1-mini",
+            Self::O1 => "o1",
+            Self::O3Mini => "o3-mini",
+            Self::Custom(name) => name,
         }
     }
 }
@@ -92,28 +224,79 @@ struct Prompt {
     model: OpenAiModel,
 
     /// structure holding our query
-    messages: Vec<Message>,
+    messages: Vec<ChatMessage>,
+
+    /// sampling parameters, the unset ones are left out
+    #[serde(flatten)]
+    params: GenerationParams,
+
+    /// `max_tokens` as reasoning models expect it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_completion_tokens: Option<u32>,
+
+    /// the functions the model may call
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<Vec<ToolDefinition>>,
 
-    /// verbosity of the answer, 0.1 is dry, 1 is very chatty
-    temperature: f32,
+    /// JSON mode or structured outputs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<ResponseFormat>,
+
+    /// asks for server-sent events instead of a single body
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_options: Option<StreamOptions>,
 }
 
-/// panics if the OPEN_AI_TOKEN is not in the .env file
-fn read_open_ai_token_from_dot_env_file() -> &'static str {
-    // read .env file to env vars
-    dotenv().ok();
-    &OPEN_AI_TOKEN
+impl Prompt {
+    /// upper bound for the generated tokens of a single choice
+    fn max_tokens(&self) -> Option<u32> {
+        self.params.max_tokens.or(self.max_completion_tokens)
+    }
+
+    /// the tokens the request counts against the TPM limit
+    fn estimate_tokens(&self, tokenizer: &Tokenizer, serialized_prompt: &str) -> u32 {
+        let completion_tokens_maybe = self
+            .max_tokens()
+            .map(|max_tokens| max_tokens * self.params.n.unwrap_or(1));
+        estimate_request_tokens(tokenizer, serialized_prompt, completion_tokens_maybe)
+    }
 }
 
-pub struct OpenAiClient<'a> {
+#[derive(Serialize, Debug)]
+struct StreamOptions {
+    /// sends a last chunk without choices that carries the usage
+    include_usage: bool,
+}
+
+/// cheap to clone, clones share the connection pool
+#[derive(Clone)]
+pub struct OpenAiClient {
     model: OpenAiModel,
-    token: &'a str,
+    credential_provider: Arc<dyn CredentialProvider>,
+    base_url: String,
+    default_headers: HeaderMap,
+    retry_policy: RetryPolicy,
+    timeouts: Timeouts,
+    http_client: HttpClient,
+    rate_limiter_maybe: Option<RateLimiter>,
+    generation_params: GenerationParams,
+    answer_selector: AnswerSelector,
+    json_repair_attempts: usize,
+    tokenizer: Tokenizer,
+    context_window_check: ContextWindowCheck,
+    cost_ledger_maybe: Option<CostLedger>,
+    tag_maybe: Option<String>,
+    model_registry: Arc<ModelRegistry>,
+    azure_config_maybe: Option<AzureConfig>,
 }
 
-impl<'a> OpenAiClient<'a> {
+impl OpenAiClient {
     pub fn new(
         model_override_maybe: Option<OpenAiModel>,
-        token_override_maybe: Option<&'a str>,
+        token_override_maybe: Option<&str>,
     ) -> Self {
         // default or override model
         let model = if let Some(model_override) = model_override_maybe {
@@ -122,79 +305,472 @@ impl<'a> OpenAiClient<'a> {
             OpenAiModel::Gpt35_16k
         };
 
-        // default or override token
-        let token = if let Some(token_override) = token_override_maybe {
-            token_override
-        } else {
-            // read .env file to env vars
-            read_open_ai_token_from_dot_env_file()
+        // default or override token, the default is read from `.env` or the
+        // environment when a request is sent
+        let credential_provider: Arc<dyn CredentialProvider> = match token_override_maybe {
+            Some(token_override) => Arc::new(Credentials::new(token_override)),
+            None => Arc::new(DotEnvCredentials::default()),
         };
 
-        Self { model, token }
+        Self {
+            model,
+            credential_provider,
+            base_url: OPEN_AI_BASE_URL.to_string(),
+            default_headers: HeaderMap::new(),
+            retry_policy: RetryPolicy::default(),
+            timeouts: Timeouts::default(),
+            http_client: build_http_client(false, &Timeouts::default()),
+            rate_limiter_maybe: None,
+            generation_params: GenerationParams::default(),
+            answer_selector: AnswerSelector::default(),
+            json_repair_attempts: 0,
+            tokenizer: Tokenizer::default(),
+            context_window_check: ContextWindowCheck::default(),
+            cost_ledger_maybe: None,
+            tag_maybe: None,
+            model_registry: Arc::new(ModelRegistry::default()),
+            azure_config_maybe: None,
+        }
+    }
+
+    /// for anything beyond the model and the token, e.g. pointing the client
+    /// to an OpenAI compatible server
+    pub fn builder() -> OpenAiClientBuilder {
+        OpenAiClientBuilder::default()
     }
 
     // TODO: rename to reflect the fact that this creates a Model specific prompt
     pub async fn perform_request(
         &self,
         prompt: &PromptType,
-    ) -> Result<OpenAiSimplifiedResponse, String> {
-        let prompt = self.generate_prompt(&prompt.prompt());
+    ) -> Result<OpenAiSimplifiedResponse, LlmError> {
+        self.perform_request_with_params(prompt, &GenerationParams::default())
+            .await
+    }
+
+    /// same as [`Self::perform_request`] with the parameters set in `params`
+    /// overriding the client's [`GenerationParams`]
+    pub async fn perform_request_with_params(
+        &self,
+        prompt: &PromptType,
+        params: &GenerationParams,
+    ) -> Result<OpenAiSimplifiedResponse, LlmError> {
+        let prompt = self.build_prompt(prompt, params)?;
+
+        if let Some(response_format) = prompt.response_format.clone() {
+            return self
+                .perform_structured_request(prompt, self.json_repair_attempts, |answer| {
+                    response_format.validate(answer).map(|_| ())
+                })
+                .await;
+        }
+
+        self.complete(&prompt).await
+    }
+
+    /// sends the conversation and appends the selected answer to it as an
+    /// assistant message, ready for the next user message
+    pub async fn perform_conversation_request(
+        &self,
+        conversation: &mut Conversation,
+    ) -> Result<OpenAiSimplifiedResponse, LlmError> {
+        let prompt = PromptType::new_conversation(conversation.clone());
+        let simplified_response = self.perform_request(&prompt).await?;
+
+        if let Some(answer) = &simplified_response.answer {
+            conversation.push(ChatMessage::assistant(answer.clone()));
+        }
+
+        Ok(simplified_response)
+    }
+
+    /// sends the request and returns the simplified response
+    async fn complete(&self, prompt: &Prompt) -> Result<OpenAiSimplifiedResponse, LlmError> {
+        self.check_context_window(prompt)?;
+        let serialized_prompt = serialize_prompt(prompt)?;
+        let estimated_tokens = prompt.estimate_tokens(&self.tokenizer, &serialized_prompt);
 
         // call OpenAI
         let open_ai_completions_response_body = self
-            .call_open_ai(prompt)
-            .await
-            .map_err(|error| format!("error while calling OpenAI: {error}"))?;
-
-        let simplified_response: OpenAiSimplifiedResponse =
-            open_ai_completions_response_body.try_into()?;
+            .post_chat_completions(serialized_prompt, estimated_tokens)
+            .await?;
 
         // return body, which contains the response to our prompt with a few
         // other things
+        self.simplify_response(open_ai_completions_response_body)
+    }
+
+    /// the simplified response with the cost and the selected answer filled
+    /// in, its usage is recorded to the ledger
+    fn simplify_response(
+        &self,
+        open_ai_completions_response_body: OpenAiCompletionsResponseBody,
+    ) -> Result<OpenAiSimplifiedResponse, LlmError> {
+        let model_name = open_ai_completions_response_body.model.clone();
+
+        let mut simplified_response: OpenAiSimplifiedResponse =
+            open_ai_completions_response_body.try_into()?;
+        simplified_response.cost_usd =
+            self.record_usage(&model_name, simplified_response.usage.as_ref());
+        simplified_response.answer = simplified_response
+            .select(&self.answer_selector)
+            .map(str::to_string);
+
         Ok(simplified_response)
     }
 
+    /// records the usage of a response to the ledger, returns its cost
+    fn record_usage(&self, model_name: &str, usage_maybe: Option<&Usage>) -> Option<f64> {
+        record_usage(
+            &self.model_registry,
+            self.cost_ledger_maybe.as_ref(),
+            self.tag_maybe.as_deref(),
+            model_name,
+            usage_maybe,
+        )
+    }
+
+    /// same as [`Self::perform_request`] but returns the answer as it is being
+    /// generated, see [`OpenAiCompletionStream`]
+    pub async fn perform_request_stream(
+        &self,
+        prompt: &PromptType,
+    ) -> Result<OpenAiCompletionStream, LlmError> {
+        self.perform_request_stream_with_params(prompt, &GenerationParams::default())
+            .await
+    }
+
+    /// same as [`Self::perform_request_stream`] with the parameters set in
+    /// `params` overriding the client's [`GenerationParams`]
+    pub async fn perform_request_stream_with_params(
+        &self,
+        prompt: &PromptType,
+        params: &GenerationParams,
+    ) -> Result<OpenAiCompletionStream, LlmError> {
+        let mut prompt = self.build_prompt(prompt, params)?;
+        self.check_context_window(&prompt)?;
+        prompt.stream = true;
+        prompt.stream_options = Some(StreamOptions {
+            include_usage: true,
+        });
+        let serialized_prompt = serialize_prompt(&prompt)?;
+        let estimated_tokens = prompt.estimate_tokens(&self.tokenizer, &serialized_prompt);
+
+        // the body is handed to the stream unread, so the overall timeout only
+        // covers the time until the response headers arrived
+        let read_timeout = self.timeouts.read;
+        let body_maybe = Some(serialized_prompt);
+        self.send_with_retries("/chat/completions", body_maybe, estimated_tokens, |resp| {
+            let cost_ledger_maybe = self
+                .cost_ledger_maybe
+                .clone()
+                .map(|cost_ledger| (cost_ledger, self.tag_maybe.clone()));
+            let model_registry = self.model_registry.clone();
+            let answer_selector = self.answer_selector.clone();
+            async move {
+                Ok(OpenAiCompletionStream::new(
+                    resp.into_body(),
+                    read_timeout,
+                    cost_ledger_maybe,
+                    model_registry,
+                    answer_selector,
+                ))
+            }
+        })
+        .await
+    }
+
+    /// a clone whose requests are additionally recorded under the tag in the
+    /// [`CostLedger`], the connection pool and the ledger are shared
+    pub fn with_tag(&self, tag: impl Into<String>) -> Self {
+        Self {
+            tag_maybe: Some(tag.into()),
+            ..self.clone()
+        }
+    }
+
+    /// tokenizer used for the context window check
+    pub fn tokenizer(&self) -> &Tokenizer {
+        &self.tokenizer
+    }
+
+    pub fn model(&self) -> &OpenAiModel {
+        &self.model
+    }
+
+    /// defaults of every request, see [`Self::perform_request_with_params`]
+    pub fn generation_params(&self) -> &GenerationParams {
+        &self.generation_params
+    }
+
+    /// picks the answer of responses with several choices
+    pub fn answer_selector(&self) -> &AnswerSelector {
+        &self.answer_selector
+    }
+
+    /// a clone picking answers with another selector, the connection pool is
+    /// shared
+    pub fn with_answer_selector(&self, answer_selector: AnswerSelector) -> Self {
+        Self {
+            answer_selector,
+            ..self.clone()
+        }
+    }
+
+    /// models known to this client
+    pub fn model_registry(&self) -> &ModelRegistry {
+        &self.model_registry
+    }
+
+    /// the descriptor of the client's model, `None` for models that are not
+    /// in the registry
+    pub fn model_descriptor(&self) -> Option<&ModelDescriptor> {
+        self.model_registry.get(self.model.name())
+    }
+
+    /// pre-flight check that the prompt and the requested completion tokens
+    /// fit into the model's context window
+    fn check_context_window(&self, prompt: &Prompt) -> Result<(), LlmError> {
+        if self.context_window_check == ContextWindowCheck::Off {
+            return Ok(());
+        }
+
+        let prompt_tokens = self.tokenizer.count_chat_tokens(&prompt.messages);
+        self.check_prompt_tokens(prompt_tokens, prompt.max_tokens())
+    }
+
+    /// the context window check for a prompt of `prompt_tokens` tokens,
+    /// whichever endpoint it goes to
+    fn check_prompt_tokens(
+        &self,
+        prompt_tokens: usize,
+        max_tokens_maybe: Option<u32>,
+    ) -> Result<(), LlmError> {
+        // without a descriptor there is nothing to check against
+        let Some(model_descriptor) = self.model_descriptor() else {
+            return Ok(());
+        };
+
+        let max_tokens = max_tokens_maybe.unwrap_or(0) as usize;
+        let context_window = model_descriptor.context_window;
+
+        if prompt_tokens + max_tokens <= context_window {
+            return Ok(());
+        }
+
+        let error = LlmError::ContextWindowExceeded {
+            model: self.model.name().to_string(),
+            prompt_tokens,
+            max_tokens,
+            context_window,
+        };
+        match self.context_window_check {
+            ContextWindowCheck::Reject => Err(error),
+            _ => {
+                log::warn!("{error}");
+                Ok(())
+            }
+        }
+    }
+
     /// part of the high level flow, performing the actual call
     pub async fn call_open_ai(
         &self,
         prompt: String,
-    ) -> Result<OpenAiCompletionsResponseBody, String> {
-        let https_builder = HttpsConnector::new();
-        let client = Client::builder().build(https_builder);
+    ) -> Result<OpenAiCompletionsResponseBody, LlmError> {
+        let params = &self.generation_params;
+        let completion_tokens_maybe = params
+            .max_tokens
+            .map(|max_tokens| max_tokens * params.n.unwrap_or(1));
+        let estimated_tokens =
+            estimate_request_tokens(&self.tokenizer, &prompt, completion_tokens_maybe);
+
+        self.post_chat_completions(prompt, estimated_tokens).await
+    }
+
+    async fn post_chat_completions(
+        &self,
+        prompt: String,
+        estimated_tokens: u32,
+    ) -> Result<OpenAiCompletionsResponseBody, LlmError> {
+        let read_timeout = self.timeouts.read;
+        let body_maybe = Some(prompt);
+        self.send_with_retries(
+            "/chat/completions",
+            body_maybe,
+            estimated_tokens,
+            |mut resp| async move {
+                let body = read_body(resp.body_mut(), read_timeout).await?;
+
+                let parsed_body: OpenAiCompletionsResponseBody = serde_json::from_slice(&body)?;
+
+                Ok(parsed_body)
+            },
+        )
+        .await
+    }
 
-        let token = &self.token;
+    /// posts the body to the endpoint, or gets it when there is no body,
+    /// retrying according to the retry policy until an attempt gets a
+    /// successful response that `handle_success` is able to turn into the
+    /// result. Every attempt takes `estimated_tokens` from the TPM budget.
+    async fn send_with_retries<T, F, Fut>(
+        &self,
+        endpoint_path: &str,
+        body_maybe: Option<String>,
+        estimated_tokens: u32,
+        handle_success: F,
+    ) -> Result<T, LlmError>
+    where
+        F: Fn(Response<Body>) -> Fut,
+        Fut: Future<Output = Result<T, LlmError>>,
+    {
+        send_with_retries(
+            &self.retry_policy,
+            &self.timeouts,
+            self.rate_limiter_maybe.as_ref(),
+            estimated_tokens,
+            || self.send(endpoint_path, body_maybe.clone()),
+            handle_success,
+        )
+        .await
+    }
 
-        let request = Request::builder()
-            .uri("https://api.openai.com/v1/chat/completions")
-            .method(Method::POST)
-            .header("content-type", "application/json")
-            .header("Authorization", format!("Bearer {token}"))
-            .body(Body::from(prompt))
-            .map_err(|error| error.to_string())?;
+    /// a single attempt of posting the body to the endpoint, returns once the
+    /// response headers arrived
+    async fn send(
+        &self,
+        endpoint_path: &str,
+        body_maybe: Option<String>,
+    ) -> Result<Response<Body>, LlmError> {
+        let credentials = self.credential_provider.credentials()?;
+        let base_url = &self.base_url;
+
+        let url = match &self.azure_config_maybe {
+            Some(azure_config) => {
+                azure_config.endpoint_url(base_url, endpoint_path, &self.model)?
+            }
+            None => format!("{base_url}{endpoint_path}"),
+        };
 
-        let mut resp = client.request(request).await.unwrap();
-        let body = to_bytes(resp.body_mut()).await.unwrap();
+        let mut request_builder = Request::builder().uri(url);
+        for (name, value) in &self.default_headers {
+            request_builder = request_builder.header(name, value);
+        }
+        if self.azure_config_maybe.is_some() {
+            request_builder = request_builder.header("api-key", &credentials.api_key);
+        } else {
+            request_builder =
+                request_builder.header("Authorization", format!("Bearer {}", credentials.api_key));
+            if let Some(organization) = &credentials.organization_maybe {
+                request_builder = request_builder.header("OpenAI-Organization", organization);
+            }
+            if let Some(project) = &credentials.project_maybe {
+                request_builder = request_builder.header("OpenAI-Project", project);
+            }
+        }
 
-        let parsed_body: OpenAiCompletionsResponseBody =
-            serde_json::from_str(std::str::from_utf8(body.as_ref()).unwrap()).unwrap();
+        let request = match body_maybe {
+            Some(body) => request_builder
+                .method(Method::POST)
+                .header("content-type", "application/json")
+                .body(Body::from(body))?,
+            None => request_builder.method(Method::GET).body(Body::empty())?,
+        };
 
-        Ok(parsed_body)
+        with_timeout(self.timeouts.read, TimeoutKind::Read, async {
+            Ok(self.http_client.request(request).await?)
+        })
+        .await
     }
 
     // TODO: rename to reflect the fact that this creates a Model specific prompt
     /// returns a ready prompt request that can be posted to OpenAI's API
-    pub fn generate_prompt(&self, prompt: &str) -> String {
-        let escaped_query = prompt.replace('\n', "\\n");
-
-        let prompt = Prompt {
-            messages: vec![Message {
-                content: escaped_query,
-                role: "user".to_string(),
-            }],
-            model: self.model,
-            temperature: 0.01,
+    pub fn generate_prompt(&self, prompt: &str) -> Result<String, LlmError> {
+        let prompt = PromptType::new_zero_shot_prompt(prompt.to_string());
+        serialize_prompt(&self.build_prompt(&prompt, &GenerationParams::default())?)
+    }
+
+    /// the request for the prompt with the client's parameters overridden by
+    /// `params`, validated against the model's capabilities
+    fn build_prompt(
+        &self,
+        prompt: &PromptType,
+        params: &GenerationParams,
+    ) -> Result<Prompt, LlmError> {
+        let mut chat_prompt = self.build_chat_prompt(prompt.messages(), params)?;
+        chat_prompt.response_format = prompt.response_format().cloned();
+        self.check_response_format(&chat_prompt)?;
+
+        Ok(chat_prompt)
+    }
+
+    /// models in the registry need JSON mode for a response format
+    fn check_response_format(&self, prompt: &Prompt) -> Result<(), LlmError> {
+        if let (Some(_), Some(model_descriptor)) =
+            (&prompt.response_format, self.model_descriptor())
+        {
+            if !model_descriptor.features.json_mode {
+                return Err(LlmError::InvalidRequest(format!(
+                    "{} does not support response_format",
+                    model_descriptor.name
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// the request for a conversation, see [`Self::build_prompt`]
+    fn build_chat_prompt(
+        &self,
+        messages: Vec<ChatMessage>,
+        params: &GenerationParams,
+    ) -> Result<Prompt, LlmError> {
+        let model_descriptor_maybe = self.model_descriptor();
+        let mut params = self.generation_params.merge(params);
+        params.validate(&GenerationLimits::OPEN_AI, model_descriptor_maybe)?;
+
+        let is_reasoning_model =
+            model_descriptor_maybe.is_some_and(|descriptor| descriptor.features.reasoning);
+        let max_completion_tokens = if is_reasoning_model {
+            params.max_tokens.take()
+        } else {
+            params.temperature.get_or_insert(DEFAULT_TEMPERATURE);
+            None
         };
 
-        serde_json::to_string(&prompt).unwrap()
+        Ok(Prompt {
+            messages,
+            model: self.model.clone(),
+            params,
+            max_completion_tokens,
+            tools: None,
+            response_format: None,
+            stream: false,
+            stream_options: None,
+        })
+    }
+}
+
+/// the usage of several requests, e.g. the steps of a tool loop
+fn sum_usage(total_maybe: Option<Usage>, usage_maybe: Option<Usage>) -> Option<Usage> {
+    match (total_maybe, usage_maybe) {
+        (Some(mut total), Some(usage)) => {
+            total += usage;
+            Some(total)
+        }
+        (total_maybe, usage_maybe) => total_maybe.or(usage_maybe),
     }
 }
+
+/// the cost of several requests, `None` once the cost of one is unknown
+fn sum_cost_usd(total_maybe: Option<f64>, cost_usd_maybe: Option<f64>) -> Option<f64> {
+    total_maybe
+        .zip(cost_usd_maybe)
+        .map(|(total, cost_usd)| total + cost_usd)
+}
+
+fn serialize_prompt(prompt: &Prompt) -> Result<String, LlmError> {
+    serde_json::to_string(prompt).map_err(|error| LlmError::InvalidRequest(error.to_string()))
+}
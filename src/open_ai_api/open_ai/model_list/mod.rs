@@ -0,0 +1,48 @@
+use super::OpenAiClient;
+use crate::timeouts::read_body;
+use crate::{LlmError, ModelRegistry};
+use serde::Deserialize;
+use std::sync::Arc;
+
+/// a model as listed by `/v1/models`
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct OpenAiModelEntry {
+    pub id: String,
+    pub object: String,
+    pub created: u64,
+    pub owned_by: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct OpenAiModelListResponseBody {
+    data: Vec<OpenAiModelEntry>,
+}
+
+impl OpenAiClient {
+    /// the models available to the token, sorted by id
+    pub async fn list_models(&self) -> Result<Vec<OpenAiModelEntry>, LlmError> {
+        let read_timeout = self.timeouts.read;
+        let mut models = self
+            .send_with_retries("/models", None, 0, |mut resp| async move {
+                let body = read_body(resp.body_mut(), read_timeout).await?;
+
+                let parsed_body: OpenAiModelListResponseBody = serde_json::from_slice(&body)?;
+
+                Ok(parsed_body.data)
+            })
+            .await?;
+        models.sort_by(|a, b| a.id.cmp(&b.id));
+
+        Ok(models)
+    }
+
+    /// lists the models and merges them into the client's registry, see
+    /// [`ModelRegistry::merge_model_names`]. Clones made before keep the
+    /// registry they had. Returns the added names.
+    pub async fn refresh_model_registry(&mut self) -> Result<Vec<String>, LlmError> {
+        let models = self.list_models().await?;
+
+        let model_registry: &mut ModelRegistry = Arc::make_mut(&mut self.model_registry);
+        Ok(model_registry.merge_model_names(models.into_iter().map(|model| model.id)))
+    }
+}
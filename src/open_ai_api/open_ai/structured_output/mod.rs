@@ -0,0 +1,77 @@
+use super::{sum_cost_usd, sum_usage, OpenAiClient, OpenAiSimplifiedResponse, Prompt};
+use crate::llm_client::parse_answer;
+use crate::response_format::repair_request;
+use crate::{
+    ChatMessage, GenerationParams, LlmError, LlmResponse, PromptType, ResponseFormat,
+    SchemaViolation, Usage,
+};
+
+impl OpenAiClient {
+    /// the answer to the prompt deserialized into `T`, the schema of `T` is
+    /// sent as a strict [`ResponseFormat::JsonSchema`]. An answer that does not
+    /// match it is sent back to be corrected at least once, more often if the
+    /// client's `json_repair_attempts` allow.
+    pub async fn perform_request_typed<T: LlmResponse>(
+        &self,
+        prompt: &PromptType,
+    ) -> Result<T, LlmError> {
+        let mut chat_prompt = self.build_prompt(prompt, &GenerationParams::default())?;
+        let response_format = ResponseFormat::JsonSchema(T::response_json_schema());
+        chat_prompt.response_format = Some(response_format.clone());
+        self.check_response_format(&chat_prompt)?;
+
+        let repair_attempts = self.json_repair_attempts.max(1);
+        let simplified_response = self
+            .perform_structured_request(chat_prompt, repair_attempts, |answer| {
+                parse_answer::<T>(&response_format, answer).map(|_| ())
+            })
+            .await?;
+
+        simplified_response.answer_json()
+    }
+
+    /// sends the prompt and validates the answer. An invalid answer is sent
+    /// back with the violation to be corrected, at most `repair_attempts`
+    /// times. The usage and cost of the response cover all attempts.
+    pub(super) async fn perform_structured_request<F>(
+        &self,
+        mut prompt: Prompt,
+        repair_attempts: usize,
+        validate: F,
+    ) -> Result<OpenAiSimplifiedResponse, LlmError>
+    where
+        F: Fn(&str) -> Result<(), SchemaViolation>,
+    {
+        let mut usage_maybe: Option<Usage> = None;
+        let mut cost_usd_maybe: Option<f64> = Some(0.0);
+
+        let mut repair_attempt = 0;
+        let (violation, answer) = loop {
+            let mut simplified_response = self.complete(&prompt).await?;
+            usage_maybe = sum_usage(usage_maybe, simplified_response.usage);
+            cost_usd_maybe = sum_cost_usd(cost_usd_maybe, simplified_response.cost_usd);
+
+            let answer = simplified_response.answer.clone().unwrap_or_default();
+            match validate(&answer) {
+                Ok(()) => {
+                    simplified_response.usage = usage_maybe;
+                    simplified_response.cost_usd = cost_usd_maybe;
+                    return Ok(simplified_response);
+                }
+                Err(violation) if repair_attempt == repair_attempts => break (violation, answer),
+                Err(violation) => {
+                    log::warn!(
+                        "answer does not match the response format, asking for a repair: {violation}"
+                    );
+                    prompt.messages.push(ChatMessage::assistant(answer));
+                    prompt
+                        .messages
+                        .push(ChatMessage::user(repair_request(&violation)));
+                }
+            }
+            repair_attempt += 1;
+        };
+
+        Err(violation.into_error(answer))
+    }
+}
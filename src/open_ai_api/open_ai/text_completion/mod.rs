@@ -0,0 +1,288 @@
+use super::{OpenAiClient, OpenAiModel, DEFAULT_TEMPERATURE};
+use crate::content_filter::{unfiltered_choices, AnnotatedChoice};
+use crate::rate_limiter::estimate_request_tokens;
+use crate::timeouts::read_body;
+use crate::{
+    ChatResponse, ContextWindowCheck, GenerationLimits, GenerationParams, LlmError, PromptType,
+    TokenLogprob, Usage,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// the most alternatives `/completions` returns per token
+const MAX_LOGPROBS: u8 = 5;
+
+/// what a text completion request sends besides the prompt and the
+/// [`GenerationParams`].
+///
+/// # Usage
+/// ```no_run
+/// let options = OpenAiTextCompletionOptions::default().echo(true).logprobs(5);
+/// let chat_response = open_ai_client.perform_text_completion(&prompt, &options).await?;
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OpenAiTextCompletionOptions {
+    /// text after the insertion point, the model fills in the gap
+    pub suffix: Option<String>,
+
+    /// returns the prompt in front of the answer, with the log probabilities
+    /// of its tokens when `logprobs` is set
+    pub echo: bool,
+
+    /// between 0 and 5, returns the log probability of every token and of
+    /// that many of the most likely alternatives at its position
+    pub logprobs: Option<u8>,
+}
+
+impl OpenAiTextCompletionOptions {
+    pub fn suffix(mut self, suffix: impl Into<String>) -> Self {
+        self.suffix = Some(suffix.into());
+        self
+    }
+
+    pub fn echo(mut self, echo: bool) -> Self {
+        self.echo = echo;
+        self
+    }
+
+    pub fn logprobs(mut self, logprobs: u8) -> Self {
+        self.logprobs = Some(logprobs);
+        self
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct TextCompletionPrompt<'a> {
+    model: &'a OpenAiModel,
+    prompt: &'a str,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    suffix: Option<&'a str>,
+
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    echo: bool,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    logprobs: Option<u8>,
+
+    /// sampling parameters, the unset ones are left out
+    #[serde(flatten)]
+    params: GenerationParams,
+}
+
+/// the answer of `/completions`
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct OpenAiTextCompletionResponseBody {
+    pub id: String,
+    pub object: String,
+    pub created: u64,
+    pub model: String,
+    pub choices: Vec<OpenAiTextChoice>,
+    pub usage: Option<Usage>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct OpenAiTextChoice {
+    pub index: u64,
+    pub text: String,
+
+    /// only when `logprobs` was requested
+    #[serde(default)]
+    pub logprobs: Option<OpenAiLogprobs>,
+
+    /// `stop`, `length` or `content_filter`
+    #[serde(default)]
+    pub finish_reason: Option<String>,
+
+    /// Azure's annotations of the answer per category, see
+    /// [`crate::ContentFilterResult`]
+    #[serde(default)]
+    pub content_filter_results: Option<serde_json::Value>,
+}
+
+impl AnnotatedChoice for OpenAiTextChoice {
+    fn finish_reason(&self) -> Option<&str> {
+        self.finish_reason.as_deref()
+    }
+
+    fn content_filter_results(&self) -> Option<&serde_json::Value> {
+        self.content_filter_results.as_ref()
+    }
+}
+
+/// the tokens of a choice as parallel lists
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct OpenAiLogprobs {
+    pub tokens: Vec<String>,
+
+    /// `None` for the first token of an echoed prompt, which nothing
+    /// precedes
+    pub token_logprobs: Vec<Option<f64>>,
+
+    /// the most likely tokens at every position with their log probabilities
+    #[serde(default)]
+    pub top_logprobs: Option<Vec<Option<BTreeMap<String, f64>>>>,
+
+    /// the character offset of every token in the text
+    #[serde(default)]
+    pub text_offset: Vec<usize>,
+}
+
+impl OpenAiLogprobs {
+    /// the tokens in the crate's terms with the alternatives ordered from the
+    /// most likely one, a token without a log probability gets 0
+    pub fn token_logprobs(&self) -> Vec<TokenLogprob> {
+        self.tokens
+            .iter()
+            .enumerate()
+            .map(|(index, token)| {
+                let mut top_logprobs: Vec<(String, f64)> = self
+                    .top_logprobs
+                    .as_ref()
+                    .and_then(|top_logprobs| top_logprobs.get(index))
+                    .into_iter()
+                    .flatten()
+                    .flatten()
+                    .map(|(token, logprob)| (token.clone(), *logprob))
+                    .collect();
+                top_logprobs.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+                TokenLogprob {
+                    token: token.clone(),
+                    logprob: self
+                        .token_logprobs
+                        .get(index)
+                        .copied()
+                        .flatten()
+                        .unwrap_or(0.0),
+                    token_id_maybe: None,
+                    top_logprobs,
+                }
+            })
+            .collect()
+    }
+}
+
+impl OpenAiClient {
+    /// sends the prompt as raw text to the legacy `/completions` endpoint of
+    /// completion models such as [`OpenAiModel::Gpt35TurboInstruct`], see
+    /// [`PromptType::completion_prompt`]. The tokens of the selected answer
+    /// are returned when `logprobs` is set.
+    pub async fn perform_text_completion(
+        &self,
+        prompt: &PromptType,
+        options: &OpenAiTextCompletionOptions,
+    ) -> Result<ChatResponse, LlmError> {
+        if prompt.response_format().is_some() {
+            return Err(LlmError::InvalidRequest(
+                "/completions does not support response_format".to_string(),
+            ));
+        }
+
+        let response_body = self
+            .perform_text_completion_request(
+                &prompt.completion_prompt(),
+                options,
+                &GenerationParams::default(),
+            )
+            .await?;
+        self.text_completion_response(response_body)
+    }
+
+    /// the body of `/completions` for the raw prompt, with the parameters set
+    /// in `params` overriding the client's [`GenerationParams`]
+    pub async fn perform_text_completion_request(
+        &self,
+        prompt: &str,
+        options: &OpenAiTextCompletionOptions,
+        params: &GenerationParams,
+    ) -> Result<OpenAiTextCompletionResponseBody, LlmError> {
+        if options
+            .logprobs
+            .is_some_and(|logprobs| logprobs > MAX_LOGPROBS)
+        {
+            return Err(LlmError::InvalidRequest(format!(
+                "logprobs must be between 0 and {MAX_LOGPROBS}"
+            )));
+        }
+
+        let model_descriptor_maybe = self.model_descriptor();
+        if model_descriptor_maybe.is_some_and(|descriptor| descriptor.features.reasoning) {
+            return Err(LlmError::InvalidRequest(format!(
+                "{} does not support /completions",
+                self.model.name()
+            )));
+        }
+        let mut params = self.generation_params.merge(params);
+        params.validate(&GenerationLimits::OPEN_AI, model_descriptor_maybe)?;
+        params.temperature.get_or_insert(DEFAULT_TEMPERATURE);
+
+        if self.context_window_check != ContextWindowCheck::Off {
+            self.check_prompt_tokens(self.tokenizer.count_tokens(prompt), params.max_tokens)?;
+        }
+
+        let completion_tokens_maybe = params
+            .max_tokens
+            .map(|max_tokens| max_tokens * params.n.unwrap_or(1));
+        let text_completion_prompt = TextCompletionPrompt {
+            model: &self.model,
+            prompt,
+            suffix: options.suffix.as_deref(),
+            echo: options.echo,
+            logprobs: options.logprobs,
+            params,
+        };
+        let serialized_prompt = serde_json::to_string(&text_completion_prompt)
+            .map_err(|error| LlmError::InvalidRequest(error.to_string()))?;
+        let estimated_tokens =
+            estimate_request_tokens(&self.tokenizer, &serialized_prompt, completion_tokens_maybe);
+
+        let read_timeout = self.timeouts.read;
+        self.send_with_retries(
+            "/completions",
+            Some(serialized_prompt),
+            estimated_tokens,
+            |mut resp| async move {
+                let body = read_body(resp.body_mut(), read_timeout).await?;
+
+                let parsed_body: OpenAiTextCompletionResponseBody = serde_json::from_slice(&body)?;
+
+                Ok(parsed_body)
+            },
+        )
+        .await
+    }
+
+    /// the answers with the cost and the selected answer filled in, the usage
+    /// is recorded to the ledger
+    fn text_completion_response(
+        &self,
+        mut response_body: OpenAiTextCompletionResponseBody,
+    ) -> Result<ChatResponse, LlmError> {
+        if response_body.choices.is_empty() {
+            return Err(LlmError::EmptyChoices);
+        }
+        response_body.choices.sort_by_key(|choice| choice.index);
+
+        let (choices, content_filter_results) = unfiltered_choices(response_body.choices)?;
+        let answers: Vec<String> = choices.iter().map(|choice| choice.text.clone()).collect();
+        let answer = self.answer_selector.select(&answers).map(str::to_string);
+        let tokens = choices
+            .iter()
+            .find(|choice| Some(&choice.text) == answer.as_ref())
+            .and_then(|choice| choice.logprobs.as_ref())
+            .map(OpenAiLogprobs::token_logprobs)
+            .unwrap_or_default();
+
+        let cost_usd = self.record_usage(&response_body.model, response_body.usage.as_ref());
+
+        Ok(ChatResponse {
+            answer,
+            answers,
+            usage: response_body.usage,
+            cost_usd,
+            content_filter_results,
+            tokens,
+        })
+    }
+}
@@ -0,0 +1,120 @@
+use super::{serialize_prompt, sum_cost_usd, sum_usage, OpenAiClient, OpenAiSimplifiedResponse};
+use crate::response_format::repair_request;
+use crate::{ChatMessage, GenerationParams, LlmError, PromptType, ToolRegistry, Usage};
+use futures::future::join_all;
+
+impl OpenAiClient {
+    /// same as [`Self::perform_request`] but the model may call the tools of
+    /// the registry. The handlers of the requested calls run concurrently,
+    /// their outputs are sent back as `tool` messages until the model gives a
+    /// final answer. A final answer that does not match the prompt's
+    /// [`crate::ResponseFormat`] is sent back to be corrected, at most
+    /// `json_repair_attempts` times, each repair is a step. The usage and cost
+    /// of the response cover all steps.
+    pub async fn perform_request_with_tools(
+        &self,
+        prompt: &PromptType,
+        tool_registry: &ToolRegistry,
+    ) -> Result<OpenAiSimplifiedResponse, LlmError> {
+        if let Some(model_descriptor) = self.model_descriptor() {
+            if !model_descriptor.features.tools {
+                return Err(LlmError::InvalidRequest(format!(
+                    "{} does not support tools",
+                    model_descriptor.name
+                )));
+            }
+        }
+
+        let mut prompt = self.build_prompt(prompt, &GenerationParams::default())?;
+        if !tool_registry.is_empty() {
+            prompt.tools = Some(tool_registry.definitions());
+        }
+
+        // of the steps before the final answer
+        let mut usage_maybe: Option<Usage> = None;
+        let mut cost_usd_maybe: Option<f64> = Some(0.0);
+        let mut repair_attempt = 0;
+
+        for _ in 0..tool_registry.max_steps() {
+            self.check_context_window(&prompt)?;
+            let serialized_prompt = serialize_prompt(&prompt)?;
+            let estimated_tokens = prompt.estimate_tokens(&self.tokenizer, &serialized_prompt);
+
+            let mut open_ai_completions_response_body = self
+                .post_chat_completions(serialized_prompt, estimated_tokens)
+                .await?;
+            open_ai_completions_response_body
+                .choices
+                .sort_by_key(|choice| choice.index);
+
+            let tool_calls = open_ai_completions_response_body
+                .choices
+                .first()
+                .and_then(|choice| choice.message.tool_calls.clone())
+                .unwrap_or_default();
+
+            if tool_calls.is_empty() {
+                let mut simplified_response =
+                    self.simplify_response(open_ai_completions_response_body)?;
+                usage_maybe = sum_usage(usage_maybe, simplified_response.usage);
+                cost_usd_maybe = sum_cost_usd(cost_usd_maybe, simplified_response.cost_usd);
+
+                let answer = simplified_response.answer.clone().unwrap_or_default();
+                let violation = match &prompt.response_format {
+                    Some(response_format) => response_format.validate(&answer).err(),
+                    None => None,
+                };
+                match violation {
+                    None => {
+                        simplified_response.usage = usage_maybe;
+                        simplified_response.cost_usd = cost_usd_maybe;
+                        return Ok(simplified_response);
+                    }
+                    Some(violation) if repair_attempt == self.json_repair_attempts => {
+                        return Err(violation.into_error(answer));
+                    }
+                    Some(violation) => {
+                        log::warn!(
+                            "answer does not match the response format, asking for a repair: {violation}"
+                        );
+                        repair_attempt += 1;
+                        prompt.messages.push(ChatMessage::assistant(answer));
+                        prompt
+                            .messages
+                            .push(ChatMessage::user(repair_request(&violation)));
+                        continue;
+                    }
+                }
+            }
+
+            let step_cost_usd_maybe = self.record_usage(
+                &open_ai_completions_response_body.model,
+                open_ai_completions_response_body.usage.as_ref(),
+            );
+            usage_maybe = sum_usage(usage_maybe, open_ai_completions_response_body.usage);
+            cost_usd_maybe = sum_cost_usd(cost_usd_maybe, step_cost_usd_maybe);
+
+            let assistant_message = open_ai_completions_response_body
+                .choices
+                .swap_remove(0)
+                .message;
+            prompt.messages.push(assistant_message);
+
+            let outputs = join_all(
+                tool_calls
+                    .iter()
+                    .map(|tool_call| tool_registry.call(tool_call)),
+            )
+            .await;
+            for (tool_call, output) in tool_calls.iter().zip(outputs) {
+                prompt
+                    .messages
+                    .push(ChatMessage::tool(&tool_call.id, output));
+            }
+        }
+
+        Err(LlmError::ToolStepsExceeded {
+            max_steps: tool_registry.max_steps(),
+        })
+    }
+}
@@ -4,9 +4,12 @@ pub mod zero_shot_prompt;
 pub use multi_shot_prompt::{MultiShotExampleCount, MultiShotPrompt, MultiShotQuestionsAndAnswers};
 pub use zero_shot_prompt::ZeroShotPrompt;
 
+use crate::{ChatMessage, Conversation, JsonSchema, ResponseFormat, Tokenizer};
+
 pub enum PromptType {
     ZeroShotPrompt(ZeroShotPrompt),
     MultiShotPrompt(MultiShotPrompt),
+    Conversation(Conversation),
 }
 
 impl PromptType {
@@ -40,11 +43,75 @@ question: {inner_prompt}"#
         PromptType::ZeroShotPrompt(ZeroShotPrompt::new(inner_prompt))
     }
 
-    /// returns the constructed prompt
+    /// the messages so far, e.g. starting with a system prompt
+    pub fn new_conversation(conversation: Conversation) -> PromptType {
+        PromptType::Conversation(conversation)
+    }
+
+    /// returns the constructed prompt, the contents of the messages of a
+    /// conversation one per line
     pub fn prompt(&self) -> String {
         match self {
             PromptType::MultiShotPrompt(multi_shot_prompt) => multi_shot_prompt.prompt(),
             PromptType::ZeroShotPrompt(zero_shot_prompt) => zero_shot_prompt.prompt(),
+            PromptType::Conversation(conversation) => conversation
+                .messages()
+                .iter()
+                .filter_map(|message| message.content.as_deref())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+
+    /// the prompt as the raw text of a completion model, a multi shot prompt
+    /// ends where the answer follows
+    pub fn completion_prompt(&self) -> String {
+        match self {
+            PromptType::MultiShotPrompt(multi_shot_prompt) => multi_shot_prompt.completion_prompt(),
+            _ => self.prompt(),
+        }
+    }
+
+    /// the messages the prompt is sent as, the shot prompts are a single user
+    /// message
+    pub fn messages(&self) -> Vec<ChatMessage> {
+        match self {
+            PromptType::Conversation(conversation) => conversation.messages().to_vec(),
+            _ => vec![ChatMessage::user(self.prompt())],
         }
     }
+
+    /// asks for an answer in the format, which the client validates
+    pub fn with_response_format(mut self, response_format: ResponseFormat) -> PromptType {
+        match &mut self {
+            PromptType::MultiShotPrompt(multi_shot_prompt) => {
+                multi_shot_prompt.set_response_format(response_format)
+            }
+            PromptType::ZeroShotPrompt(zero_shot_prompt) => {
+                zero_shot_prompt.set_response_format(response_format)
+            }
+            PromptType::Conversation(conversation) => {
+                conversation.set_response_format(response_format)
+            }
+        }
+        self
+    }
+
+    /// asks for an answer matching the schema, see [`JsonSchema`]
+    pub fn with_json_schema(self, json_schema: JsonSchema) -> PromptType {
+        self.with_response_format(ResponseFormat::JsonSchema(json_schema))
+    }
+
+    pub fn response_format(&self) -> Option<&ResponseFormat> {
+        match self {
+            PromptType::MultiShotPrompt(multi_shot_prompt) => multi_shot_prompt.response_format(),
+            PromptType::ZeroShotPrompt(zero_shot_prompt) => zero_shot_prompt.response_format(),
+            PromptType::Conversation(conversation) => conversation.response_format(),
+        }
+    }
+
+    /// tokens of the constructed prompt, without the chat format overhead
+    pub fn count_tokens(&self, tokenizer: &Tokenizer) -> usize {
+        tokenizer.count_tokens(&self.prompt())
+    }
 }
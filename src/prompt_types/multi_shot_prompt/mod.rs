@@ -1,3 +1,5 @@
+use crate::ResponseFormat;
+
 /// this has to be implemented for a trait and then that trait has to be defined
 /// as a type for the type [`MultiShotQuestionsAndAnswers`] for an implementation
 /// for a prompt. This allows the developer of a prompt to define a set of
@@ -76,14 +78,32 @@ pub enum MultiShotExampleCount {
 
 pub struct MultiShotPrompt {
     prompt: String,
+    response_format_maybe: Option<ResponseFormat>,
 }
 
 impl MultiShotPrompt {
     pub fn new(prompt: String) -> Self {
-        Self { prompt }
+        Self {
+            prompt,
+            response_format_maybe: None,
+        }
     }
 
     pub fn prompt(&self) -> String {
         self.prompt.clone()
     }
+
+    /// the examples and the question as the raw text of a completion model,
+    /// ending where the model continues with the answer
+    pub fn completion_prompt(&self) -> String {
+        format!("{}\nanswer:", self.prompt.trim())
+    }
+
+    pub fn response_format(&self) -> Option<&ResponseFormat> {
+        self.response_format_maybe.as_ref()
+    }
+
+    pub fn set_response_format(&mut self, response_format: ResponseFormat) {
+        self.response_format_maybe = Some(response_format);
+    }
 }
@@ -1,13 +1,27 @@
+use crate::ResponseFormat;
+
 pub struct ZeroShotPrompt {
     prompt: String,
+    response_format_maybe: Option<ResponseFormat>,
 }
 
 impl ZeroShotPrompt {
     pub fn new(prompt: String) -> Self {
-        Self { prompt }
+        Self {
+            prompt,
+            response_format_maybe: None,
+        }
     }
 
     pub fn prompt(&self) -> String {
         self.prompt.clone()
     }
+
+    pub fn response_format(&self) -> Option<&ResponseFormat> {
+        self.response_format_maybe.as_ref()
+    }
+
+    pub fn set_response_format(&mut self, response_format: ResponseFormat) {
+        self.response_format_maybe = Some(response_format);
+    }
 }
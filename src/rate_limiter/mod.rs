@@ -0,0 +1,167 @@
+use crate::Tokenizer;
+use hyper::header::HeaderMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::{sleep, Instant};
+
+/// client side limiter for requests per minute (RPM) and tokens per minute
+/// (TPM). Clones share the same budget, so one limiter can be attached to
+/// several clients or workers using the same OpenAI organization.
+///
+/// Both budgets are token buckets that start full and refill continuously.
+/// The limiter also follows the `x-ratelimit-limit-*` and
+/// `x-ratelimit-remaining-*` headers of the responses, so that usage by other
+/// processes sharing the organization is taken into account.
+///
+/// # Usage
+/// ```no_run
+/// let rate_limiter = RateLimiter::new(3_500, 90_000);
+///
+/// let open_ai_client = OpenAiClient::builder()
+///     .rate_limiter(rate_limiter.clone())
+///     .build()?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    buckets: Arc<Mutex<Buckets>>,
+}
+
+#[derive(Debug)]
+struct Buckets {
+    requests: Bucket,
+    tokens: Bucket,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    capacity: f64,
+    available: f64,
+    refilled_at: Instant,
+}
+
+impl Bucket {
+    fn new(per_minute: u32) -> Self {
+        Self {
+            capacity: per_minute as f64,
+            available: per_minute as f64,
+            refilled_at: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.refilled_at).as_secs_f64();
+        self.available = (self.available + elapsed * self.capacity / 60.0).min(self.capacity);
+        self.refilled_at = now;
+    }
+
+    /// how long until `amount` is available, zero if it already is
+    fn wait_for(&self, amount: f64) -> Duration {
+        let missing = amount - self.available;
+        if missing <= 0.0 {
+            Duration::ZERO
+        } else if self.capacity <= 0.0 {
+            Duration::from_secs(60)
+        } else {
+            Duration::from_secs_f64(missing * 60.0 / self.capacity)
+        }
+    }
+
+    /// follows the limit and remaining budget reported by the server
+    fn update(&mut self, limit_maybe: Option<f64>, remaining_maybe: Option<f64>) {
+        if let Some(limit) = limit_maybe {
+            self.capacity = limit;
+            self.available = self.available.min(limit);
+        }
+        if let Some(remaining) = remaining_maybe {
+            self.available = self.available.min(remaining);
+        }
+    }
+}
+
+impl RateLimiter {
+    pub fn new(requests_per_minute: u32, tokens_per_minute: u32) -> Self {
+        Self {
+            buckets: Arc::new(Mutex::new(Buckets {
+                requests: Bucket::new(requests_per_minute),
+                tokens: Bucket::new(tokens_per_minute),
+            })),
+        }
+    }
+
+    /// waits until one request and `estimated_tokens` tokens are available and
+    /// takes them from the budget. Requests estimated above the whole TPM
+    /// budget only wait for a full bucket instead of forever.
+    pub async fn acquire(&self, estimated_tokens: u32) {
+        loop {
+            let wait = {
+                let mut buckets = self.buckets.lock().unwrap();
+                let now = Instant::now();
+                buckets.requests.refill(now);
+                buckets.tokens.refill(now);
+
+                let tokens = (estimated_tokens as f64).min(buckets.tokens.capacity);
+                let wait = buckets
+                    .requests
+                    .wait_for(1.0)
+                    .max(buckets.tokens.wait_for(tokens));
+
+                if wait.is_zero() {
+                    buckets.requests.available -= 1.0;
+                    buckets.tokens.available -= tokens;
+                    return;
+                }
+                wait
+            };
+
+            sleep(wait).await;
+        }
+    }
+
+    /// requests that could be sent right now
+    pub fn available_requests(&self) -> u32 {
+        let mut buckets = self.buckets.lock().unwrap();
+        buckets.requests.refill(Instant::now());
+        buckets.requests.available.max(0.0) as u32
+    }
+
+    /// tokens that could be used right now
+    pub fn available_tokens(&self) -> u32 {
+        let mut buckets = self.buckets.lock().unwrap();
+        buckets.tokens.refill(Instant::now());
+        buckets.tokens.available.max(0.0) as u32
+    }
+
+    /// adjusts the budget to the `x-ratelimit-*` headers of a response
+    pub fn update_from_headers(&self, headers: &HeaderMap) {
+        let header_number = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.trim().parse::<f64>().ok())
+        };
+
+        let mut buckets = self.buckets.lock().unwrap();
+        let now = Instant::now();
+        buckets.requests.refill(now);
+        buckets.tokens.refill(now);
+
+        buckets.requests.update(
+            header_number("x-ratelimit-limit-requests"),
+            header_number("x-ratelimit-remaining-requests"),
+        );
+        buckets.tokens.update(
+            header_number("x-ratelimit-limit-tokens"),
+            header_number("x-ratelimit-remaining-tokens"),
+        );
+    }
+}
+
+/// estimates the tokens a request counts against the TPM limit, the prompt
+/// plus the completion tokens it may generate
+pub(crate) fn estimate_request_tokens(
+    tokenizer: &Tokenizer,
+    serialized_prompt: &str,
+    max_tokens_maybe: Option<u32>,
+) -> u32 {
+    tokenizer.count_tokens(serialized_prompt) as u32 + max_tokens_maybe.unwrap_or(0)
+}
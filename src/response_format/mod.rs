@@ -0,0 +1,355 @@
+use crate::LlmError;
+use serde::{Serialize, Serializer};
+use serde_json::{Map, Value};
+use std::fmt::{Display, Formatter};
+
+/// asks the model for JSON instead of free text, sent as `response_format`.
+/// The answer is validated before it is returned, see
+/// [`crate::PromptType::with_response_format`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResponseFormat {
+    /// any JSON object
+    JsonObject,
+
+    /// JSON matching the schema
+    JsonSchema(JsonSchema),
+}
+
+impl Serialize for ResponseFormat {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let response_format = match self {
+            Self::JsonObject => serde_json::json!({"type": "json_object"}),
+            Self::JsonSchema(json_schema) => serde_json::json!({
+                "type": "json_schema",
+                "json_schema": {
+                    "name": json_schema.name,
+                    "schema": json_schema.schema,
+                    "strict": json_schema.strict,
+                }
+            }),
+        };
+        response_format.serialize(serializer)
+    }
+}
+
+impl ResponseFormat {
+    /// checks that the answer is JSON in the requested format
+    pub fn validate(&self, answer: &str) -> Result<Value, SchemaViolation> {
+        let value: Value = serde_json::from_str(answer).map_err(|error| SchemaViolation {
+            path: "$".to_string(),
+            message: format!("not valid JSON: {error}"),
+        })?;
+
+        match self {
+            Self::JsonObject if !value.is_object() => Err(SchemaViolation {
+                path: "$".to_string(),
+                message: "expected an object".to_string(),
+            }),
+            Self::JsonObject => Ok(value),
+            Self::JsonSchema(json_schema) => json_schema.validate(&value).map(|_| value),
+        }
+    }
+
+    /// same as [`Self::validate`] with a violation as
+    /// [`LlmError::SchemaValidation`]
+    pub fn validate_answer(&self, answer: &str) -> Result<(), LlmError> {
+        self.validate(answer)
+            .map(|_| ())
+            .map_err(|violation| violation.into_error(answer.to_string()))
+    }
+}
+
+/// a named JSON schema for structured outputs. With `strict` the server
+/// guarantees the schema for the subset of JSON schema it supports, the
+/// answer is validated on the client either way.
+///
+/// # Usage
+/// ```no_run
+/// let json_schema = JsonSchema::new(
+///     "sentiment",
+///     serde_json::json!({
+///         "type": "object",
+///         "properties": {"label": {"type": "string", "enum": ["positive", "negative"]}},
+///         "required": ["label"],
+///         "additionalProperties": false
+///     }),
+/// );
+///
+/// let prompt = PromptType::new_zero_shot_prompt(query).with_json_schema(json_schema);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonSchema {
+    /// letters, digits, `_` and `-` only
+    pub name: String,
+    pub schema: Value,
+    pub strict: bool,
+}
+
+impl JsonSchema {
+    /// a strict schema
+    pub fn new(name: impl Into<String>, schema: Value) -> Self {
+        Self {
+            name: name.into(),
+            schema,
+            strict: true,
+        }
+    }
+
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// checks the value against the schema. Supports the keywords of
+    /// structured outputs: `type`, `enum`, `const`, `properties`, `required`,
+    /// `additionalProperties`, `items`, `anyOf`, `$ref` into `$defs`, and the
+    /// length and range bounds. Other keywords are ignored.
+    pub fn validate(&self, value: &Value) -> Result<(), SchemaViolation> {
+        Validator { root: &self.schema }.validate(&self.schema, value, "$")
+    }
+}
+
+/// where and why a value does not match a schema, the path is in the form
+/// `$.items[0].name`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaViolation {
+    pub path: String,
+    pub message: String,
+}
+
+impl SchemaViolation {
+    /// the error for the answer with this violation
+    pub(crate) fn into_error(self, answer: String) -> LlmError {
+        LlmError::SchemaValidation {
+            path: self.path,
+            message: self.message,
+            answer,
+        }
+    }
+}
+
+impl Display for SchemaViolation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// the user message asking the model to correct an answer
+pub(crate) fn repair_request(violation: &SchemaViolation) -> String {
+    format!(
+        "The answer does not match the required JSON format at {violation}. Reply with the corrected JSON only."
+    )
+}
+
+struct Validator<'s> {
+    root: &'s Value,
+}
+
+impl<'s> Validator<'s> {
+    fn validate(
+        &self,
+        schema: &'s Value,
+        value: &Value,
+        path: &str,
+    ) -> Result<(), SchemaViolation> {
+        let violation = |message: String| SchemaViolation {
+            path: path.to_string(),
+            message,
+        };
+
+        let schema = match schema {
+            Value::Bool(true) => return Ok(()),
+            Value::Bool(false) => return Err(violation("no value is allowed".to_string())),
+            Value::Object(schema) => schema,
+            _ => return Ok(()),
+        };
+
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            let referenced_schema = self
+                .resolve(reference)
+                .ok_or_else(|| violation(format!("unresolvable $ref {reference}")))?;
+            self.validate(referenced_schema, value, path)?;
+        }
+
+        if let Some(any_of) = schema.get("anyOf").and_then(Value::as_array) {
+            let matches_any = any_of
+                .iter()
+                .any(|sub_schema| self.validate(sub_schema, value, path).is_ok());
+            if !matches_any {
+                return Err(violation("does not match any schema of anyOf".to_string()));
+            }
+        }
+
+        if let Some(expected_type) = schema.get("type") {
+            let types: Vec<&str> = match expected_type {
+                Value::String(expected_type) => vec![expected_type.as_str()],
+                Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+                _ => vec![],
+            };
+            if !types.is_empty() && !types.iter().any(|t| has_type(value, t)) {
+                return Err(violation(format!(
+                    "expected {}, got {}",
+                    types.join(" or "),
+                    type_name(value)
+                )));
+            }
+        }
+
+        if let Some(allowed) = schema.get("enum").and_then(Value::as_array) {
+            if !allowed.contains(value) {
+                return Err(violation(format!(
+                    "{value} is not one of {}",
+                    Value::from(allowed.clone())
+                )));
+            }
+        }
+        if let Some(constant) = schema.get("const") {
+            if constant != value {
+                return Err(violation(format!("expected {constant}, got {value}")));
+            }
+        }
+
+        match value {
+            Value::Object(object) => self.validate_object(schema, object, path),
+            Value::Array(items) => self.validate_array(schema, items, path),
+            Value::String(string) => {
+                let length = string.chars().count() as u64;
+                check_bound(schema, "minLength", length, |min, n| n >= min, path)?;
+                check_bound(schema, "maxLength", length, |max, n| n <= max, path)
+            }
+            Value::Number(number) => {
+                let number = number.as_f64().unwrap_or(f64::NAN);
+                for (keyword, holds) in [
+                    ("minimum", (|bound, n| n >= bound) as fn(f64, f64) -> bool),
+                    ("maximum", |bound, n| n <= bound),
+                    ("exclusiveMinimum", |bound, n| n > bound),
+                    ("exclusiveMaximum", |bound, n| n < bound),
+                ] {
+                    if let Some(bound) = schema.get(keyword).and_then(Value::as_f64) {
+                        if !holds(bound, number) {
+                            return Err(violation(format!("{number} violates {keyword} {bound}")));
+                        }
+                    }
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn validate_object(
+        &self,
+        schema: &'s Map<String, Value>,
+        object: &Map<String, Value>,
+        path: &str,
+    ) -> Result<(), SchemaViolation> {
+        for required in schema
+            .get("required")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+        {
+            if !object.contains_key(required) {
+                return Err(SchemaViolation {
+                    path: path.to_string(),
+                    message: format!("missing required property {required}"),
+                });
+            }
+        }
+
+        let properties = schema.get("properties").and_then(Value::as_object);
+        for (name, property_value) in object {
+            let property_path = format!("{path}.{name}");
+            match properties.and_then(|properties| properties.get(name)) {
+                Some(property_schema) => {
+                    self.validate(property_schema, property_value, &property_path)?
+                }
+                None => match schema.get("additionalProperties") {
+                    Some(Value::Bool(false)) => {
+                        return Err(SchemaViolation {
+                            path: property_path,
+                            message: "additional property is not allowed".to_string(),
+                        })
+                    }
+                    Some(additional_schema) => {
+                        self.validate(additional_schema, property_value, &property_path)?
+                    }
+                    None => {}
+                },
+            }
+        }
+
+        Ok(())
+    }
+
+    fn validate_array(
+        &self,
+        schema: &'s Map<String, Value>,
+        items: &[Value],
+        path: &str,
+    ) -> Result<(), SchemaViolation> {
+        let length = items.len() as u64;
+        check_bound(schema, "minItems", length, |min, n| n >= min, path)?;
+        check_bound(schema, "maxItems", length, |max, n| n <= max, path)?;
+
+        if let Some(item_schema) = schema.get("items") {
+            for (index, item) in items.iter().enumerate() {
+                self.validate(item_schema, item, &format!("{path}[{index}]"))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `#` or a JSON pointer into the root schema such as `#/$defs/address`
+    fn resolve(&self, reference: &str) -> Option<&'s Value> {
+        let pointer = reference.strip_prefix('#')?;
+        self.root.pointer(pointer)
+    }
+}
+
+fn check_bound(
+    schema: &Map<String, Value>,
+    keyword: &str,
+    actual: u64,
+    holds: fn(u64, u64) -> bool,
+    path: &str,
+) -> Result<(), SchemaViolation> {
+    match schema.get(keyword).and_then(Value::as_u64) {
+        Some(bound) if !holds(bound, actual) => Err(SchemaViolation {
+            path: path.to_string(),
+            message: format!("length {actual} violates {keyword} {bound}"),
+        }),
+        _ => Ok(()),
+    }
+}
+
+fn has_type(value: &Value, expected_type: &str) -> bool {
+    match expected_type {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => {
+            value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|n| n.fract() == 0.0)
+        }
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
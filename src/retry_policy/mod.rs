@@ -0,0 +1,229 @@
+use crate::rate_limiter::RateLimiter;
+use crate::timeouts::{read_body, with_timeout};
+use crate::{LlmError, TimeoutKind, Timeouts};
+use hyper::header::HeaderMap;
+use hyper::{Body, Response};
+use std::future::Future;
+use std::time::{Duration, SystemTime};
+use tokio::time::sleep;
+
+/// how often and how patiently a failed request is retried. Only transport
+/// errors, timeouts and the `retryable_statuses` are retried, anything else is
+/// returned right away.
+///
+/// # Usage
+/// ```no_run
+/// let open_ai_client = OpenAiClient::builder()
+///     .retry_policy(RetryPolicy {
+///         max_attempts: 5,
+///         ..RetryPolicy::default()
+///     })
+///     .build()?;
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// total number of attempts including the first one, 1 disables retries
+    pub max_attempts: u32,
+
+    /// delay before the first retry, doubled for each following retry
+    pub base_delay: Duration,
+
+    /// upper bound for every delay. A server asking for a longer wait, e.g.
+    /// until a daily limit resets, fails the request right away.
+    pub max_delay: Duration,
+
+    /// fraction of the delay that is randomised, 0.2 turns 1s into 0.8s..1.2s
+    pub jitter: f64,
+
+    /// HTTP status codes that are worth another attempt
+    pub retryable_statuses: Vec<u16>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            jitter: 0.2,
+            retryable_statuses: vec![429, 500, 502, 503, 504],
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// a policy that gives up after the first attempt
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    pub fn is_retryable_status(&self, status: u16) -> bool {
+        self.retryable_statuses.contains(&status)
+    }
+
+    /// delay before the retry following the failed attempt with the given
+    /// index, the server hint wins over the exponential backoff when present.
+    /// `None` when the hint exceeds `max_delay` and the request is not worth
+    /// waiting for.
+    pub fn delay(
+        &self,
+        failed_attempt_index: u32,
+        server_hint_maybe: Option<Duration>,
+    ) -> Option<Duration> {
+        if let Some(server_hint) = server_hint_maybe {
+            return (server_hint <= self.max_delay).then_some(server_hint);
+        }
+
+        let exponential = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(failed_attempt_index))
+            .min(self.max_delay);
+
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        let factor = 1.0 - jitter + 2.0 * jitter * fastrand::f64();
+
+        Some(exponential.mul_f64(factor).min(self.max_delay))
+    }
+}
+
+/// the longest delay the server asked for through `Retry-After` (seconds or an
+/// HTTP date) or OpenAI's `x-ratelimit-reset-requests` and
+/// `x-ratelimit-reset-tokens` (durations such as `6m0s` or `250ms`)
+pub(crate) fn retry_delay_from_headers(headers: &HeaderMap) -> Option<Duration> {
+    let header_str = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+
+    let retry_after = header_str("retry-after").and_then(|value| {
+        let value = value.trim();
+        if let Ok(seconds) = value.parse::<f64>() {
+            Duration::try_from_secs_f64(seconds).ok()
+        } else {
+            httpdate::parse_http_date(value)
+                .ok()
+                .map(|date| date.duration_since(SystemTime::now()).unwrap_or_default())
+        }
+    });
+
+    let rate_limit_resets = ["x-ratelimit-reset-requests", "x-ratelimit-reset-tokens"]
+        .into_iter()
+        .filter_map(|name| header_str(name).and_then(parse_reset_duration));
+
+    retry_after.into_iter().chain(rate_limit_resets).max()
+}
+
+/// parses durations in the format OpenAI uses for the rate limit reset headers,
+/// e.g. `1s`, `6m0s`, `1h2m3.5s` or `20ms`
+pub(crate) fn parse_reset_duration(value: &str) -> Option<Duration> {
+    let mut rest = value.trim();
+    if rest.is_empty() {
+        return None;
+    }
+
+    let mut total = Duration::ZERO;
+    while !rest.is_empty() {
+        let number_end = rest
+            .find(|character: char| !(character.is_ascii_digit() || character == '.'))
+            .unwrap_or(rest.len());
+        let number: f64 = rest[..number_end].parse().ok()?;
+        rest = &rest[number_end..];
+
+        let unit_end = rest
+            .find(|character: char| character.is_ascii_digit() || character == '.')
+            .unwrap_or(rest.len());
+        let seconds_per_unit = match &rest[..unit_end] {
+            "h" => 3600.0,
+            "m" => 60.0,
+            "s" => 1.0,
+            "ms" => 0.001,
+            _ => return None,
+        };
+        rest = &rest[unit_end..];
+
+        total += Duration::try_from_secs_f64(number * seconds_per_unit).ok()?;
+    }
+
+    Some(total)
+}
+
+/// sends the request until an attempt gets a successful response that
+/// `handle_success` is able to turn into the result, retrying according to the
+/// retry policy. Every attempt takes `estimated_tokens` from the TPM budget.
+pub(crate) async fn send_with_retries<T, S, SendFut, F, Fut>(
+    retry_policy: &RetryPolicy,
+    timeouts: &Timeouts,
+    rate_limiter_maybe: Option<&RateLimiter>,
+    estimated_tokens: u32,
+    send: S,
+    handle_success: F,
+) -> Result<T, LlmError>
+where
+    S: Fn() -> SendFut,
+    SendFut: Future<Output = Result<Response<Body>, LlmError>>,
+    F: Fn(Response<Body>) -> Fut,
+    Fut: Future<Output = Result<T, LlmError>>,
+{
+    let mut failed_attempts: Vec<LlmError> = vec![];
+
+    loop {
+        // every attempt counts against the limits, waiting for the budget is
+        // not part of the timeouts
+        if let Some(rate_limiter) = rate_limiter_maybe {
+            rate_limiter.acquire(estimated_tokens).await;
+        }
+
+        let attempt = with_timeout(timeouts.overall, TimeoutKind::Overall, async {
+            let mut resp = send().await?;
+            if let Some(rate_limiter) = rate_limiter_maybe {
+                rate_limiter.update_from_headers(resp.headers());
+            }
+
+            let status = resp.status().as_u16();
+            if resp.status().is_success() {
+                return handle_success(resp).await.map(Ok);
+            }
+
+            let server_hint_maybe = retry_delay_from_headers(resp.headers());
+            let error_body = read_body(resp.body_mut(), timeouts.read).await?;
+            let error = LlmError::from_status_and_body(status, &error_body);
+
+            Ok(Err((status, error, server_hint_maybe)))
+        })
+        .await;
+
+        let (error, server_hint_maybe) = match attempt {
+            Ok(Ok(result)) => return Ok(result),
+            Ok(Err((status, error, server_hint_maybe))) => {
+                if !retry_policy.is_retryable_status(status) {
+                    return Err(attempts_error(failed_attempts, error));
+                }
+                (error, server_hint_maybe)
+            }
+            Err(error) if error.is_transient() => (error, None),
+            Err(error) => return Err(attempts_error(failed_attempts, error)),
+        };
+
+        let failed_attempt_index = failed_attempts.len() as u32;
+        if failed_attempt_index + 1 >= retry_policy.max_attempts {
+            return Err(attempts_error(failed_attempts, error));
+        }
+        let Some(delay) = retry_policy.delay(failed_attempt_index, server_hint_maybe) else {
+            log::warn!("server asks to wait longer than the retry policy allows: {error}");
+            return Err(attempts_error(failed_attempts, error));
+        };
+        failed_attempts.push(error);
+
+        sleep(delay).await;
+    }
+}
+
+/// the final error of a request, listing all attempts when it was retried
+fn attempts_error(mut failed_attempts: Vec<LlmError>, last_error: LlmError) -> LlmError {
+    if failed_attempts.is_empty() {
+        last_error
+    } else {
+        failed_attempts.push(last_error);
+        LlmError::AttemptsFailed(failed_attempts)
+    }
+}
@@ -0,0 +1,89 @@
+/// a single server-sent event, only the fields the LLM providers use
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ServerSentEvent {
+    /// value of the `event:` field, `None` for the default `message` event
+    pub event: Option<String>,
+
+    /// the `data:` lines of the event joined with `\n`
+    pub data: String,
+}
+
+/// incremental parser for a `text/event-stream` body. Bytes are buffered until
+/// a full line is available, so events and even multi byte characters may be
+/// split across any number of reads.
+#[derive(Debug, Default)]
+pub(crate) struct ServerSentEventParser {
+    buffer: Vec<u8>,
+    event: Option<String>,
+    data_lines: Vec<String>,
+}
+
+impl ServerSentEventParser {
+    /// feeds the next chunk of the body and returns the events it completed
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<ServerSentEvent> {
+        self.buffer.extend_from_slice(chunk);
+
+        let mut events = vec![];
+        while let Some(newline_index) = self.buffer.iter().position(|byte| *byte == b'\n') {
+            let mut line: Vec<u8> = self.buffer.drain(..=newline_index).collect();
+            line.pop();
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+
+            if let Some(event) = self.process_line(&String::from_utf8_lossy(&line)) {
+                events.push(event);
+            }
+        }
+
+        events
+    }
+
+    /// dispatches an event that was not followed by a blank line before the
+    /// body ended
+    pub fn finish(&mut self) -> Option<ServerSentEvent> {
+        let rest = std::mem::take(&mut self.buffer);
+        if !rest.is_empty() {
+            if let Some(event) = self.process_line(&String::from_utf8_lossy(&rest)) {
+                return Some(event);
+            }
+        }
+        self.dispatch()
+    }
+
+    fn process_line(&mut self, line: &str) -> Option<ServerSentEvent> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+
+        // lines starting with a colon are comments, often used as keep-alive
+        if line.starts_with(':') {
+            return None;
+        }
+
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+
+        match field {
+            "event" => self.event = Some(value.to_string()),
+            "data" => self.data_lines.push(value.to_string()),
+            _ => {}
+        }
+
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<ServerSentEvent> {
+        let event = self.event.take();
+        if self.data_lines.is_empty() {
+            return None;
+        }
+
+        Some(ServerSentEvent {
+            event,
+            data: std::mem::take(&mut self.data_lines).join("\n"),
+        })
+    }
+}
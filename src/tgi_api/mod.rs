@@ -0,0 +1,3 @@
+mod tgi;
+
+pub use tgi::{TgiClient, TgiClientBuilder, TgiDetails, TgiGenerateResponseBody, TgiToken};
@@ -0,0 +1,147 @@
+use super::TgiClient;
+use crate::http_client::build_http_client;
+use crate::{
+    ChatTemplate, CredentialProvider, Credentials, GenerationLimits, GenerationParams, LlmError,
+    RetryPolicy, Timeouts, Tokenizer,
+};
+use std::sync::Arc;
+
+const TGI_BASE_URL: &str = "http://localhost:8080";
+
+/// builds a [`TgiClient`] for a Text Generation Inference server or a
+/// Hugging Face Inference Endpoint.
+///
+/// # Usage
+/// ```no_run
+/// let tgi_client = TgiClient::builder()
+///     .base_url("https://my-endpoint.endpoints.huggingface.cloud")
+///     .token(hf_token)
+///     .chat_template(ChatTemplate::ChatMl)
+///     .top_n_tokens(5)
+///     .build()?;
+/// ```
+#[derive(Default)]
+pub struct TgiClientBuilder {
+    base_url_maybe: Option<String>,
+    model_maybe: Option<String>,
+    credential_provider_maybe: Option<Arc<dyn CredentialProvider>>,
+    chat_template: ChatTemplate,
+    generation_params: GenerationParams,
+    top_n_tokens_maybe: Option<u32>,
+    retry_policy: RetryPolicy,
+    timeouts: Timeouts,
+    tokenizer: Tokenizer,
+}
+
+impl TgiClientBuilder {
+    /// URL `/generate` and `/generate_stream` are appended to, defaults to
+    /// `http://localhost:8080`. Plain HTTP is allowed for local servers.
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url_maybe = Some(base_url.into());
+        self
+    }
+
+    /// name the served model is reported as, TGI serves the model it was
+    /// started with. Defaults to `tgi`.
+    pub fn model(mut self, model: impl Into<String>) -> Self {
+        self.model_maybe = Some(model.into());
+        self
+    }
+
+    /// Hugging Face token sent as bearer token, shorthand for a static
+    /// [`Credentials`]. A local server needs none.
+    pub fn token(self, token: impl Into<String>) -> Self {
+        self.credential_provider(Credentials::new(token))
+    }
+
+    /// where the bearer token comes from, asked before every attempt
+    pub fn credential_provider(
+        mut self,
+        credential_provider: impl CredentialProvider + 'static,
+    ) -> Self {
+        self.credential_provider_maybe = Some(Arc::new(credential_provider));
+        self
+    }
+
+    /// how the chat is turned into the raw prompt, has to match the served
+    /// model. Defaults to [`ChatTemplate::Llama2`].
+    pub fn chat_template(mut self, chat_template: ChatTemplate) -> Self {
+        self.chat_template = chat_template;
+        self
+    }
+
+    /// sampling parameters of every request, which a request can override.
+    /// `n`, `presence_penalty` and `logit_bias` are not supported and `user`
+    /// is not sent.
+    pub fn generation_params(mut self, generation_params: GenerationParams) -> Self {
+        self.generation_params = generation_params;
+        self
+    }
+
+    /// upper bound for the generated tokens of every request, shorthand for
+    /// the `max_tokens` of the [`GenerationParams`]
+    pub fn max_tokens(mut self, max_tokens: u32) -> Self {
+        self.generation_params.max_tokens = Some(max_tokens);
+        self
+    }
+
+    /// how many of the most likely tokens are returned for every generated
+    /// token, see [`crate::TokenLogprob::top_logprobs`]
+    pub fn top_n_tokens(mut self, top_n_tokens: u32) -> Self {
+        self.top_n_tokens_maybe = Some(top_n_tokens);
+        self
+    }
+
+    /// how failed requests are retried, defaults to [`RetryPolicy::default`]
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// connect, read and overall timeouts, defaults to [`Timeouts::default`]
+    pub fn timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// tokenizer for [`crate::LlmClient::count_tokens`], defaults to an
+    /// estimate
+    pub fn tokenizer(mut self, tokenizer: impl Into<Tokenizer>) -> Self {
+        self.tokenizer = tokenizer.into();
+        self
+    }
+
+    pub fn build(self) -> Result<TgiClient, LlmError> {
+        let base_url = self
+            .base_url_maybe
+            .as_deref()
+            .unwrap_or(TGI_BASE_URL)
+            .trim_end_matches('/')
+            .to_string();
+
+        let uri: hyper::Uri = base_url.parse().map_err(|error| {
+            LlmError::InvalidRequest(format!("invalid base url {base_url}: {error}"))
+        })?;
+        if !matches!(uri.scheme_str(), Some("http") | Some("https")) {
+            return Err(LlmError::InvalidRequest(format!(
+                "base url {base_url} must start with http:// or https://"
+            )));
+        }
+
+        self.generation_params
+            .validate(&GenerationLimits::TGI, None)?;
+
+        Ok(TgiClient {
+            base_url,
+            model: self.model_maybe.unwrap_or_else(|| "tgi".to_string()),
+            credential_provider_maybe: self.credential_provider_maybe,
+            chat_template: self.chat_template,
+            generation_params: self.generation_params,
+            top_n_tokens_maybe: self.top_n_tokens_maybe,
+            retry_policy: self.retry_policy,
+            timeouts: self.timeouts,
+            tokenizer: self.tokenizer,
+            http_client: build_http_client(true, &self.timeouts),
+        })
+    }
+}
@@ -0,0 +1,118 @@
+use super::{TgiDetails, TgiToken};
+use crate::server_sent_events::ServerSentEventParser;
+use crate::timeouts::with_timeout;
+use crate::{ChatStream, LlmError, TimeoutKind};
+use futures::StreamExt;
+use hyper::body::HttpBody;
+use hyper::Body;
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// an event of `/generate_stream`, the last one carries the details
+#[derive(Deserialize, Debug)]
+struct StreamToken {
+    token: Option<TgiToken>,
+    details: Option<TgiDetails>,
+
+    /// sent instead of a token when generation failed
+    error: Option<String>,
+}
+
+struct StreamState {
+    body: Body,
+    parser: ServerSentEventParser,
+    pending: VecDeque<Result<String, LlmError>>,
+    done: bool,
+    read_timeout: Option<Duration>,
+}
+
+impl StreamState {
+    /// queues the text of the token, an error or the details end the stream
+    fn process(&mut self, data: &str) {
+        let data = data.trim();
+        if data.is_empty() || self.done {
+            return;
+        }
+
+        let stream_token = match serde_json::from_str::<StreamToken>(data) {
+            Ok(stream_token) => stream_token,
+            Err(error) => {
+                self.fail(LlmError::Decode(format!(
+                    "invalid stream event {data}: {error}"
+                )));
+                return;
+            }
+        };
+
+        if let Some(error) = stream_token.error {
+            self.fail(LlmError::HttpStatus {
+                status: 200,
+                body: error,
+            });
+            return;
+        }
+
+        if let Some(token) = stream_token.token {
+            if !token.special && !token.text.is_empty() {
+                self.pending.push_back(Ok(token.text));
+            }
+        }
+        if stream_token.details.is_some() {
+            self.done = true;
+        }
+    }
+
+    fn fail(&mut self, error: LlmError) {
+        self.pending.push_back(Err(error));
+        self.done = true;
+    }
+}
+
+/// the text of the generated tokens. A stream that ends before the details
+/// arrived fails with [`LlmError::Transport`].
+pub(super) fn text_stream(body: Body, read_timeout: Option<Duration>) -> ChatStream {
+    let state = StreamState {
+        body,
+        parser: ServerSentEventParser::default(),
+        pending: VecDeque::new(),
+        done: false,
+        read_timeout,
+    };
+
+    futures::stream::unfold(state, |mut state| async move {
+        loop {
+            if let Some(item) = state.pending.pop_front() {
+                return Some((item, state));
+            }
+            if state.done {
+                return None;
+            }
+
+            let read_result = with_timeout(state.read_timeout, TimeoutKind::Read, async {
+                state.body.data().await.transpose().map_err(LlmError::from)
+            })
+            .await;
+
+            match read_result {
+                Ok(Some(bytes)) => {
+                    for event in state.parser.feed(&bytes) {
+                        state.process(&event.data);
+                    }
+                }
+                Ok(None) => {
+                    if let Some(event) = state.parser.finish() {
+                        state.process(&event.data);
+                    }
+                    if !state.done {
+                        state.fail(LlmError::Transport(
+                            "TGI stream ended before the details".to_string(),
+                        ));
+                    }
+                }
+                Err(error) => state.fail(error),
+            }
+        }
+    })
+    .boxed()
+}
@@ -0,0 +1,373 @@
+mod client_builder;
+mod generate_stream;
+
+pub use client_builder::TgiClientBuilder;
+
+use crate::http_client::HttpClient;
+use crate::retry_policy::send_with_retries;
+use crate::timeouts::{read_body, with_timeout};
+use crate::{
+    ChatResponse, ChatStream, ChatTemplate, CredentialProvider, GenerationLimits, GenerationParams,
+    LlmClient, LlmError, PromptType, ResponseFormat, RetryPolicy, TimeoutKind, Timeouts,
+    TokenLogprob, Tokenizer, Usage,
+};
+use async_trait::async_trait;
+use hyper::header::HeaderMap;
+use hyper::{Body, Method, Request, Response};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+
+/// a token as TGI reports it
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct TgiToken {
+    pub id: u32,
+    pub text: String,
+
+    /// missing for tokens of the prompt the model did not score, e.g. the
+    /// first one
+    pub logprob: Option<f64>,
+
+    /// e.g. the end of sequence token
+    #[serde(default)]
+    pub special: bool,
+}
+
+/// the `details` of a generation, requested with every call
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct TgiDetails {
+    /// `length`, `eos_token` or `stop_sequence`
+    pub finish_reason: String,
+    pub generated_tokens: u64,
+    pub seed: Option<u64>,
+
+    /// the tokens of the prompt, only with `decoder_input_details`
+    #[serde(default)]
+    pub prefill: Vec<TgiToken>,
+
+    #[serde(default)]
+    pub tokens: Vec<TgiToken>,
+
+    /// for every generated token the most likely ones, with `top_n_tokens`
+    #[serde(default)]
+    pub top_tokens: Vec<Vec<TgiToken>>,
+}
+
+impl TgiDetails {
+    /// the generated tokens in the crate's terms
+    pub fn token_logprobs(&self) -> Vec<TokenLogprob> {
+        self.tokens
+            .iter()
+            .enumerate()
+            .map(|(index, token)| TokenLogprob {
+                token: token.text.clone(),
+                logprob: token.logprob.unwrap_or(0.0),
+                token_id_maybe: Some(token.id),
+                top_logprobs: self
+                    .top_tokens
+                    .get(index)
+                    .into_iter()
+                    .flatten()
+                    .map(|top_token| (top_token.text.clone(), top_token.logprob.unwrap_or(0.0)))
+                    .collect(),
+            })
+            .collect()
+    }
+}
+
+/// the answer of `/generate`
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct TgiGenerateResponseBody {
+    pub generated_text: String,
+    pub details: Option<TgiDetails>,
+
+    /// token counts from the `x-prompt-tokens` and `x-generated-tokens`
+    /// headers
+    #[serde(skip)]
+    pub usage: Option<Usage>,
+}
+
+impl From<TgiGenerateResponseBody> for ChatResponse {
+    fn from(body: TgiGenerateResponseBody) -> Self {
+        ChatResponse {
+            answer: Some(body.generated_text.clone()),
+            answers: vec![body.generated_text],
+            usage: body.usage,
+            cost_usd: None,
+            content_filter_results: vec![],
+            tokens: body
+                .details
+                .as_ref()
+                .map(TgiDetails::token_logprobs)
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct GenerateRequest {
+    inputs: String,
+    parameters: GenerateParameters,
+}
+
+#[derive(Serialize, Debug)]
+struct GenerateParameters {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_new_tokens: Option<u32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    stop: Vec<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frequency_penalty: Option<f32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_n_tokens: Option<u32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    grammar: Option<Value>,
+
+    details: bool,
+    return_full_text: bool,
+}
+
+/// runs prompts on models served by Hugging Face's Text Generation
+/// Inference. The chat is rendered into raw text with the client's
+/// [`ChatTemplate`], which has to match the served model. Cheap to clone,
+/// clones share the connection pool.
+///
+/// # Usage
+/// ```no_run
+/// let tgi_client = TgiClient::builder()
+///     .base_url("http://gpu-box:8080")
+///     .chat_template(ChatTemplate::MistralInstruct)
+///     .build()?;
+///
+/// let chat_response = tgi_client.perform_request(&prompt).await?;
+/// ```
+#[derive(Clone)]
+pub struct TgiClient {
+    base_url: String,
+    model: String,
+    credential_provider_maybe: Option<Arc<dyn CredentialProvider>>,
+    chat_template: ChatTemplate,
+    generation_params: GenerationParams,
+    top_n_tokens_maybe: Option<u32>,
+    retry_policy: RetryPolicy,
+    timeouts: Timeouts,
+    tokenizer: Tokenizer,
+    http_client: HttpClient,
+}
+
+impl TgiClient {
+    pub fn builder() -> TgiClientBuilder {
+        TgiClientBuilder::default()
+    }
+
+    pub fn chat_template(&self) -> ChatTemplate {
+        self.chat_template
+    }
+
+    /// the raw text the prompt is sent as
+    pub fn render(&self, prompt: &PromptType) -> String {
+        self.chat_template.render(&prompt.messages())
+    }
+
+    pub async fn perform_request(&self, prompt: &PromptType) -> Result<ChatResponse, LlmError> {
+        self.perform_request_with_params(prompt, &GenerationParams::default())
+            .await
+    }
+
+    /// same as [`Self::perform_request`] with the parameters set in `params`
+    /// overriding the client's [`GenerationParams`]. An answer not matching
+    /// the prompt's [`ResponseFormat`] is an error.
+    pub async fn perform_request_with_params(
+        &self,
+        prompt: &PromptType,
+        params: &GenerationParams,
+    ) -> Result<ChatResponse, LlmError> {
+        let response_body = self.perform_generate_request(prompt, params).await?;
+
+        if let Some(response_format) = prompt.response_format() {
+            response_format.validate_answer(&response_body.generated_text)?;
+        }
+
+        Ok(response_body.into())
+    }
+
+    /// the generated text with the details of every token, as sent by
+    /// `/generate`
+    pub async fn perform_generate_request(
+        &self,
+        prompt: &PromptType,
+        params: &GenerationParams,
+    ) -> Result<TgiGenerateResponseBody, LlmError> {
+        let body = self.build_body(prompt, params)?;
+
+        let read_timeout = self.timeouts.read;
+        send_with_retries(
+            &self.retry_policy,
+            &self.timeouts,
+            None,
+            0,
+            || self.send("/generate", body.clone()),
+            |mut resp| async move {
+                let usage_maybe = usage_from_headers(resp.headers());
+                let body = read_body(resp.body_mut(), read_timeout).await?;
+                let mut response_body = serde_json::from_slice::<TgiGenerateResponseBody>(&body)?;
+                response_body.usage = usage_maybe;
+                Ok(response_body)
+            },
+        )
+        .await
+    }
+
+    /// streams the text of the answer from `/generate_stream` as it is
+    /// generated, special tokens are left out. Failed attempts to open the
+    /// stream are retried, a stream that broke off is not.
+    pub async fn perform_request_stream(
+        &self,
+        prompt: &PromptType,
+    ) -> Result<ChatStream, LlmError> {
+        let body = self.build_body(prompt, &GenerationParams::default())?;
+
+        let resp = send_with_retries(
+            &self.retry_policy,
+            &self.timeouts,
+            None,
+            0,
+            || self.send("/generate_stream", body.clone()),
+            |resp| async move { Ok(resp) },
+        )
+        .await?;
+
+        Ok(generate_stream::text_stream(
+            resp.into_body(),
+            self.timeouts.read,
+        ))
+    }
+
+    fn build_body(
+        &self,
+        prompt: &PromptType,
+        params: &GenerationParams,
+    ) -> Result<String, LlmError> {
+        let params = self.generation_params.merge(params);
+        params.validate(&GenerationLimits::TGI, None)?;
+
+        let unsupported = [
+            ("n", params.n.is_some_and(|n| n > 1)),
+            ("presence_penalty", params.presence_penalty.is_some()),
+            ("logit_bias", params.logit_bias.is_some()),
+            ("a negative seed", params.seed.is_some_and(|seed| seed < 0)),
+        ];
+        if let Some((name, _)) = unsupported.iter().find(|(_, is_set)| *is_set) {
+            return Err(LlmError::InvalidRequest(format!(
+                "TGI does not support {name}"
+            )));
+        }
+
+        let mut stop = params.stop.unwrap_or_default();
+        for stop_sequence in self.chat_template.stop_sequences() {
+            if !stop.iter().any(|stop| stop == stop_sequence) {
+                stop.push(stop_sequence.to_string());
+            }
+        }
+
+        let grammar = prompt.response_format().map(|response_format| {
+            let schema = match response_format {
+                ResponseFormat::JsonObject => serde_json::json!({"type": "object"}),
+                ResponseFormat::JsonSchema(json_schema) => json_schema.schema.clone(),
+            };
+            serde_json::json!({"type": "json", "value": schema})
+        });
+
+        let request = GenerateRequest {
+            inputs: self.render(prompt),
+            parameters: GenerateParameters {
+                max_new_tokens: params.max_tokens,
+                // TGI samples greedily unless a positive temperature or a
+                // top_p below 1 is given
+                temperature: params.temperature.filter(|temperature| *temperature > 0.0),
+                top_p: params.top_p.filter(|top_p| *top_p < 1.0),
+                stop,
+                seed: params.seed.map(|seed| seed as u64),
+                frequency_penalty: params.frequency_penalty,
+                top_n_tokens: self.top_n_tokens_maybe,
+                grammar,
+                details: true,
+                return_full_text: false,
+            },
+        };
+
+        serde_json::to_string(&request).map_err(|error| LlmError::InvalidRequest(error.to_string()))
+    }
+
+    /// a single attempt of posting the body to the endpoint, returns once
+    /// the response headers arrived
+    async fn send(&self, endpoint_path: &str, body: String) -> Result<Response<Body>, LlmError> {
+        let mut request_builder = Request::builder()
+            .method(Method::POST)
+            .uri(format!("{}{endpoint_path}", self.base_url))
+            .header("content-type", "application/json");
+        if let Some(credential_provider) = &self.credential_provider_maybe {
+            let credentials = credential_provider.credentials()?;
+            request_builder =
+                request_builder.header("Authorization", format!("Bearer {}", credentials.api_key));
+        }
+        let request = request_builder.body(Body::from(body))?;
+
+        with_timeout(self.timeouts.read, TimeoutKind::Read, async {
+            Ok(self.http_client.request(request).await?)
+        })
+        .await
+    }
+}
+
+/// TGI reports the token counts in headers rather than in the body
+fn usage_from_headers(headers: &HeaderMap) -> Option<Usage> {
+    let count = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok())
+    };
+
+    let prompt_tokens = count("x-prompt-tokens")?;
+    let completion_tokens = count("x-generated-tokens")?;
+    Some(Usage {
+        prompt_tokens,
+        completion_tokens,
+        total_tokens: prompt_tokens + completion_tokens,
+        prompt_tokens_details: None,
+    })
+}
+
+#[async_trait]
+impl LlmClient for TgiClient {
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+
+    async fn chat(&self, prompt: &PromptType) -> Result<ChatResponse, LlmError> {
+        self.perform_request(prompt).await
+    }
+
+    async fn chat_stream(&self, prompt: &PromptType) -> Result<ChatStream, LlmError> {
+        self.perform_request_stream(prompt).await
+    }
+
+    /// estimated with the client's tokenizer over the rendered prompt
+    fn count_tokens(&self, prompt: &PromptType) -> usize {
+        self.tokenizer.count_tokens(&self.render(prompt))
+    }
+}
@@ -0,0 +1,94 @@
+use crate::LlmError;
+use hyper::body::{Body, Bytes, HttpBody};
+use std::fmt::{Display, Formatter};
+use std::future::Future;
+use std::time::Duration;
+use tokio::time::timeout;
+
+/// how long a request may take, `None` waits forever
+///
+/// # Usage
+/// ```no_run
+/// let open_ai_client = OpenAiClient::builder()
+///     .timeouts(Timeouts {
+///         overall: Some(Duration::from_secs(30)),
+///         ..Timeouts::default()
+///     })
+///     .build()?;
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Timeouts {
+    /// establishing the TCP connection
+    pub connect: Option<Duration>,
+
+    /// waiting for the response headers and for each following piece of the
+    /// body, which is what bounds a stalled stream
+    pub read: Option<Duration>,
+
+    /// a whole attempt from sending the request to having read the body, for
+    /// streams only until the response headers arrived
+    pub overall: Option<Duration>,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            connect: Some(Duration::from_secs(10)),
+            read: Some(Duration::from_secs(120)),
+            overall: None,
+        }
+    }
+}
+
+/// which of the [`Timeouts`] ran out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeoutKind {
+    Connect,
+    Read,
+    Overall,
+}
+
+impl Display for TimeoutKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Connect => write!(f, "connect"),
+            Self::Read => write!(f, "read"),
+            Self::Overall => write!(f, "overall"),
+        }
+    }
+}
+
+/// awaits the future, failing with a timeout of the given kind when the
+/// duration runs out first
+pub(crate) async fn with_timeout<T>(
+    duration_maybe: Option<Duration>,
+    kind: TimeoutKind,
+    future: impl Future<Output = Result<T, LlmError>>,
+) -> Result<T, LlmError> {
+    match duration_maybe {
+        Some(duration) => timeout(duration, future)
+            .await
+            .map_err(|_| LlmError::Timeout(kind))?,
+        None => future.await,
+    }
+}
+
+/// reads the whole body, the read timeout applies to every single piece
+pub(crate) async fn read_body(
+    body: &mut Body,
+    read_timeout_maybe: Option<Duration>,
+) -> Result<Bytes, LlmError> {
+    let mut collected = Vec::new();
+
+    loop {
+        let data_maybe = with_timeout(read_timeout_maybe, TimeoutKind::Read, async {
+            body.data().await.transpose().map_err(LlmError::from)
+        })
+        .await?;
+
+        match data_maybe {
+            Some(data) => collected.extend_from_slice(&data),
+            None => return Ok(collected.into()),
+        }
+    }
+}
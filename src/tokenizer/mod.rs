@@ -0,0 +1,257 @@
+use crate::{ChatMessage, LlmError};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use fancy_regex::Regex;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+/// pre-tokenization pattern of `cl100k_base`, used by gpt-3.5 and gpt-4
+const CL100K_BASE_PATTERN: &str = r"(?i:'s|'t|'re|'ve|'m|'ll|'d)|[^\r\n\p{L}\p{N}]?\p{L}+|\p{N}{1,3}| ?[^\s\p{L}\p{N}]+[\r\n]*|\s*[\r\n]+|\s+(?!\S)|\s+";
+
+/// pre-tokenization pattern of `o200k_base`, used by gpt-4o and later
+const O200K_BASE_PATTERN: &str = concat!(
+    r"[^\r\n\p{L}\p{N}]?[\p{Lu}\p{Lt}\p{Lm}\p{Lo}\p{M}]*[\p{Ll}\p{Lm}\p{Lo}\p{M}]+(?i:'s|'t|'re|'ve|'m|'ll|'d)?",
+    r"|[^\r\n\p{L}\p{N}]?[\p{Lu}\p{Lt}\p{Lm}\p{Lo}\p{M}]+[\p{Ll}\p{Lm}\p{Lo}\p{M}]*(?i:'s|'t|'re|'ve|'m|'ll|'d)?",
+    r"|\p{N}{1,3}| ?[^\s\p{L}\p{N}]+[\r\n/]*|\s*[\r\n]+|\s+(?!\S)|\s+",
+);
+
+/// rough number of characters per token for [`Tokenizer::Estimate`]
+const CHARACTERS_PER_TOKEN: usize = 4;
+
+/// tokens added for every message of a chat request by the chat format
+const TOKENS_PER_MESSAGE: usize = 3;
+
+/// tokens the reply is primed with, `<|start|>assistant<|message|>`
+const TOKENS_PER_REPLY: usize = 3;
+
+/// the encodings OpenAI uses for its chat models
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenizerEncoding {
+    Cl100kBase,
+    O200kBase,
+}
+
+impl TokenizerEncoding {
+    fn pattern(&self) -> &'static str {
+        match self {
+            Self::Cl100kBase => CL100K_BASE_PATTERN,
+            Self::O200kBase => O200K_BASE_PATTERN,
+        }
+    }
+}
+
+/// byte pair encoding tokenizer compatible with OpenAI's `tiktoken`. The
+/// vocabulary is not shipped with the crate, it is read from a `.tiktoken`
+/// file, e.g. `cl100k_base.tiktoken`, which holds one base64 encoded token and
+/// its rank per line.
+///
+/// # Usage
+/// ```no_run
+/// let bpe_tokenizer =
+///     BpeTokenizer::from_tiktoken_file(TokenizerEncoding::Cl100kBase, "cl100k_base.tiktoken")?;
+/// let token_count = bpe_tokenizer.count_tokens("could you fix this rust code");
+/// ```
+#[derive(Debug)]
+pub struct BpeTokenizer {
+    encoding: TokenizerEncoding,
+    ranks: HashMap<Vec<u8>, u32>,
+    tokens: HashMap<u32, Vec<u8>>,
+    pattern: Regex,
+}
+
+impl BpeTokenizer {
+    pub fn from_tiktoken_file(
+        encoding: TokenizerEncoding,
+        path: impl AsRef<Path>,
+    ) -> Result<Self, LlmError> {
+        let path = path.as_ref();
+        let data = std::fs::read(path).map_err(|error| {
+            LlmError::Tokenizer(format!("could not read {}: {error}", path.display()))
+        })?;
+        Self::from_tiktoken_bytes(encoding, &data)
+    }
+
+    /// builds the tokenizer from the content of a `.tiktoken` file, e.g. one
+    /// embedded with `include_bytes!`
+    pub fn from_tiktoken_bytes(encoding: TokenizerEncoding, data: &[u8]) -> Result<Self, LlmError> {
+        let mut ranks = HashMap::new();
+
+        for (line_index, line) in data.split(|byte| *byte == b'\n').enumerate() {
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+            if line.is_empty() {
+                continue;
+            }
+
+            let invalid_line =
+                || LlmError::Tokenizer(format!("invalid vocabulary line {}", line_index + 1));
+            let separator_index = line
+                .iter()
+                .position(|byte| *byte == b' ')
+                .ok_or_else(invalid_line)?;
+            let token = BASE64
+                .decode(&line[..separator_index])
+                .map_err(|_| invalid_line())?;
+            let rank = std::str::from_utf8(&line[separator_index + 1..])
+                .ok()
+                .and_then(|rank| rank.trim().parse::<u32>().ok())
+                .ok_or_else(invalid_line)?;
+
+            ranks.insert(token, rank);
+        }
+
+        if ranks.is_empty() {
+            return Err(LlmError::Tokenizer("vocabulary is empty".to_string()));
+        }
+
+        let tokens = ranks
+            .iter()
+            .map(|(token, rank)| (*rank, token.clone()))
+            .collect();
+        let pattern = Regex::new(encoding.pattern())
+            .map_err(|error| LlmError::Tokenizer(error.to_string()))?;
+
+        Ok(Self {
+            encoding,
+            ranks,
+            tokens,
+            pattern,
+        })
+    }
+
+    pub fn encoding(&self) -> TokenizerEncoding {
+        self.encoding
+    }
+
+    /// token ids of the text, special tokens are treated as ordinary text
+    pub fn encode(&self, text: &str) -> Vec<u32> {
+        let mut encoded = vec![];
+
+        for piece_match in self.pattern.find_iter(text) {
+            // the patterns have no constructs that can fail on valid UTF-8
+            let Ok(piece_match) = piece_match else {
+                continue;
+            };
+            let piece = piece_match.as_str().as_bytes();
+
+            match self.ranks.get(piece) {
+                Some(rank) => encoded.push(*rank),
+                None => encoded.extend(self.byte_pair_encode(piece)),
+            }
+        }
+
+        encoded
+    }
+
+    /// the text of the token ids, unknown ids are skipped
+    pub fn decode(&self, token_ids: &[u32]) -> String {
+        let bytes: Vec<u8> = token_ids
+            .iter()
+            .filter_map(|token_id| self.tokens.get(token_id))
+            .flatten()
+            .copied()
+            .collect();
+        String::from_utf8_lossy(&bytes).into_owned()
+    }
+
+    pub fn count_tokens(&self, text: &str) -> usize {
+        self.encode(text).len()
+    }
+
+    /// merges the bytes of a piece pairwise, always the pair with the lowest
+    /// rank first, until no pair is in the vocabulary anymore
+    fn byte_pair_encode(&self, piece: &[u8]) -> Vec<u32> {
+        // boundaries of the current parts, part i is piece[parts[i]..parts[i + 1]]
+        let mut parts: Vec<usize> = (0..=piece.len()).collect();
+
+        loop {
+            let lowest_maybe = (0..parts.len().saturating_sub(2))
+                .filter_map(|index| {
+                    self.ranks
+                        .get(&piece[parts[index]..parts[index + 2]])
+                        .map(|rank| (*rank, index))
+                })
+                .min();
+
+            match lowest_maybe {
+                Some((_, index)) => {
+                    parts.remove(index + 1);
+                }
+                None => break,
+            }
+        }
+
+        parts
+            .windows(2)
+            .filter_map(|window| self.ranks.get(&piece[window[0]..window[1]]).copied())
+            .collect()
+    }
+}
+
+/// counts tokens with a BPE vocabulary when one is available, otherwise
+/// estimates them from the length of the text
+#[derive(Debug, Clone, Default)]
+pub enum Tokenizer {
+    /// about four characters per token, good enough to catch prompts that are
+    /// far too long but not exact
+    #[default]
+    Estimate,
+
+    Bpe(Arc<BpeTokenizer>),
+}
+
+impl From<BpeTokenizer> for Tokenizer {
+    fn from(bpe_tokenizer: BpeTokenizer) -> Self {
+        Self::Bpe(Arc::new(bpe_tokenizer))
+    }
+}
+
+impl Tokenizer {
+    pub fn count_tokens(&self, text: &str) -> usize {
+        match self {
+            Self::Estimate => text.chars().count().div_ceil(CHARACTERS_PER_TOKEN),
+            Self::Bpe(bpe_tokenizer) => bpe_tokenizer.count_tokens(text),
+        }
+    }
+
+    /// tokens of a single chat message including the chat format overhead
+    pub fn count_message_tokens(&self, role: &str, content: &str) -> usize {
+        TOKENS_PER_MESSAGE + self.count_tokens(role) + self.count_tokens(content)
+    }
+
+    /// tokens of the prompt of a chat request with the given messages
+    pub fn count_messages_tokens<'m>(
+        &self,
+        messages: impl IntoIterator<Item = (&'m str, &'m str)>,
+    ) -> usize {
+        messages
+            .into_iter()
+            .map(|(role, content)| self.count_message_tokens(role, content))
+            .sum::<usize>()
+            + TOKENS_PER_REPLY
+    }
+
+    /// same as [`Self::count_messages_tokens`] for the messages of a chat
+    pub fn count_chat_tokens(&self, messages: &[ChatMessage]) -> usize {
+        self.count_messages_tokens(messages.iter().map(|message| {
+            (
+                message.role.as_str(),
+                message.content.as_deref().unwrap_or(""),
+            )
+        }))
+    }
+}
+
+/// what happens when a prompt does not fit into the context window of the
+/// model, checked before the request is sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ContextWindowCheck {
+    /// fail with [`LlmError::ContextWindowExceeded`] without calling the API
+    Reject,
+
+    /// log a warning and send the request anyway
+    #[default]
+    Warn,
+
+    Off,
+}
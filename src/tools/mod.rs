@@ -0,0 +1,208 @@
+use futures::future::BoxFuture;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::future::Future;
+use std::sync::Arc;
+
+/// steps of the tool loop when not configured otherwise
+const DEFAULT_MAX_STEPS: usize = 10;
+
+/// a tool as advertised to the model in the `tools` field of a request
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolDefinition {
+    pub name: String,
+    pub description: String,
+
+    /// JSON schema of the arguments object
+    pub parameters: serde_json::Value,
+}
+
+impl Serialize for ToolDefinition {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serde_json::json!({
+            "type": "function",
+            "function": {
+                "name": self.name,
+                "description": self.description,
+                "parameters": self.parameters,
+            }
+        })
+        .serialize(serializer)
+    }
+}
+
+/// a call of a tool requested by the model
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct ToolCall {
+    pub id: String,
+
+    /// always `function`
+    #[serde(rename = "type")]
+    pub call_type: String,
+
+    pub function: FunctionCall,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct FunctionCall {
+    pub name: String,
+
+    /// the arguments as a JSON encoded string, which the model may have
+    /// gotten wrong
+    pub arguments: String,
+}
+
+type ToolHandler = dyn Fn(String) -> BoxFuture<'static, Result<String, String>> + Send + Sync;
+
+struct Tool {
+    definition: ToolDefinition,
+    handler: Arc<ToolHandler>,
+}
+
+/// the Rust functions the model may call, see
+/// [`crate::OpenAiClient::perform_request_with_tools`]. Cheap to clone.
+///
+/// # Usage
+/// ```no_run
+/// #[derive(Deserialize)]
+/// struct WeatherArguments {
+///     city: String,
+/// }
+///
+/// let mut tool_registry = ToolRegistry::default();
+/// tool_registry.register(
+///     "get_weather",
+///     "current weather of a city",
+///     serde_json::json!({
+///         "type": "object",
+///         "properties": {"city": {"type": "string"}},
+///         "required": ["city"]
+///     }),
+///     |arguments: WeatherArguments| async move {
+///         Ok::<_, String>(format!("sunny in {}", arguments.city))
+///     },
+/// );
+///
+/// let response = open_ai_client
+///     .perform_request_with_tools(&prompt, &tool_registry)
+///     .await?;
+/// ```
+#[derive(Clone)]
+pub struct ToolRegistry {
+    tools: BTreeMap<String, Arc<Tool>>,
+    max_steps: usize,
+}
+
+impl Default for ToolRegistry {
+    fn default() -> Self {
+        Self::with_max_steps(DEFAULT_MAX_STEPS)
+    }
+}
+
+impl std::fmt::Debug for ToolRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ToolRegistry")
+            .field("tools", &self.tools.keys().collect::<Vec<_>>())
+            .field("max_steps", &self.max_steps)
+            .finish()
+    }
+}
+
+impl ToolRegistry {
+    /// a registry whose tool loop gives up after `max_steps` requests that
+    /// all asked for tool calls
+    pub fn with_max_steps(max_steps: usize) -> Self {
+        Self {
+            tools: BTreeMap::new(),
+            max_steps,
+        }
+    }
+
+    /// registers an async handler, registering a name again replaces it.
+    /// The arguments sent by the model are deserialized into `A`, use
+    /// [`serde_json::Value`] to take them as they are. The output is sent back
+    /// as is if it serializes to a string and as JSON otherwise.
+    pub fn register<A, R, E, F, Fut>(
+        &mut self,
+        name: impl Into<String>,
+        description: impl Into<String>,
+        parameters: serde_json::Value,
+        handler: F,
+    ) where
+        A: DeserializeOwned + Send + 'static,
+        R: Serialize,
+        E: Display,
+        F: Fn(A) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<R, E>> + Send + 'static,
+    {
+        let name = name.into();
+        let handler = Arc::new(handler);
+        let tool_handler: Arc<ToolHandler> = Arc::new(move |arguments: String| {
+            let handler = handler.clone();
+            Box::pin(async move {
+                let arguments: A = serde_json::from_str(&arguments)
+                    .map_err(|error| format!("invalid arguments: {error}"))?;
+                let output = handler(arguments)
+                    .await
+                    .map_err(|error| error.to_string())?;
+                match serde_json::to_value(output) {
+                    Ok(serde_json::Value::String(output)) => Ok(output),
+                    Ok(output) => Ok(output.to_string()),
+                    Err(error) => Err(format!("could not serialize the output: {error}")),
+                }
+            })
+        });
+
+        let definition = ToolDefinition {
+            name: name.clone(),
+            description: description.into(),
+            parameters,
+        };
+        self.tools.insert(
+            name,
+            Arc::new(Tool {
+                definition,
+                handler: tool_handler,
+            }),
+        );
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.tools.contains_key(name)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tools.is_empty()
+    }
+
+    pub fn max_steps(&self) -> usize {
+        self.max_steps
+    }
+
+    /// the definitions sent with every request, ordered by name
+    pub fn definitions(&self) -> Vec<ToolDefinition> {
+        self.tools
+            .values()
+            .map(|tool| tool.definition.clone())
+            .collect()
+    }
+
+    /// runs the handler of the call and returns the content of the `tool`
+    /// message. Unknown tools, invalid arguments and handler errors are
+    /// reported to the model as `error: ...` so that it can correct itself.
+    pub async fn call(&self, tool_call: &ToolCall) -> String {
+        let Some(tool) = self.tools.get(&tool_call.function.name) else {
+            return format!("error: unknown tool {}", tool_call.function.name);
+        };
+
+        match (tool.handler)(tool_call.function.arguments.clone()).await {
+            Ok(output) => output,
+            Err(error) => format!("error: {error}"),
+        }
+    }
+}
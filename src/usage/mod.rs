@@ -0,0 +1,179 @@
+use crate::ModelRegistry;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ops::AddAssign;
+use std::sync::{Arc, Mutex};
+
+/// the `usage` block of a response
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Default)]
+pub struct Usage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub total_tokens: u64,
+    pub prompt_tokens_details: Option<PromptTokensDetails>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Default)]
+pub struct PromptTokensDetails {
+    /// prompt tokens served from the prompt cache, billed at a lower price
+    #[serde(default)]
+    pub cached_tokens: u64,
+}
+
+impl Usage {
+    pub fn cached_tokens(&self) -> u64 {
+        self.prompt_tokens_details
+            .map(|details| details.cached_tokens)
+            .unwrap_or(0)
+    }
+}
+
+/// sums the usage of several requests, e.g. the steps of a tool loop
+impl AddAssign for Usage {
+    fn add_assign(&mut self, other: Self) {
+        let cached_tokens = self.cached_tokens() + other.cached_tokens();
+
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.total_tokens += other.total_tokens;
+        if self.prompt_tokens_details.is_some() || other.prompt_tokens_details.is_some() {
+            self.prompt_tokens_details = Some(PromptTokensDetails { cached_tokens });
+        }
+    }
+}
+
+/// USD per one million tokens
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelPricing {
+    pub input_per_million: f64,
+
+    /// price of cached prompt tokens, the input price when the model has no
+    /// discount for them
+    pub cached_input_per_million: Option<f64>,
+
+    pub output_per_million: f64,
+}
+
+impl ModelPricing {
+    /// cost of the usage in USD
+    pub fn cost(&self, usage: &Usage) -> f64 {
+        let cached_tokens = usage.cached_tokens().min(usage.prompt_tokens);
+        let uncached_tokens = usage.prompt_tokens - cached_tokens;
+        let cached_input_per_million = self
+            .cached_input_per_million
+            .unwrap_or(self.input_per_million);
+
+        (uncached_tokens as f64 * self.input_per_million
+            + cached_tokens as f64 * cached_input_per_million
+            + usage.completion_tokens as f64 * self.output_per_million)
+            / 1_000_000.0
+    }
+}
+
+/// aggregated usage and spend of a model or a tag
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct LedgerEntry {
+    pub requests: u64,
+    pub prompt_tokens: u64,
+    pub cached_tokens: u64,
+    pub completion_tokens: u64,
+
+    /// USD, requests to models without known pricing count as zero
+    pub cost_usd: f64,
+}
+
+impl LedgerEntry {
+    fn add(&mut self, usage: &Usage, cost_usd: f64) {
+        self.requests += 1;
+        self.prompt_tokens += usage.prompt_tokens;
+        self.cached_tokens += usage.cached_tokens();
+        self.completion_tokens += usage.completion_tokens;
+        self.cost_usd += cost_usd;
+    }
+}
+
+/// collects the usage and cost of every successful request. Clones share the
+/// same ledger, so one ledger can be attached to several clients.
+///
+/// # Usage
+/// ```no_run
+/// let cost_ledger = CostLedger::default();
+/// let open_ai_client = OpenAiClient::builder()
+///     .cost_ledger(cost_ledger.clone())
+///     .build()?;
+///
+/// // spend can additionally be grouped by a tag chosen by the caller
+/// let ingest_client = open_ai_client.with_tag("ingest");
+///
+/// println!("spent {} USD", cost_ledger.total().cost_usd);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CostLedger {
+    entries: Arc<Mutex<CostLedgerEntries>>,
+}
+
+#[derive(Debug, Default)]
+struct CostLedgerEntries {
+    total: LedgerEntry,
+    by_model: HashMap<String, LedgerEntry>,
+    by_tag: HashMap<String, LedgerEntry>,
+}
+
+impl CostLedger {
+    pub fn record(
+        &self,
+        model: &str,
+        tag_maybe: Option<&str>,
+        usage: &Usage,
+        cost_usd_maybe: Option<f64>,
+    ) {
+        let cost_usd = cost_usd_maybe.unwrap_or(0.0);
+        let mut entries = self.entries.lock().unwrap();
+
+        entries.total.add(usage, cost_usd);
+        entries
+            .by_model
+            .entry(model.to_string())
+            .or_default()
+            .add(usage, cost_usd);
+        if let Some(tag) = tag_maybe {
+            entries
+                .by_tag
+                .entry(tag.to_string())
+                .or_default()
+                .add(usage, cost_usd);
+        }
+    }
+
+    pub fn total(&self) -> LedgerEntry {
+        self.entries.lock().unwrap().total
+    }
+
+    /// keyed by the model name the provider reported
+    pub fn by_model(&self) -> HashMap<String, LedgerEntry> {
+        self.entries.lock().unwrap().by_model.clone()
+    }
+
+    pub fn by_tag(&self) -> HashMap<String, LedgerEntry> {
+        self.entries.lock().unwrap().by_tag.clone()
+    }
+}
+
+/// records the usage of a response to the ledger, if there is one, and
+/// returns its cost
+pub(crate) fn record_usage(
+    model_registry: &ModelRegistry,
+    cost_ledger_maybe: Option<&CostLedger>,
+    tag_maybe: Option<&str>,
+    model_name: &str,
+    usage_maybe: Option<&Usage>,
+) -> Option<f64> {
+    let usage = usage_maybe?;
+    let cost_usd_maybe = model_registry.cost_usd(model_name, usage);
+
+    if let Some(cost_ledger) = cost_ledger_maybe {
+        cost_ledger.record(model_name, tag_maybe, usage, cost_usd_maybe);
+    }
+
+    cost_usd_maybe
+}
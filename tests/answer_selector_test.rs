@@ -0,0 +1,100 @@
+mod mock_server;
+
+use rust_llm_utils::{normalize_answer, AnswerSelector, GenerationParams, PromptType};
+
+use mock_server::{open_ai_client_for, MockResponse, MockServer};
+
+fn completion_with_choices(answers: &[&str]) -> MockResponse {
+    // choices are not guaranteed to arrive in index order
+    let choices: Vec<serde_json::Value> = answers
+        .iter()
+        .enumerate()
+        .rev()
+        .map(|(index, answer)| {
+            serde_json::json!({
+                "index": index,
+                "message": {"role": "assistant", "content": answer},
+                "finish_reason": "stop"
+            })
+        })
+        .collect();
+    let body = serde_json::json!({
+        "id": "chatcmpl-mock",
+        "object": "chat.completion",
+        "created": 1_700_000_000u64,
+        "model": "gpt-4o-mini",
+        "choices": choices,
+    });
+    MockResponse::json(200, body.to_string())
+}
+
+fn answers(answers: &[&str]) -> Vec<String> {
+    answers.iter().map(|answer| answer.to_string()).collect()
+}
+
+#[tokio::test]
+async fn should_return_all_choices_and_select_by_majority_vote() {
+    let mock_server = MockServer::start(vec![completion_with_choices(&[
+        "Negative",
+        "positive.",
+        "Positive",
+        "neutral",
+        "  POSITIVE ",
+    ])])
+    .await;
+    let open_ai_client = open_ai_client_for(&mock_server)
+        .generation_params(GenerationParams::default().temperature(0.7).n(5))
+        .answer_selector(AnswerSelector::MajorityVote)
+        .build()
+        .unwrap();
+
+    let prompt = PromptType::new_zero_shot_prompt("Sentiment of: I love it".to_string());
+    let response = open_ai_client.perform_request(&prompt).await.unwrap();
+
+    assert_eq!(mock_server.requests()[0].json_body()["n"], 5);
+    assert_eq!(response.answers.len(), 5);
+    assert_eq!(response.answers[0], "Negative");
+    assert_eq!(response.answer.as_deref(), Some("positive."));
+    assert_eq!(response.select(&AnswerSelector::First), Some("Negative"));
+    assert_eq!(
+        response.select(&AnswerSelector::Longest),
+        Some("  POSITIVE ")
+    );
+}
+
+#[tokio::test]
+async fn should_select_the_first_choice_by_default() {
+    let mock_server = MockServer::start(vec![completion_with_choices(&["a", "bb"])]).await;
+    let open_ai_client = open_ai_client_for(&mock_server).build().unwrap();
+
+    let prompt = PromptType::new_zero_shot_prompt("pick".to_string());
+    let response = open_ai_client.perform_request(&prompt).await.unwrap();
+    assert_eq!(response.answer.as_deref(), Some("a"));
+
+    let longest_client = open_ai_client.with_answer_selector(AnswerSelector::Longest);
+    let response = longest_client.perform_request(&prompt).await.unwrap();
+    assert_eq!(response.answer.as_deref(), Some("bb"));
+}
+
+#[test]
+fn should_break_majority_ties_by_first_occurrence() {
+    let tied = answers(&["b", "a", "a", "b", "c"]);
+    assert_eq!(AnswerSelector::MajorityVote.select(&tied), Some("b"));
+}
+
+#[test]
+fn should_select_with_a_custom_closure() {
+    let last = AnswerSelector::custom(|answers| answers.len().checked_sub(1));
+    assert_eq!(last.select(&answers(&["a", "b", "c"])), Some("c"));
+    assert_eq!(last.select(&[]), None);
+
+    let out_of_range = AnswerSelector::custom(|_| Some(10));
+    assert_eq!(out_of_range.select(&answers(&["a"])), None);
+}
+
+#[test]
+fn should_normalize_answers() {
+    assert_eq!(normalize_answer("  Yes.\n"), "yes");
+    assert_eq!(normalize_answer("\"Not  sure\"!"), "not sure");
+    assert_eq!(AnswerSelector::First.select(&[]), None);
+}
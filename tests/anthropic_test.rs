@@ -0,0 +1,355 @@
+mod mock_server;
+#[allow(dead_code)]
+mod topic_prompts;
+
+use futures::StreamExt;
+use rust_llm_utils::{
+    AnthropicClient, AnthropicStopReason, AnthropicStreamEvent, ContentFilterStage, CostLedger,
+    GenerationParams, LlmClient, LlmError, MultiShotExampleCount, PromptType, RetryPolicy,
+};
+
+use mock_server::{weather_conversation, MockResponse, MockServer};
+use topic_prompts::test_prompts::{
+    MultiShotQuestionsAndAnswersWeatherInTwoLanguages, WeatherInTwoLanguages,
+};
+use topic_prompts::TopicPrompt;
+
+fn client_for(mock_server: &MockServer) -> AnthropicClient {
+    AnthropicClient::builder()
+        .base_url(mock_server.base_url())
+        .allow_http(true)
+        .token("test-key")
+        .retry_policy(RetryPolicy::none())
+        .build()
+        .unwrap()
+}
+
+fn messages_response(content: serde_json::Value, stop_reason: &str) -> MockResponse {
+    let body = serde_json::json!({
+        "id": "msg_01",
+        "type": "message",
+        "role": "assistant",
+        "model": "claude-3-5-sonnet-20241022",
+        "content": content,
+        "stop_reason": stop_reason,
+        "stop_sequence": null,
+        "usage": {
+            "input_tokens": 100,
+            "output_tokens": 20,
+            "cache_creation_input_tokens": 0,
+            "cache_read_input_tokens": 1000
+        }
+    });
+    MockResponse::json(200, body.to_string())
+}
+
+#[tokio::test]
+async fn should_run_a_topic_prompt_against_claude() {
+    let prompt = WeatherInTwoLanguages::new_from_prompt_template(
+        "it is -8c and snowing in Berlin".to_string(),
+    );
+    let prompt_wrapped_in_prompt_type = PromptType::new_multi_shot_prompt(
+        prompt.query(),
+        MultiShotQuestionsAndAnswersWeatherInTwoLanguages {},
+        MultiShotExampleCount::Tree,
+    );
+    let mock_server = MockServer::start(vec![messages_response(
+        serde_json::json!([
+            {"type": "text", "text": "it seems like winter weather. "},
+            {"type": "text", "text": "Es sieht aus wie Winterwetter ❄️"}
+        ]),
+        "end_turn",
+    )])
+    .await;
+
+    let chat_response = client_for(&mock_server)
+        .perform_request(&prompt_wrapped_in_prompt_type)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        chat_response.answer.as_deref(),
+        Some("it seems like winter weather. Es sieht aus wie Winterwetter ❄️")
+    );
+
+    let request = &mock_server.requests()[0];
+    assert_eq!(request.path, "/v1/messages");
+    assert_eq!(request.header("x-api-key").as_deref(), Some("test-key"));
+    assert_eq!(
+        request.header("anthropic-version").as_deref(),
+        Some("2023-06-01")
+    );
+    assert_eq!(request.header("authorization"), None);
+
+    let body = request.json_body();
+    assert_eq!(body["model"], "claude-3-5-sonnet-latest");
+    assert_eq!(body["max_tokens"], 8_192);
+    assert_eq!(body["messages"][0]["role"], "user");
+    assert!(body["messages"][0]["content"]
+        .as_str()
+        .unwrap()
+        .contains("snowing in Berlin"));
+    assert!(body.get("system").is_none());
+}
+
+#[tokio::test]
+async fn should_send_system_prompt_top_level_and_map_usage() {
+    let mock_server = MockServer::start(vec![messages_response(
+        serde_json::json!([
+            {"type": "text", "text": "Es schneit."},
+            {"type": "tool_use", "id": "toolu_01", "name": "get_weather", "input": {"city": "Berlin"}}
+        ]),
+        "end_turn",
+    )])
+    .await;
+    let cost_ledger = CostLedger::default();
+    let anthropic_client = AnthropicClient::builder()
+        .base_url(mock_server.base_url())
+        .allow_http(true)
+        .token("test-key")
+        .max_tokens(256)
+        .cost_ledger(cost_ledger.clone())
+        .build()
+        .unwrap();
+
+    let chat_response = anthropic_client
+        .chat(&weather_conversation())
+        .await
+        .unwrap();
+
+    assert_eq!(chat_response.answer.as_deref(), Some("Es schneit."));
+    let usage = chat_response.usage.unwrap();
+    assert_eq!(usage.prompt_tokens, 1_100);
+    assert_eq!(usage.cached_tokens(), 1_000);
+    assert_eq!(usage.completion_tokens, 20);
+    // 100 * $3 + 1000 * $0.30 + 20 * $15 per million tokens
+    let cost_usd = chat_response.cost_usd.unwrap();
+    assert!((cost_usd - 0.0009).abs() < 1e-9, "{cost_usd}");
+    assert_eq!(cost_ledger.total().requests, 1);
+
+    let body = mock_server.requests()[0].json_body();
+    assert_eq!(body["system"], "Answer in German.");
+    assert_eq!(body["max_tokens"], 256);
+    assert_eq!(
+        body["messages"],
+        serde_json::json!([{"role": "user", "content": "What is the weather like in Berlin?"}])
+    );
+}
+
+#[tokio::test]
+async fn should_expose_content_blocks_and_stop_reason() {
+    let mock_server = MockServer::start(vec![messages_response(
+        serde_json::json!([{"type": "text", "text": "Es schnei"}]),
+        "max_tokens",
+    )])
+    .await;
+
+    let response_body = client_for(&mock_server)
+        .perform_messages_request(&weather_conversation(), &GenerationParams::default())
+        .await
+        .unwrap();
+
+    assert_eq!(
+        response_body.stop_reason,
+        Some(AnthropicStopReason::MaxTokens)
+    );
+    assert_eq!(response_body.text(), "Es schnei");
+}
+
+#[tokio::test]
+async fn should_return_refusals_as_content_filtered() {
+    let mock_server =
+        MockServer::start(vec![messages_response(serde_json::json!([]), "refusal")]).await;
+
+    let error = client_for(&mock_server)
+        .perform_request(&weather_conversation())
+        .await
+        .unwrap_err();
+
+    assert!(matches!(
+        error,
+        LlmError::ContentFiltered {
+            stage: ContentFilterStage::Completion,
+            ..
+        }
+    ));
+}
+
+#[tokio::test]
+async fn should_parse_error_payloads() {
+    let mock_server = MockServer::start(vec![MockResponse::json(
+        400,
+        r#"{"type": "error", "error": {"type": "invalid_request_error", "message": "max_tokens: Field required"}}"#,
+    )])
+    .await;
+
+    let error = client_for(&mock_server)
+        .perform_request(&weather_conversation())
+        .await
+        .unwrap_err();
+
+    assert!(matches!(
+        error,
+        LlmError::Provider { status: 400, ref error } if error.error_type.as_deref() == Some("invalid_request_error")
+    ));
+}
+
+#[tokio::test]
+async fn should_retry_when_overloaded() {
+    let mock_server = MockServer::start(vec![
+        MockResponse::json(
+            529,
+            r#"{"type": "error", "error": {"type": "overloaded_error", "message": "Overloaded"}}"#,
+        ),
+        messages_response(
+            serde_json::json!([{"type": "text", "text": "Es schneit."}]),
+            "end_turn",
+        ),
+    ])
+    .await;
+    let anthropic_client = AnthropicClient::builder()
+        .base_url(mock_server.base_url())
+        .allow_http(true)
+        .token("test-key")
+        .build()
+        .unwrap();
+
+    let chat_response = anthropic_client
+        .perform_request(&weather_conversation())
+        .await
+        .unwrap();
+
+    assert_eq!(chat_response.answer.as_deref(), Some("Es schneit."));
+    assert_eq!(mock_server.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn should_reject_parameters_the_messages_api_lacks() {
+    let mock_server = MockServer::start(vec![]).await;
+    let params = GenerationParams {
+        seed: Some(7),
+        ..Default::default()
+    };
+
+    let error = client_for(&mock_server)
+        .perform_request_with_params(&weather_conversation(), &params)
+        .await
+        .unwrap_err();
+
+    assert!(matches!(error, LlmError::InvalidRequest(message) if message.contains("seed")));
+    assert!(mock_server.requests().is_empty());
+}
+
+const WEATHER_EVENT_STREAM: &str = concat!(
+    "event: message_start\n",
+    "data: {\"type\":\"message_start\",\"message\":{\"id\":\"msg_01\",\"type\":\"message\",\"role\":\"assistant\",\"content\":[],\"model\":\"claude-3-5-sonnet-20241022\",\"stop_reason\":null,\"stop_sequence\":null,\"usage\":{\"input_tokens\":25,\"output_tokens\":1}}}\n\n",
+    "event: content_block_start\n",
+    "data: {\"type\":\"content_block_start\",\"index\":0,\"content_block\":{\"type\":\"text\",\"text\":\"\"}}\n\n",
+    "event: ping\n",
+    "data: {\"type\": \"ping\"}\n\n",
+    "event: content_block_delta\n",
+    "data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"Es sieht aus \"}}\n\n",
+    "event: content_block_delta\n",
+    "data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"wie Winterwetter ❄️\"}}\n\n",
+    "event: content_block_stop\n",
+    "data: {\"type\":\"content_block_stop\",\"index\":0}\n\n",
+    "event: message_delta\n",
+    "data: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"end_turn\",\"stop_sequence\":null},\"usage\":{\"output_tokens\":15}}\n\n",
+    "event: message_stop\n",
+    "data: {\"type\":\"message_stop\"}\n\n",
+);
+
+#[tokio::test]
+async fn should_stream_events_and_aggregate_the_answer() {
+    for chunk_size in [1, 7, 4096] {
+        let mock_server = MockServer::start(vec![MockResponse::event_stream(
+            WEATHER_EVENT_STREAM,
+            chunk_size,
+        )])
+        .await;
+
+        let mut message_stream = client_for(&mock_server)
+            .perform_request_stream(&weather_conversation())
+            .await
+            .unwrap();
+
+        let mut events = vec![];
+        while let Some(event) = message_stream.next().await {
+            events.push(event.unwrap());
+        }
+
+        assert_eq!(events.len(), 8, "chunk size {chunk_size}");
+        assert!(matches!(
+            events[0],
+            AnthropicStreamEvent::MessageStart { .. }
+        ));
+        assert_eq!(events[7], AnthropicStreamEvent::MessageStop);
+        assert_eq!(
+            message_stream.stop_reason(),
+            Some(AnthropicStopReason::EndTurn)
+        );
+
+        let chat_response = message_stream.response();
+        assert_eq!(
+            chat_response.answer.as_deref(),
+            Some("Es sieht aus wie Winterwetter ❄️")
+        );
+        let usage = chat_response.usage.unwrap();
+        assert_eq!((usage.prompt_tokens, usage.completion_tokens), (25, 15));
+        assert_eq!(mock_server.requests()[0].json_body()["stream"], true);
+    }
+}
+
+#[tokio::test]
+async fn should_stream_text_through_llm_client() {
+    let mock_server =
+        MockServer::start(vec![MockResponse::event_stream(WEATHER_EVENT_STREAM, 16)]).await;
+    let llm_client: &dyn LlmClient = &client_for(&mock_server);
+
+    let pieces: Vec<String> = llm_client
+        .chat_stream(&weather_conversation())
+        .await
+        .unwrap()
+        .map(Result::unwrap)
+        .collect()
+        .await;
+
+    assert_eq!(pieces, vec!["Es sieht aus ", "wie Winterwetter ❄️"]);
+}
+
+#[tokio::test]
+async fn should_return_error_events_and_broken_off_streams() {
+    let event_stream = concat!(
+        "event: content_block_delta\n",
+        "data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"Es\"}}\n\n",
+        "event: error\n",
+        "data: {\"type\":\"error\",\"error\":{\"type\":\"overloaded_error\",\"message\":\"Overloaded\"}}\n\n",
+    );
+    let mock_server = MockServer::start(vec![
+        MockResponse::event_stream(event_stream, 16),
+        MockResponse::event_stream(
+            &WEATHER_EVENT_STREAM[..WEATHER_EVENT_STREAM.find("event: message_stop").unwrap()],
+            4096,
+        ),
+    ])
+    .await;
+    let anthropic_client = client_for(&mock_server);
+
+    let error = anthropic_client
+        .perform_request_stream(&weather_conversation())
+        .await
+        .unwrap()
+        .into_response()
+        .await
+        .unwrap_err();
+    assert!(matches!(error, LlmError::Provider { status: 200, .. }));
+
+    let error = anthropic_client
+        .perform_request_stream(&weather_conversation())
+        .await
+        .unwrap()
+        .into_response()
+        .await
+        .unwrap_err();
+    assert!(matches!(error, LlmError::Transport(_)));
+}
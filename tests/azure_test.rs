@@ -0,0 +1,223 @@
+mod mock_server;
+
+use rust_llm_utils::{
+    AzureConfig, ContentFilterResult, ContentFilterStage, LlmClient, LlmError, OpenAiClient,
+    OpenAiModel,
+};
+
+use mock_server::{open_ai_client_for, ping, MockResponse, MockServer};
+
+fn azure_config() -> AzureConfig {
+    AzureConfig::new("2024-10-21").deployment(OpenAiModel::Gpt4o, "gpt-4o-prod")
+}
+
+fn client_for(mock_server: &MockServer) -> OpenAiClient {
+    open_ai_client_for(mock_server)
+        .base_url(mock_server.url())
+        .token("azure-key")
+        .model(OpenAiModel::Gpt4o)
+        .azure(azure_config())
+        .build()
+        .unwrap()
+}
+
+/// a completion whose choices finished with the reasons, filtered ones carry
+/// a `hate` annotation
+fn completion_with_finish_reasons(finish_reasons: &[&str]) -> MockResponse {
+    let choices: Vec<serde_json::Value> = finish_reasons
+        .iter()
+        .enumerate()
+        .map(|(index, finish_reason)| {
+            let filtered = *finish_reason == "content_filter";
+            serde_json::json!({
+                "index": index,
+                "message": {"role": "assistant", "content": if filtered { "" } else { "pong" }},
+                "finish_reason": finish_reason,
+                "content_filter_results": {
+                    "hate": {"filtered": filtered, "severity": if filtered { "high" } else { "safe" }},
+                    "violence": {"filtered": false, "severity": "safe"}
+                }
+            })
+        })
+        .collect();
+
+    MockResponse::json(
+        200,
+        serde_json::json!({
+            "id": "chatcmpl-azure",
+            "object": "chat.completion",
+            "created": 1_700_000_000u64,
+            "model": "gpt-4o",
+            "choices": choices
+        })
+        .to_string(),
+    )
+}
+
+#[tokio::test]
+async fn should_send_to_the_deployment_with_the_api_key_header() {
+    let mock_server = MockServer::start(vec![MockResponse::chat_completion("pong")]).await;
+    let open_ai_client = client_for(&mock_server);
+
+    let response = open_ai_client.perform_request(&ping()).await.unwrap();
+
+    assert_eq!(response.answer.as_deref(), Some("pong"));
+    let request = &mock_server.requests()[0];
+    assert_eq!(
+        request.path,
+        "/openai/deployments/gpt-4o-prod/chat/completions?api-version=2024-10-21"
+    );
+    assert_eq!(request.header("api-key").as_deref(), Some("azure-key"));
+    assert_eq!(request.header("authorization"), None);
+}
+
+#[tokio::test]
+async fn should_list_the_models_of_the_resource() {
+    let mock_server = MockServer::start(vec![MockResponse::json(
+        200,
+        r#"{"object":"list","data":[{"id":"gpt-4o","object":"model","created":1,"owned_by":"system"}]}"#,
+    )])
+    .await;
+    let open_ai_client = client_for(&mock_server);
+
+    let models = open_ai_client.list_models().await.unwrap();
+
+    assert_eq!(models[0].id, "gpt-4o");
+    assert_eq!(
+        mock_server.requests()[0].path,
+        "/openai/models?api-version=2024-10-21"
+    );
+}
+
+#[test]
+fn should_reject_models_without_a_deployment() {
+    let result = OpenAiClient::builder()
+        .base_url("https://my-resource.openai.azure.com")
+        .token("azure-key")
+        .model(OpenAiModel::Gpt4oMini)
+        .azure(azure_config())
+        .build();
+
+    assert!(matches!(result, Err(LlmError::InvalidRequest(_))));
+}
+
+#[tokio::test]
+async fn should_return_a_filtered_prompt_as_content_filtered() {
+    let mock_server = MockServer::start(vec![MockResponse::json(
+        400,
+        serde_json::json!({
+            "error": {
+                "message": "The response was filtered due to the prompt triggering Azure OpenAI's content management policy.",
+                "type": null,
+                "param": "prompt",
+                "code": "content_filter",
+                "status": 400,
+                "innererror": {
+                    "code": "ResponsibleAIPolicyViolation",
+                    "content_filter_result": {
+                        "hate": {"filtered": false, "severity": "safe"},
+                        "jailbreak": {"filtered": true, "detected": true},
+                        "violence": {"filtered": true, "severity": "medium"}
+                    }
+                }
+            }
+        })
+        .to_string(),
+    )])
+    .await;
+    let open_ai_client = client_for(&mock_server);
+
+    let error = open_ai_client.perform_request(&ping()).await.unwrap_err();
+
+    assert_eq!(
+        error,
+        LlmError::ContentFiltered {
+            stage: ContentFilterStage::Prompt,
+            results: vec![
+                ContentFilterResult {
+                    category: "jailbreak".to_string(),
+                    filtered: true,
+                    severity_maybe: None,
+                },
+                ContentFilterResult {
+                    category: "violence".to_string(),
+                    filtered: true,
+                    severity_maybe: Some("medium".to_string()),
+                },
+            ],
+        }
+    );
+    assert_eq!(
+        error.to_string(),
+        "content filter flagged the prompt: jailbreak, violence (medium)"
+    );
+}
+
+#[tokio::test]
+async fn should_return_a_withheld_completion_as_content_filtered() {
+    let mock_server =
+        MockServer::start(vec![completion_with_finish_reasons(&["content_filter"])]).await;
+    let open_ai_client = client_for(&mock_server);
+
+    let error = open_ai_client.perform_request(&ping()).await.unwrap_err();
+
+    assert_eq!(
+        error,
+        LlmError::ContentFiltered {
+            stage: ContentFilterStage::Completion,
+            results: vec![ContentFilterResult {
+                category: "hate".to_string(),
+                filtered: true,
+                severity_maybe: Some("high".to_string()),
+            }],
+        }
+    );
+}
+
+#[tokio::test]
+async fn should_leave_out_withheld_choices_when_others_remain() {
+    let mock_server = MockServer::start(vec![completion_with_finish_reasons(&[
+        "content_filter",
+        "stop",
+    ])])
+    .await;
+    let open_ai_client = client_for(&mock_server);
+
+    let response = open_ai_client.perform_request(&ping()).await.unwrap();
+
+    assert_eq!(response.answers, vec!["pong".to_string()]);
+    assert_eq!(response.answer.as_deref(), Some("pong"));
+}
+
+#[tokio::test]
+async fn should_return_the_annotations_of_returned_answers() {
+    let body = serde_json::json!({
+        "id": "chatcmpl-azure",
+        "object": "chat.completion",
+        "created": 1_700_000_000u64,
+        "model": "gpt-4o",
+        "choices": [{
+            "index": 0,
+            "message": {"role": "assistant", "content": "pong"},
+            "finish_reason": "stop",
+            "content_filter_results": {
+                "hate": {"filtered": false, "severity": "safe"},
+                "protected_material_text": {"filtered": false, "detected": true}
+            }
+        }]
+    });
+    let mock_server = MockServer::start(vec![MockResponse::json(200, body.to_string())]).await;
+    let open_ai_client = client_for(&mock_server);
+
+    let chat_response = LlmClient::chat(&open_ai_client, &ping()).await.unwrap();
+
+    assert_eq!(chat_response.answer.as_deref(), Some("pong"));
+    assert_eq!(
+        chat_response.content_filter_results,
+        vec![ContentFilterResult {
+            category: "protected_material_text".to_string(),
+            filtered: false,
+            severity_maybe: None,
+        }]
+    );
+}
@@ -0,0 +1,106 @@
+mod mock_server;
+
+use rust_llm_utils::{ChatMessage, ChatRole, Conversation, OpenAiClient, PromptType, Tokenizer};
+
+use mock_server::{open_ai_client_for, MockResponse, MockServer};
+
+fn client_for(mock_server: &MockServer) -> OpenAiClient {
+    open_ai_client_for(mock_server).build().unwrap()
+}
+
+#[test]
+fn should_serialize_roles_in_lowercase() {
+    let roles = [
+        ChatRole::System,
+        ChatRole::Developer,
+        ChatRole::User,
+        ChatRole::Assistant,
+        ChatRole::Tool,
+    ];
+
+    assert_eq!(
+        serde_json::to_value(roles).unwrap(),
+        serde_json::json!(["system", "developer", "user", "assistant", "tool"])
+    );
+    assert_eq!(ChatRole::Developer.to_string(), "developer");
+}
+
+#[test]
+fn should_render_shot_prompts_as_a_single_user_message() {
+    let prompt = PromptType::new_zero_shot_prompt("hello".to_string());
+
+    assert_eq!(prompt.messages(), vec![ChatMessage::user("hello")]);
+}
+
+#[test]
+fn should_count_tokens_of_all_messages() {
+    let tokenizer = Tokenizer::default();
+    let conversation = Conversation::with_system_prompt("be brief");
+    let prompt = PromptType::new_conversation(conversation);
+
+    assert_eq!(prompt.prompt(), "be brief");
+    assert_eq!(
+        prompt.messages()[0].count_tokens(&tokenizer),
+        tokenizer.count_message_tokens("system", "be brief")
+    );
+}
+
+#[tokio::test]
+async fn should_send_all_messages_of_the_conversation() {
+    let mock_server =
+        MockServer::start(vec![MockResponse::chat_completion("Paris, of course.")]).await;
+    let open_ai_client = client_for(&mock_server);
+
+    let mut conversation = Conversation::with_system_prompt("You answer in one sentence.");
+    conversation.push(ChatMessage::developer("Never use lists."));
+    conversation.push_user("What is the capital\nof France?");
+    let prompt = PromptType::new_conversation(conversation);
+
+    open_ai_client.perform_request(&prompt).await.unwrap();
+
+    assert_eq!(
+        mock_server.requests()[0].json_body()["messages"],
+        serde_json::json!([
+            {"role": "system", "content": "You answer in one sentence."},
+            {"role": "developer", "content": "Never use lists."},
+            {"role": "user", "content": "What is the capital\nof France?"}
+        ])
+    );
+}
+
+#[tokio::test]
+async fn should_accumulate_replies_turn_by_turn() {
+    let mock_server = MockServer::start(vec![
+        MockResponse::chat_completion("A systems programming language."),
+        MockResponse::chat_completion("The Rust Foundation."),
+    ])
+    .await;
+    let open_ai_client = client_for(&mock_server);
+
+    let mut conversation = Conversation::with_system_prompt("You answer in one sentence.");
+    conversation.push_user("What is Rust?");
+    open_ai_client
+        .perform_conversation_request(&mut conversation)
+        .await
+        .unwrap();
+
+    conversation.push_user("Who maintains it?");
+    let response = open_ai_client
+        .perform_conversation_request(&mut conversation)
+        .await
+        .unwrap();
+
+    assert_eq!(response.answer.as_deref(), Some("The Rust Foundation."));
+    assert_eq!(conversation.len(), 5);
+    assert_eq!(conversation.last_answer(), Some("The Rust Foundation."));
+
+    let requests = mock_server.requests();
+    let second_messages = requests[1].json_body()["messages"].clone();
+    assert_eq!(second_messages.as_array().unwrap().len(), 4);
+    assert_eq!(second_messages[2]["role"], "assistant");
+    assert_eq!(
+        second_messages[2]["content"],
+        "A systems programming language."
+    );
+    assert_eq!(second_messages[3]["content"], "Who maintains it?");
+}
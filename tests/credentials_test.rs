@@ -0,0 +1,166 @@
+mod mock_server;
+
+use rust_llm_utils::{
+    CredentialProvider, Credentials, DotEnvCredentials, EnvCredentials, FileCredentials, LlmError,
+    OpenAiClient, RotatingCredentials,
+};
+use std::path::PathBuf;
+
+use mock_server::{open_ai_client_for, ping, MockResponse, MockServer};
+
+fn client_for(
+    mock_server: &MockServer,
+    credential_provider: impl CredentialProvider + 'static,
+) -> OpenAiClient {
+    open_ai_client_for(mock_server)
+        .credential_provider(credential_provider)
+        .build()
+        .unwrap()
+}
+
+/// a file in the temp dir that is unique per test
+fn temp_file(name: &str, content: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("rust_llm_utils_{}_{name}", std::process::id()));
+    std::fs::write(&path, content).unwrap();
+    path
+}
+
+fn assert_shareable<T: Clone + Send + Sync + 'static>() {}
+
+#[test]
+fn should_be_shareable_between_tasks() {
+    assert_shareable::<OpenAiClient>();
+}
+
+#[test]
+fn should_not_print_the_key() {
+    let credentials = Credentials::new("sk-secret").organization("org-1");
+
+    let debug = format!("{credentials:?}");
+
+    assert!(!debug.contains("sk-secret"));
+    assert!(debug.contains("org-1"));
+}
+
+#[tokio::test]
+async fn should_send_organization_and_project_headers() {
+    let mock_server = MockServer::start(vec![MockResponse::chat_completion("pong")]).await;
+    let credentials = Credentials::new("sk-static")
+        .organization("org-1")
+        .project("proj-1");
+    let open_ai_client = client_for(&mock_server, credentials);
+
+    // the client is 'static and can be moved into a task
+    tokio::spawn(async move { open_ai_client.perform_request(&ping()).await })
+        .await
+        .unwrap()
+        .unwrap();
+
+    let request = &mock_server.requests()[0];
+    assert_eq!(
+        request.header("authorization").as_deref(),
+        Some("Bearer sk-static")
+    );
+    assert_eq!(
+        request.header("openai-organization").as_deref(),
+        Some("org-1")
+    );
+    assert_eq!(request.header("openai-project").as_deref(), Some("proj-1"));
+}
+
+#[tokio::test]
+async fn should_rotate_through_the_keys() {
+    let mock_server = MockServer::start(vec![
+        MockResponse::chat_completion("pong"),
+        MockResponse::chat_completion("pong"),
+        MockResponse::chat_completion("pong"),
+    ])
+    .await;
+    let open_ai_client = client_for(
+        &mock_server,
+        RotatingCredentials::from_keys(["sk-1", "sk-2"]),
+    );
+
+    for _ in 0..3 {
+        open_ai_client.perform_request(&ping()).await.unwrap();
+    }
+
+    let authorizations: Vec<Option<String>> = mock_server
+        .requests()
+        .iter()
+        .map(|request| request.header("authorization"))
+        .collect();
+    assert_eq!(
+        authorizations,
+        vec![
+            Some("Bearer sk-1".to_string()),
+            Some("Bearer sk-2".to_string()),
+            Some("Bearer sk-1".to_string()),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn should_read_the_key_from_a_file_on_every_request() {
+    let path = temp_file("key_file", "sk-first\n");
+    let mock_server = MockServer::start(vec![
+        MockResponse::chat_completion("pong"),
+        MockResponse::chat_completion("pong"),
+    ])
+    .await;
+    let open_ai_client = client_for(&mock_server, FileCredentials::new(&path));
+
+    open_ai_client.perform_request(&ping()).await.unwrap();
+    std::fs::write(&path, "sk-rotated").unwrap();
+    open_ai_client.perform_request(&ping()).await.unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let requests = mock_server.requests();
+    assert_eq!(
+        requests[0].header("authorization").as_deref(),
+        Some("Bearer sk-first")
+    );
+    assert_eq!(
+        requests[1].header("authorization").as_deref(),
+        Some("Bearer sk-rotated")
+    );
+}
+
+#[tokio::test]
+async fn should_fail_without_sending_when_the_key_is_missing() {
+    let mock_server = MockServer::start(vec![MockResponse::chat_completion("pong")]).await;
+    let open_ai_client = client_for(
+        &mock_server,
+        EnvCredentials::new("RUST_LLM_UTILS_TEST_MISSING_KEY"),
+    );
+
+    let result = open_ai_client.perform_request(&ping()).await;
+
+    assert!(matches!(result, Err(LlmError::Credentials(_))));
+    assert!(mock_server.requests().is_empty());
+}
+
+#[test]
+fn should_read_organization_and_project_from_the_environment() {
+    std::env::set_var("RUST_LLM_UTILS_TEST_ENV_KEY", "sk-env");
+    std::env::set_var("RUST_LLM_UTILS_TEST_ENV_PROJECT", "proj-env");
+    let env_credentials = EnvCredentials::new("RUST_LLM_UTILS_TEST_ENV_KEY")
+        .organization_var("RUST_LLM_UTILS_TEST_ENV_ORGANIZATION")
+        .project_var("RUST_LLM_UTILS_TEST_ENV_PROJECT");
+
+    let credentials = env_credentials.credentials().unwrap();
+
+    assert_eq!(credentials, Credentials::new("sk-env").project("proj-env"));
+}
+
+#[test]
+fn should_load_the_dot_env_file() {
+    let path = temp_file("dot_env", "RUST_LLM_UTILS_TEST_DOT_ENV_KEY=sk-dot-env\n");
+    let dot_env_credentials = DotEnvCredentials::new(&path)
+        .env_credentials(EnvCredentials::new("RUST_LLM_UTILS_TEST_DOT_ENV_KEY"));
+
+    let credentials = dot_env_credentials.credentials().unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(credentials.api_key, "sk-dot-env");
+}
@@ -0,0 +1,316 @@
+mod mock_server;
+
+use futures::StreamExt;
+use rust_llm_utils::{
+    ChatMessage, ContentFilterResult, ContentFilterStage, Conversation, GeminiClient,
+    GenerationParams, JsonSchema, LlmClient, LlmError, PromptType, RetryPolicy,
+};
+
+use mock_server::{weather_conversation, MockResponse, MockServer};
+
+fn client_for(mock_server: &MockServer) -> GeminiClient {
+    GeminiClient::builder()
+        .base_url(format!("{}/v1beta", mock_server.url()))
+        .allow_http(true)
+        .token("test-key")
+        .retry_policy(RetryPolicy::none())
+        .build()
+        .unwrap()
+}
+
+fn negligible_ratings() -> serde_json::Value {
+    serde_json::json!([
+        {"category": "HARM_CATEGORY_HARASSMENT", "probability": "NEGLIGIBLE"},
+        {"category": "HARM_CATEGORY_HATE_SPEECH", "probability": "NEGLIGIBLE"}
+    ])
+}
+
+fn generate_content(answer: &str) -> MockResponse {
+    let body = serde_json::json!({
+        "candidates": [{
+            "content": {"role": "model", "parts": [{"text": answer}]},
+            "finishReason": "STOP",
+            "index": 0,
+            "safetyRatings": negligible_ratings()
+        }],
+        "usageMetadata": {"promptTokenCount": 12, "candidatesTokenCount": 5, "totalTokenCount": 17},
+        "modelVersion": "gemini-2.0-flash-001"
+    });
+    MockResponse::json(200, body.to_string())
+}
+
+#[tokio::test]
+async fn should_translate_messages_into_contents_and_parts() {
+    let mock_server = MockServer::start(vec![generate_content("Es schneit.")]).await;
+    let gemini_client = GeminiClient::builder()
+        .base_url(format!("{}/v1beta", mock_server.url()))
+        .allow_http(true)
+        .token("test-key")
+        .generation_params(GenerationParams {
+            temperature: Some(0.5),
+            top_p: Some(0.5),
+            max_tokens: Some(64),
+            stop: Some(vec!["\n\n".to_string()]),
+            seed: Some(7),
+            ..Default::default()
+        })
+        .build()
+        .unwrap();
+
+    let mut conversation = Conversation::with_system_prompt("Answer in German.");
+    conversation.push(ChatMessage::developer("Be brief."));
+    conversation.push_user("What is the weather like in Berlin?");
+    conversation.push(ChatMessage::assistant("Es schneit."));
+    conversation.push_user("And tomorrow?");
+    conversation.push(ChatMessage::tool("call_1", "-3c and sunny"));
+
+    let chat_response = gemini_client
+        .perform_request(&PromptType::new_conversation(conversation))
+        .await
+        .unwrap();
+
+    assert_eq!(chat_response.answer.as_deref(), Some("Es schneit."));
+    let usage = chat_response.usage.unwrap();
+    assert_eq!((usage.prompt_tokens, usage.completion_tokens), (12, 5));
+    // 12 * $0.10 + 5 * $0.40 per million tokens
+    let cost_usd = chat_response.cost_usd.unwrap();
+    assert!((cost_usd - 0.0000032).abs() < 1e-12, "{cost_usd}");
+    assert!(chat_response.content_filter_results.is_empty());
+
+    let request = &mock_server.requests()[0];
+    assert_eq!(
+        request.path,
+        "/v1beta/models/gemini-2.0-flash:generateContent"
+    );
+    assert_eq!(
+        request.header("x-goog-api-key").as_deref(),
+        Some("test-key")
+    );
+
+    let body = request.json_body();
+    assert_eq!(
+        body["systemInstruction"],
+        serde_json::json!({"parts": [{"text": "Answer in German."}, {"text": "Be brief."}]})
+    );
+    assert_eq!(
+        body["contents"],
+        serde_json::json!([
+            {"role": "user", "parts": [{"text": "What is the weather like in Berlin?"}]},
+            {"role": "model", "parts": [{"text": "Es schneit."}]},
+            {"role": "user", "parts": [{"text": "And tomorrow?"}, {"text": "-3c and sunny"}]}
+        ])
+    );
+    assert_eq!(
+        body["generationConfig"],
+        serde_json::json!({
+            "temperature": 0.5,
+            "topP": 0.5,
+            "maxOutputTokens": 64,
+            "stopSequences": ["\n\n"],
+            "seed": 7
+        })
+    );
+}
+
+#[tokio::test]
+async fn should_parse_candidates_and_flagged_safety_ratings() {
+    let body = serde_json::json!({
+        "candidates": [
+            {
+                "content": {"role": "model", "parts": [
+                    {"text": "thinking about the weather", "thought": true},
+                    {"text": "Es schneit."}
+                ]},
+                "finishReason": "STOP",
+                "index": 0,
+                "safetyRatings": [
+                    {"category": "HARM_CATEGORY_HARASSMENT", "probability": "MEDIUM"},
+                    {"category": "HARM_CATEGORY_HATE_SPEECH", "probability": "NEGLIGIBLE"}
+                ]
+            },
+            {
+                "finishReason": "SAFETY",
+                "index": 1,
+                "safetyRatings": [
+                    {"category": "HARM_CATEGORY_DANGEROUS_CONTENT", "probability": "HIGH", "blocked": true}
+                ]
+            }
+        ],
+        "usageMetadata": {"promptTokenCount": 12, "candidatesTokenCount": 5, "totalTokenCount": 25, "thoughtsTokenCount": 8}
+    });
+    let mock_server = MockServer::start(vec![MockResponse::json(200, body.to_string())]).await;
+
+    let chat_response = client_for(&mock_server)
+        .perform_request_with_params(
+            &weather_conversation(),
+            &GenerationParams {
+                n: Some(2),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(chat_response.answers, vec!["Es schneit."]);
+    assert_eq!(chat_response.usage.unwrap().completion_tokens, 13);
+    assert_eq!(
+        chat_response.content_filter_results,
+        vec![ContentFilterResult {
+            category: "harassment".to_string(),
+            filtered: false,
+            severity_maybe: Some("medium".to_string()),
+        }]
+    );
+    assert_eq!(
+        mock_server.requests()[0].json_body()["generationConfig"]["candidateCount"],
+        2
+    );
+}
+
+#[tokio::test]
+async fn should_return_blocked_prompts_and_candidates_as_content_filtered() {
+    let blocked_prompt = serde_json::json!({
+        "promptFeedback": {
+            "blockReason": "SAFETY",
+            "safetyRatings": [
+                {"category": "HARM_CATEGORY_DANGEROUS_CONTENT", "probability": "HIGH", "blocked": true},
+                {"category": "HARM_CATEGORY_HARASSMENT", "probability": "NEGLIGIBLE"}
+            ]
+        }
+    });
+    let blocked_candidate = serde_json::json!({
+        "candidates": [{
+            "finishReason": "SAFETY",
+            "index": 0,
+            "safetyRatings": [
+                {"category": "HARM_CATEGORY_SEXUALLY_EXPLICIT", "probability": "HIGH", "blocked": true}
+            ]
+        }]
+    });
+    let mock_server = MockServer::start(vec![
+        MockResponse::json(200, blocked_prompt.to_string()),
+        MockResponse::json(200, blocked_candidate.to_string()),
+    ])
+    .await;
+    let gemini_client = client_for(&mock_server);
+
+    let error = gemini_client
+        .perform_request(&weather_conversation())
+        .await
+        .unwrap_err();
+    assert_eq!(
+        error,
+        LlmError::ContentFiltered {
+            stage: ContentFilterStage::Prompt,
+            results: vec![ContentFilterResult {
+                category: "dangerous_content".to_string(),
+                filtered: true,
+                severity_maybe: Some("high".to_string()),
+            }],
+        }
+    );
+
+    let error = gemini_client
+        .perform_request(&weather_conversation())
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        LlmError::ContentFiltered { stage: ContentFilterStage::Completion, ref results }
+            if results[0].category == "sexually_explicit"
+    ));
+}
+
+#[tokio::test]
+async fn should_request_json_with_the_schema() {
+    let mock_server = MockServer::start(vec![generate_content(r#"{"city": "Berlin"}"#)]).await;
+    let schema = serde_json::json!({
+        "type": "object",
+        "properties": {"city": {"type": "string"}},
+        "required": ["city"]
+    });
+    let prompt = PromptType::new_zero_shot_prompt("Where is it snowing?".to_string())
+        .with_json_schema(JsonSchema::new("weather", schema.clone()));
+
+    let chat_response = client_for(&mock_server)
+        .perform_request(&prompt)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        chat_response.answer_json::<serde_json::Value>().unwrap()["city"],
+        "Berlin"
+    );
+    let generation_config = &mock_server.requests()[0].json_body()["generationConfig"];
+    assert_eq!(generation_config["responseMimeType"], "application/json");
+    assert_eq!(generation_config["responseJsonSchema"], schema);
+}
+
+#[tokio::test]
+async fn should_parse_google_error_payloads() {
+    let mock_server = MockServer::start(vec![MockResponse::json(
+        400,
+        r#"{"error": {"code": 400, "message": "API key not valid.", "status": "INVALID_ARGUMENT"}}"#,
+    )])
+    .await;
+
+    let error = client_for(&mock_server)
+        .perform_request(&weather_conversation())
+        .await
+        .unwrap_err();
+
+    let LlmError::Provider { status, error } = error else {
+        panic!("expected a provider error, got {error:?}");
+    };
+    assert_eq!(status, 400);
+    assert_eq!(error.error_type.as_deref(), Some("INVALID_ARGUMENT"));
+    assert_eq!(error.code.as_deref(), Some("400"));
+}
+
+#[tokio::test]
+async fn should_stream_the_text_of_the_first_candidate() {
+    let event_stream = concat!(
+        "data: {\"candidates\": [{\"content\": {\"role\": \"model\", \"parts\": [{\"text\": \"Es sieht aus \"}]}, \"index\": 0}]}\r\n\r\n",
+        "data: {\"candidates\": [{\"content\": {\"role\": \"model\", \"parts\": [{\"text\": \"wie Winterwetter ❄️\"}]}, \"finishReason\": \"STOP\", \"index\": 0}], \"usageMetadata\": {\"promptTokenCount\": 12, \"candidatesTokenCount\": 8, \"totalTokenCount\": 20}}\r\n\r\n",
+    );
+
+    for chunk_size in [1, 9, 4096] {
+        let mock_server =
+            MockServer::start(vec![MockResponse::event_stream(event_stream, chunk_size)]).await;
+        let llm_client: &dyn LlmClient = &client_for(&mock_server);
+
+        let pieces: Vec<String> = llm_client
+            .chat_stream(&weather_conversation())
+            .await
+            .unwrap()
+            .map(Result::unwrap)
+            .collect()
+            .await;
+
+        assert_eq!(
+            pieces,
+            vec!["Es sieht aus ", "wie Winterwetter ❄️"],
+            "chunk size {chunk_size}"
+        );
+        assert_eq!(
+            mock_server.requests()[0].path,
+            "/v1beta/models/gemini-2.0-flash:streamGenerateContent?alt=sse"
+        );
+    }
+}
+
+#[tokio::test]
+async fn should_fail_when_stream_ends_without_finish_reason() {
+    let event_stream = "data: {\"candidates\": [{\"content\": {\"parts\": [{\"text\": \"Es\"}]}, \"index\": 0}]}\n\n";
+    let mock_server = MockServer::start(vec![MockResponse::event_stream(event_stream, 4096)]).await;
+
+    let results: Vec<Result<String, LlmError>> = client_for(&mock_server)
+        .perform_request_stream(&weather_conversation())
+        .await
+        .unwrap()
+        .collect()
+        .await;
+
+    assert_eq!(results[0].as_deref(), Ok("Es"));
+    assert!(matches!(results[1], Err(LlmError::Transport(_))));
+}
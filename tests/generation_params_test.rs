@@ -0,0 +1,205 @@
+mod mock_server;
+
+use rust_llm_utils::{
+    GenerationLimits, GenerationParams, LlmError, ModelRegistry, OpenAiClient, OpenAiModel,
+    PromptType,
+};
+
+use mock_server::{open_ai_client_for, MockResponse, MockServer};
+
+fn client_for(
+    mock_server: &MockServer,
+    model: OpenAiModel,
+    generation_params: GenerationParams,
+) -> Result<OpenAiClient, LlmError> {
+    open_ai_client_for(mock_server)
+        .model(model)
+        .generation_params(generation_params)
+        .build()
+}
+
+fn prompt() -> PromptType {
+    PromptType::new_zero_shot_prompt("Is Rust memory safe?".to_string())
+}
+
+#[tokio::test]
+async fn should_send_the_default_temperature_only() {
+    let mock_server = MockServer::start(vec![MockResponse::chat_completion("yes")]).await;
+    let open_ai_client = client_for(
+        &mock_server,
+        OpenAiModel::Gpt4o,
+        GenerationParams::default(),
+    )
+    .unwrap();
+
+    open_ai_client.perform_request(&prompt()).await.unwrap();
+
+    let body = mock_server.requests()[0].json_body();
+    let temperature = body["temperature"].as_f64().unwrap();
+    assert!((temperature - 0.01).abs() < 1e-6);
+    for name in [
+        "top_p",
+        "max_tokens",
+        "stop",
+        "seed",
+        "n",
+        "logit_bias",
+        "user",
+    ] {
+        assert!(body.get(name).is_none(), "{name} should not be sent");
+    }
+}
+
+#[tokio::test]
+async fn should_override_client_params_per_request() {
+    let mock_server = MockServer::start(vec![MockResponse::chat_completion("yes")]).await;
+    let client_params = GenerationParams::default()
+        .temperature(0.5)
+        .max_tokens(200)
+        .stop(["\n\n"])
+        .user("user-42");
+    let open_ai_client = client_for(&mock_server, OpenAiModel::Gpt4o, client_params).unwrap();
+
+    let request_params = GenerationParams::default()
+        .temperature(1.0)
+        .top_p(0.9)
+        .presence_penalty(0.5)
+        .frequency_penalty(-0.5)
+        .seed(7)
+        .n(1)
+        .logit_bias(50256, -100.0);
+    open_ai_client
+        .perform_request_with_params(&prompt(), &request_params)
+        .await
+        .unwrap();
+
+    let body = mock_server.requests()[0].json_body();
+    assert_eq!(body["temperature"], 1.0);
+    assert_eq!(body["max_tokens"], 200);
+    assert_eq!(body["stop"], serde_json::json!(["\n\n"]));
+    assert_eq!(body["user"], "user-42");
+    assert_eq!(body["presence_penalty"], 0.5);
+    assert_eq!(body["frequency_penalty"], -0.5);
+    assert_eq!(body["seed"], 7);
+    assert_eq!(body["n"], 1);
+    assert_eq!(body["logit_bias"], serde_json::json!({"50256": -100.0}));
+    assert!((body["top_p"].as_f64().unwrap() - 0.9).abs() < 1e-6);
+
+    // the client's parameters are untouched by the override
+    assert_eq!(open_ai_client.generation_params().temperature, Some(0.5));
+}
+
+#[tokio::test]
+async fn should_reject_out_of_range_params_without_sending() {
+    let mock_server = MockServer::start(vec![MockResponse::chat_completion("yes")]).await;
+    let open_ai_client = client_for(
+        &mock_server,
+        OpenAiModel::Gpt4o,
+        GenerationParams::default(),
+    )
+    .unwrap();
+
+    let invalid_params = [
+        GenerationParams::default().temperature(2.5),
+        GenerationParams::default().top_p(1.5),
+        GenerationParams::default().presence_penalty(-3.0),
+        GenerationParams::default().n(0),
+        GenerationParams::default().stop(["a", "b", "c", "d", "e"]),
+        GenerationParams::default().logit_bias(1, 101.0),
+        GenerationParams::default().max_tokens(100_000),
+    ];
+    for params in invalid_params {
+        let error = open_ai_client
+            .perform_request_with_params(&prompt(), &params)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(error, LlmError::InvalidRequest(_)),
+            "{params:?} gave {error:?}"
+        );
+    }
+
+    assert!(mock_server.requests().is_empty());
+}
+
+#[tokio::test]
+async fn should_validate_client_params_when_building() {
+    let mock_server = MockServer::start(vec![]).await;
+
+    let error = client_for(
+        &mock_server,
+        OpenAiModel::Gpt35Turbo,
+        GenerationParams::default().max_tokens(10_000),
+    )
+    .err()
+    .unwrap();
+
+    assert!(matches!(error, LlmError::InvalidRequest(_)));
+}
+
+#[tokio::test]
+async fn should_respect_reasoning_model_capabilities() {
+    let mock_server = MockServer::start(vec![MockResponse::chat_completion("yes")]).await;
+    let open_ai_client = client_for(
+        &mock_server,
+        OpenAiModel::O3Mini,
+        GenerationParams::default().max_tokens(1_000).seed(1),
+    )
+    .unwrap();
+
+    open_ai_client.perform_request(&prompt()).await.unwrap();
+
+    let body = mock_server.requests()[0].json_body();
+    assert!(body.get("temperature").is_none());
+    assert!(body.get("max_tokens").is_none());
+    assert_eq!(body["max_completion_tokens"], 1_000);
+    assert_eq!(body["seed"], 1);
+
+    let error = open_ai_client
+        .perform_request_with_params(&prompt(), &GenerationParams::default().temperature(0.2))
+        .await
+        .unwrap_err();
+    assert_eq!(
+        error,
+        LlmError::InvalidRequest("o3-mini does not support temperature".to_string())
+    );
+}
+
+#[test]
+fn should_merge_params_field_by_field() {
+    let client_params = GenerationParams::default().temperature(0.5).seed(1);
+    let request_params = GenerationParams::default().seed(2).n(3);
+
+    let merged = client_params.merge(&request_params);
+
+    assert_eq!(
+        merged,
+        GenerationParams::default().temperature(0.5).seed(2).n(3)
+    );
+    assert!(merged
+        .validate(
+            &GenerationLimits::OPEN_AI,
+            ModelRegistry::default().get("gpt-4o")
+        )
+        .is_ok());
+}
+
+#[test]
+fn should_validate_against_the_limits_of_the_provider() {
+    let five_stops = GenerationParams::default().stop(["a", "b", "c", "d", "e"]);
+    assert!(five_stops
+        .validate(&GenerationLimits::OPEN_AI, None)
+        .is_err());
+    assert!(five_stops.validate(&GenerationLimits::GEMINI, None).is_ok());
+    assert!(five_stops
+        .validate(&GenerationLimits::ANTHROPIC, None)
+        .is_ok());
+
+    let hot = GenerationParams::default().temperature(1.5);
+    assert!(hot.validate(&GenerationLimits::OPEN_AI, None).is_ok());
+    assert!(hot.validate(&GenerationLimits::ANTHROPIC, None).is_err());
+    assert!(GenerationParams::default()
+        .temperature(3.0)
+        .validate(&GenerationLimits::LLAMA, None)
+        .is_ok());
+}
@@ -0,0 +1,111 @@
+mod mock_server;
+
+use rust_llm_utils::{LlmError, ModelDescriptor, ModelRegistry, OpenAiClient, OpenAiModel};
+
+use mock_server::{open_ai_client_for, MockResponse, MockServer};
+
+fn models_response() -> MockResponse {
+    let body = serde_json::json!({
+        "object": "list",
+        "data": [
+            {"id": "gpt-4o-2024-08-06", "object": "model", "created": 1_722_814_719u64, "owned_by": "system"},
+            {"id": "dall-e-3", "object": "model", "created": 1_698_785_189u64, "owned_by": "system"},
+            {"id": "gpt-4o", "object": "model", "created": 1_715_367_049u64, "owned_by": "system"}
+        ]
+    });
+    MockResponse::json(200, body.to_string())
+}
+
+fn client_for(mock_server: &MockServer) -> OpenAiClient {
+    open_ai_client_for(mock_server).build().unwrap()
+}
+
+#[tokio::test]
+async fn should_list_models_sorted_by_id() {
+    let mock_server = MockServer::start(vec![models_response()]).await;
+    let open_ai_client = client_for(&mock_server);
+
+    let models = open_ai_client.list_models().await.unwrap();
+
+    let ids: Vec<&str> = models.iter().map(|model| model.id.as_str()).collect();
+    assert_eq!(ids, vec!["dall-e-3", "gpt-4o", "gpt-4o-2024-08-06"]);
+    assert_eq!(models[0].owned_by, "system");
+    assert_eq!(models[0].created, 1_698_785_189);
+
+    let requests = mock_server.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].method, "GET");
+    assert_eq!(requests[0].path, "/v1/models");
+    assert_eq!(
+        requests[0].header("authorization").as_deref(),
+        Some("Bearer test-token")
+    );
+    assert!(requests[0].body.is_empty());
+}
+
+#[tokio::test]
+async fn should_surface_provider_errors_when_listing_models() {
+    let body = r#"{"error":{"message":"Incorrect API key provided","type":"invalid_request_error","param":null,"code":"invalid_api_key"}}"#;
+    let mock_server = MockServer::start(vec![MockResponse::json(401, body)]).await;
+    let open_ai_client = client_for(&mock_server);
+
+    let error = open_ai_client.list_models().await.unwrap_err();
+
+    match error.last_attempt() {
+        LlmError::Provider { status, error } => {
+            assert_eq!(*status, 401);
+            assert_eq!(error.code.as_deref(), Some("invalid_api_key"));
+        }
+        other => panic!("unexpected error {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn should_merge_listed_models_into_the_registry() {
+    let mock_server = MockServer::start(vec![models_response()]).await;
+    let mut open_ai_client = client_for(&mock_server);
+
+    let added_names = open_ai_client.refresh_model_registry().await.unwrap();
+
+    assert_eq!(added_names, vec!["dall-e-3", "gpt-4o-2024-08-06"]);
+
+    let model_registry = open_ai_client.model_registry();
+    let gpt_4o = model_registry.get("gpt-4o").unwrap();
+    let snapshot = model_registry.get("gpt-4o-2024-08-06").unwrap();
+    assert_eq!(snapshot.name, "gpt-4o-2024-08-06");
+    assert_eq!(snapshot.context_window, gpt_4o.context_window);
+    assert_eq!(snapshot.pricing, gpt_4o.pricing);
+
+    let unknown = model_registry.get("dall-e-3").unwrap();
+    assert_eq!(unknown.context_window, 8192);
+    assert_eq!(unknown.pricing, None);
+}
+
+#[test]
+fn should_keep_registered_descriptors_when_merging() {
+    let mut model_registry = ModelRegistry::default();
+    let before = model_registry
+        .get(OpenAiModel::Gpt4o.name())
+        .unwrap()
+        .clone();
+
+    let added_names = model_registry.merge_model_names(["gpt-4o", "my-fine-tune"]);
+
+    assert_eq!(added_names, vec!["my-fine-tune"]);
+    assert_eq!(model_registry.get("gpt-4o"), Some(&before));
+    assert!(model_registry.contains("my-fine-tune"));
+}
+
+#[test]
+fn should_not_inherit_the_descriptor_of_a_sibling_model() {
+    let mut model_registry = ModelRegistry::default();
+
+    let added_names =
+        model_registry.merge_model_names(["o1-pro", "gpt-4o-mini-tts", "gpt-4.1-nano"]);
+
+    assert_eq!(added_names.len(), 3);
+    for name in added_names {
+        let descriptor = model_registry.get(&name).unwrap();
+        assert_eq!(descriptor, &ModelDescriptor::with_defaults(name.clone()));
+    }
+}
@@ -0,0 +1,281 @@
+mod mock_server;
+
+use futures::StreamExt;
+use rust_llm_utils::{
+    ChatMessage, ChatTemplate, GenerationParams, JsonSchema, LlamaBackend, LlamaClient, LlmClient,
+    LlmError, PromptType,
+};
+
+use mock_server::{weather_conversation, MockResponse, MockServer};
+
+fn client_for(mock_server: &MockServer, backend: LlamaBackend) -> LlamaClient {
+    LlamaClient::builder()
+        .backend(backend)
+        .base_url(mock_server.url())
+        .model("llama2:13b-chat")
+        .build()
+        .unwrap()
+}
+
+fn ollama_chat(answer: &str) -> MockResponse {
+    let body = serde_json::json!({
+        "model": "llama2:13b-chat",
+        "created_at": "2023-12-12T14:13:43.416799Z",
+        "message": {"role": "assistant", "content": answer},
+        "done": true,
+        "prompt_eval_count": 26,
+        "eval_count": 12
+    });
+    MockResponse::json(200, body.to_string())
+}
+
+fn llama_cpp_completion(answer: &str) -> MockResponse {
+    let body = serde_json::json!({
+        "content": answer,
+        "model": "llama-2-13b-chat.Q4_K_M.gguf",
+        "stop": true,
+        "tokens_evaluated": 30,
+        "tokens_predicted": 10
+    });
+    MockResponse::json(200, body.to_string())
+}
+
+#[tokio::test]
+async fn should_send_the_messages_to_ollama_chat() {
+    let mock_server = MockServer::start(vec![ollama_chat("Es schneit.")]).await;
+    let llama_client = LlamaClient::builder()
+        .base_url(mock_server.url())
+        .model("llama2:13b-chat")
+        .generation_params(GenerationParams {
+            temperature: Some(0.5),
+            max_tokens: Some(64),
+            ..Default::default()
+        })
+        .build()
+        .unwrap();
+
+    let chat_response = llama_client
+        .perform_request(&weather_conversation())
+        .await
+        .unwrap();
+
+    assert_eq!(chat_response.answer.as_deref(), Some("Es schneit."));
+    let usage = chat_response.usage.unwrap();
+    assert_eq!(
+        (
+            usage.prompt_tokens,
+            usage.completion_tokens,
+            usage.total_tokens
+        ),
+        (26, 12, 38)
+    );
+
+    let request = &mock_server.requests()[0];
+    assert_eq!(request.path, "/api/chat");
+    let body = request.json_body();
+    assert_eq!(body["model"], "llama2:13b-chat");
+    assert_eq!(body["stream"], false);
+    assert_eq!(body["messages"][0]["role"], "system");
+    assert_eq!(body["messages"][0]["content"], "Answer in German.");
+    assert_eq!(body["messages"][1]["role"], "user");
+    assert_eq!(body["options"]["temperature"], 0.5);
+    assert_eq!(body["options"]["num_predict"], 64);
+}
+
+#[tokio::test]
+async fn should_render_the_llama2_prompt_for_llama_cpp() {
+    let mock_server = MockServer::start(vec![llama_cpp_completion(" Es schneit.")]).await;
+    let llama_client = client_for(&mock_server, LlamaBackend::LlamaCpp);
+
+    let chat_response = llama_client.chat(&weather_conversation()).await.unwrap();
+
+    assert_eq!(chat_response.answer.as_deref(), Some(" Es schneit."));
+    assert_eq!(chat_response.usage.unwrap().total_tokens, 40);
+
+    let request = &mock_server.requests()[0];
+    assert_eq!(request.path, "/completion");
+    assert_eq!(
+        request.json_body()["prompt"],
+        "<s>[INST] <<SYS>>\nAnswer in German.\n<</SYS>>\n\nWhat is the weather like in Berlin? [/INST]"
+    );
+}
+
+#[tokio::test]
+async fn should_stop_llama_cpp_at_the_stop_sequences_of_the_template() {
+    let mock_server = MockServer::start(vec![llama_cpp_completion("Es schneit.")]).await;
+    let llama_client = LlamaClient::builder()
+        .backend(LlamaBackend::LlamaCpp)
+        .base_url(mock_server.url())
+        .model("qwen2.5-7b-instruct")
+        .chat_template(ChatTemplate::ChatMl)
+        .generation_params(GenerationParams {
+            stop: Some(vec!["\n\n".to_string()]),
+            ..Default::default()
+        })
+        .build()
+        .unwrap();
+
+    llama_client.chat(&weather_conversation()).await.unwrap();
+
+    assert_eq!(
+        mock_server.requests()[0].json_body()["stop"],
+        serde_json::json!(["\n\n", "<|im_end|>"])
+    );
+}
+
+#[test]
+fn should_render_multi_turn_chats_in_llama2_format() {
+    let messages = vec![
+        ChatMessage::developer("Be brief."),
+        ChatMessage::user("Hi"),
+        ChatMessage::assistant("Hello!"),
+        ChatMessage::user("Weather?"),
+        ChatMessage::user("In Berlin."),
+    ];
+
+    assert_eq!(
+        ChatTemplate::Llama2.render(&messages),
+        "<s>[INST] <<SYS>>\nBe brief.\n<</SYS>>\n\nHi [/INST] Hello! </s><s>[INST] Weather?\n\nIn Berlin. [/INST]"
+    );
+    assert_eq!(
+        ChatTemplate::Llama2.render(&[ChatMessage::user("Hi")]),
+        "<s>[INST] Hi [/INST]"
+    );
+}
+
+#[tokio::test]
+async fn should_send_json_schema_and_validate_the_answer() {
+    let mock_server = MockServer::start(vec![ollama_chat(r#"{"city": 7}"#)]).await;
+    let llama_client = client_for(&mock_server, LlamaBackend::Ollama);
+    let schema = serde_json::json!({
+        "type": "object",
+        "properties": {"city": {"type": "string"}},
+        "required": ["city"]
+    });
+    let prompt = PromptType::new_zero_shot_prompt("Where is it snowing?".to_string())
+        .with_json_schema(JsonSchema::new("weather", schema.clone()));
+
+    let error = llama_client.perform_request(&prompt).await.unwrap_err();
+
+    assert!(matches!(error, LlmError::SchemaValidation { path, .. } if path == "$.city"));
+    assert_eq!(mock_server.requests()[0].json_body()["format"], schema);
+}
+
+#[tokio::test]
+async fn should_return_the_status_of_failed_requests() {
+    let mock_server = MockServer::start(vec![MockResponse::json(
+        404,
+        r#"{"error": "model 'llama2:70b' not found, try pulling it first"}"#,
+    )])
+    .await;
+
+    let error = client_for(&mock_server, LlamaBackend::Ollama)
+        .perform_request(&weather_conversation())
+        .await
+        .unwrap_err();
+
+    assert!(matches!(error, LlmError::HttpStatus { status: 404, .. }));
+}
+
+#[tokio::test]
+async fn should_fail_when_the_answer_has_no_content() {
+    let mock_server = MockServer::start(vec![MockResponse::json(
+        200,
+        r#"{"model": "llama2:13b-chat", "done": true, "prompt_eval_count": 26}"#,
+    )])
+    .await;
+
+    let error = client_for(&mock_server, LlamaBackend::Ollama)
+        .perform_request(&weather_conversation())
+        .await
+        .unwrap_err();
+
+    assert!(matches!(error, LlmError::EmptyChoices));
+}
+
+#[tokio::test]
+async fn should_stream_ollama_lines() {
+    let lines = concat!(
+        "{\"model\":\"llama2\",\"message\":{\"role\":\"assistant\",\"content\":\"Es \"},\"done\":false}\n",
+        "{\"model\":\"llama2\",\"message\":{\"role\":\"assistant\",\"content\":\"schneit ❄️\"},\"done\":false}\n",
+        "{\"model\":\"llama2\",\"message\":{\"role\":\"assistant\",\"content\":\"\"},\"done\":true,\"eval_count\":3}\n",
+    );
+
+    for chunk_size in [1, 5, 4096] {
+        let mock_server =
+            MockServer::start(vec![MockResponse::event_stream(lines, chunk_size)]).await;
+
+        let pieces: Vec<String> = client_for(&mock_server, LlamaBackend::Ollama)
+            .chat_stream(&weather_conversation())
+            .await
+            .unwrap()
+            .map(Result::unwrap)
+            .collect()
+            .await;
+
+        assert_eq!(pieces.concat(), "Es schneit ❄️", "chunk size {chunk_size}");
+        assert_eq!(mock_server.requests()[0].json_body()["stream"], true);
+    }
+}
+
+#[tokio::test]
+async fn should_stream_llama_cpp_events() {
+    let event_stream = concat!(
+        "data: {\"content\":\" Es\",\"stop\":false}\n\n",
+        "data: {\"content\":\" schneit.\",\"stop\":false}\n\n",
+        "data: {\"content\":\"\",\"stop\":true,\"tokens_predicted\":2}\n\n",
+    );
+    let mock_server = MockServer::start(vec![MockResponse::event_stream(event_stream, 7)]).await;
+
+    let pieces: Vec<String> = client_for(&mock_server, LlamaBackend::LlamaCpp)
+        .chat_stream(&weather_conversation())
+        .await
+        .unwrap()
+        .map(Result::unwrap)
+        .collect()
+        .await;
+
+    assert_eq!(pieces, vec![" Es", " schneit."]);
+}
+
+#[tokio::test]
+async fn should_fail_when_stream_ends_before_done() {
+    let lines = "{\"message\":{\"role\":\"assistant\",\"content\":\"Es\"},\"done\":false}\n";
+    let mock_server = MockServer::start(vec![MockResponse::event_stream(lines, 4096)]).await;
+
+    let results: Vec<Result<String, LlmError>> = client_for(&mock_server, LlamaBackend::Ollama)
+        .chat_stream(&weather_conversation())
+        .await
+        .unwrap()
+        .collect()
+        .await;
+
+    assert_eq!(results[0].as_deref(), Ok("Es"));
+    assert!(matches!(results[1], Err(LlmError::Transport(_))));
+}
+
+#[test]
+fn should_default_to_llama2_on_ollama() {
+    let llama_client = LlamaClient::builder().build().unwrap();
+
+    assert_eq!(llama_client.backend(), LlamaBackend::Ollama);
+    assert_eq!(llama_client.model_name(), "llama2");
+    assert!(llama_client.count_tokens(&weather_conversation()) > 0);
+}
+
+#[tokio::test]
+async fn should_reject_several_choices() {
+    let mock_server = MockServer::start(vec![ollama_chat("Es schneit.")]).await;
+    let params = GenerationParams {
+        n: Some(2),
+        ..Default::default()
+    };
+
+    let error = client_for(&mock_server, LlamaBackend::Ollama)
+        .perform_request_with_params(&weather_conversation(), &params)
+        .await
+        .unwrap_err();
+
+    assert!(matches!(error, LlmError::InvalidRequest(_)));
+    assert!(mock_server.requests().is_empty());
+}
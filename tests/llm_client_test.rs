@@ -0,0 +1,176 @@
+mod mock_server;
+
+use async_trait::async_trait;
+use futures::{stream, StreamExt};
+use rust_llm_utils::{
+    chat_typed, llm_prompt, ChatResponse, ChatStream, LlmClient, LlmError, LlmResponse,
+    OpenAiClient, OpenAiModel, PromptType, Tokenizer,
+};
+use serde::Deserialize;
+use std::sync::Mutex;
+
+use mock_server::{open_ai_client_for, MockResponse, MockServer};
+
+const EVENT_STREAM: &str = concat!(
+    "data: {\"id\":\"chatcmpl-1\",\"object\":\"chat.completion.chunk\",\"created\":1700000000,\"model\":\"gpt-4o-mini\",\"choices\":[{\"index\":0,\"delta\":{\"role\":\"assistant\",\"content\":\"\"},\"finish_reason\":null}]}\n\n",
+    "data: {\"id\":\"chatcmpl-1\",\"object\":\"chat.completion.chunk\",\"created\":1700000000,\"model\":\"gpt-4o-mini\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Hello \"},\"finish_reason\":null}]}\n\n",
+    "data: {\"id\":\"chatcmpl-1\",\"object\":\"chat.completion.chunk\",\"created\":1700000000,\"model\":\"gpt-4o-mini\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"world\"},\"finish_reason\":\"stop\"}]}\n\n",
+    "data: [DONE]\n\n",
+);
+
+#[derive(Deserialize, LlmResponse, Debug, PartialEq)]
+struct City {
+    name: String,
+}
+
+#[llm_prompt("Capital of {country}")]
+async fn capital(country: &str) -> Result<String, LlmError>;
+
+/// a backend answering from a script, standing in for another provider
+struct ScriptedClient {
+    answers: Mutex<Vec<String>>,
+    prompts: Mutex<Vec<String>>,
+}
+
+impl ScriptedClient {
+    fn new(answers: &[&str]) -> Self {
+        Self {
+            answers: Mutex::new(answers.iter().rev().map(|a| a.to_string()).collect()),
+            prompts: Mutex::new(vec![]),
+        }
+    }
+}
+
+#[async_trait]
+impl LlmClient for ScriptedClient {
+    fn model_name(&self) -> &str {
+        "scripted"
+    }
+
+    async fn chat(&self, prompt: &PromptType) -> Result<ChatResponse, LlmError> {
+        self.prompts.lock().unwrap().push(prompt.prompt());
+        let answer = self.answers.lock().unwrap().pop();
+        Ok(ChatResponse {
+            answers: answer.iter().cloned().collect(),
+            answer,
+            ..ChatResponse::default()
+        })
+    }
+
+    async fn chat_stream(&self, prompt: &PromptType) -> Result<ChatStream, LlmError> {
+        let answer = self.chat(prompt).await?.answer.unwrap_or_default();
+        Ok(stream::iter(vec![Ok(answer)]).boxed())
+    }
+
+    fn count_tokens(&self, prompt: &PromptType) -> usize {
+        prompt.prompt().split_whitespace().count()
+    }
+}
+
+fn client_for(mock_server: &MockServer) -> OpenAiClient {
+    open_ai_client_for(mock_server)
+        .model(OpenAiModel::Gpt4oMini)
+        .build()
+        .unwrap()
+}
+
+fn prompt() -> PromptType {
+    PromptType::new_zero_shot_prompt("Say hello".to_string())
+}
+
+/// written once against the trait
+async fn ask(llm_client: &dyn LlmClient, prompt: &PromptType) -> Option<String> {
+    llm_client.chat(prompt).await.unwrap().answer
+}
+
+#[tokio::test]
+async fn should_run_the_same_code_on_any_backend() {
+    let mock_server = MockServer::start(vec![MockResponse::chat_completion("Hello")]).await;
+    let open_ai_client = client_for(&mock_server);
+    let scripted_client = ScriptedClient::new(&["Hi"]);
+
+    assert_eq!(
+        ask(&open_ai_client, &prompt()).await.as_deref(),
+        Some("Hello")
+    );
+    assert_eq!(
+        ask(&scripted_client, &prompt()).await.as_deref(),
+        Some("Hi")
+    );
+    assert_eq!(open_ai_client.model_name(), "gpt-4o-mini");
+}
+
+#[tokio::test]
+async fn should_stream_the_text_of_the_answer() {
+    let mock_server = MockServer::start(vec![MockResponse::event_stream(EVENT_STREAM, 64)]).await;
+    let open_ai_client = client_for(&mock_server);
+
+    let chat_stream = open_ai_client.chat_stream(&prompt()).await.unwrap();
+    let pieces: Vec<String> = chat_stream.map(|piece| piece.unwrap()).collect().await;
+
+    assert_eq!(pieces, vec!["Hello ".to_string(), "world".to_string()]);
+}
+
+#[test]
+fn should_count_tokens_with_the_chat_format_overhead() {
+    let open_ai_client = OpenAiClient::new(None, Some("test-token"));
+    let tokenizer = Tokenizer::default();
+
+    assert_eq!(
+        LlmClient::count_tokens(&open_ai_client, &prompt()),
+        tokenizer.count_messages_tokens([("user", "Say hello")])
+    );
+}
+
+#[tokio::test]
+async fn should_correct_typed_answers_on_any_backend() {
+    let scripted_client = ScriptedClient::new(&[r#"{"city":"Paris"}"#, r#"{"name":"Paris"}"#]);
+    let llm_client: &dyn LlmClient = &scripted_client;
+
+    let city: City = chat_typed(llm_client, &prompt()).await.unwrap();
+
+    assert_eq!(
+        city,
+        City {
+            name: "Paris".to_string()
+        }
+    );
+    let prompts = scripted_client.prompts.lock().unwrap();
+    assert_eq!(prompts.len(), 2);
+    assert!(prompts[1].contains("missing required property name"));
+}
+
+#[tokio::test]
+async fn should_run_prompt_functions_on_any_backend() {
+    let scripted_client = ScriptedClient::new(&["Paris"]);
+
+    let answer = capital(&scripted_client, "France").await.unwrap();
+
+    assert_eq!(answer, "Paris");
+    assert_eq!(
+        scripted_client.prompts.lock().unwrap()[0],
+        "Capital of France"
+    );
+}
+
+#[tokio::test]
+async fn should_get_typed_answers_from_concrete_clients() {
+    let scripted_client = ScriptedClient::new(&[r#"{"name":"Paris"}"#]);
+
+    let city: City = chat_typed(&scripted_client, &prompt()).await.unwrap();
+
+    assert_eq!(city.name, "Paris");
+}
+
+#[tokio::test]
+async fn should_send_the_newlines_of_shot_prompts_as_they_are() {
+    let mock_server = MockServer::start(vec![MockResponse::chat_completion("Hello")]).await;
+    let prompt = PromptType::new_zero_shot_prompt("Say hello\nin German".to_string());
+
+    client_for(&mock_server).chat(&prompt).await.unwrap();
+
+    assert_eq!(
+        mock_server.requests()[0].json_body()["messages"],
+        serde_json::json!([{"role": "user", "content": "Say hello\nin German"}])
+    );
+}
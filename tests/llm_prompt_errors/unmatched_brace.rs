@@ -0,0 +1,6 @@
+use rust_llm_utils::llm_prompt;
+
+#[llm_prompt("Fix this code: {code} }")]
+async fn fix(code: &str) -> Result<String, rust_llm_utils::LlmError>;
+
+fn main() {}
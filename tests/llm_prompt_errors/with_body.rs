@@ -0,0 +1,8 @@
+use rust_llm_utils::llm_prompt;
+
+#[llm_prompt("Fix this code: {code}")]
+async fn fix(code: &str) -> Result<String, rust_llm_utils::LlmError> {
+    Ok(code.to_string())
+}
+
+fn main() {}
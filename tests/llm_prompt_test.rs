@@ -0,0 +1,122 @@
+mod mock_server;
+
+use rust_llm_utils::{
+    llm_prompt, ChatResponse, InnerPrompt, LlmError, LlmResponse, OpenAiClient, OpenAiModel,
+};
+use serde::Deserialize;
+
+use mock_server::{open_ai_client_for, MockResponse, MockServer};
+
+#[derive(Deserialize, LlmResponse, Debug, PartialEq)]
+struct Fixed {
+    code: String,
+    explanation: String,
+}
+
+/// fixes code in any language
+#[llm_prompt("Fix this {lang} code: {code}")]
+async fn fix(lang: &str, code: &str) -> Result<Fixed, LlmError>;
+
+#[llm_prompt("Translate to French: {text}")]
+pub async fn translate(text: String) -> Result<String, LlmError>;
+
+#[llm_prompt("Explain {{braces}} in {lang}, then {lang} again")]
+async fn explain(lang: &str) -> Result<ChatResponse, LlmError>;
+
+fn client_for(mock_server: &MockServer) -> OpenAiClient {
+    open_ai_client_for(mock_server)
+        .model(OpenAiModel::Gpt4oMini)
+        .build()
+        .unwrap()
+}
+
+fn sent_query(mock_server: &MockServer) -> String {
+    mock_server.requests()[0].json_body()["messages"][0]["content"]
+        .as_str()
+        .unwrap()
+        .to_string()
+}
+
+#[test]
+fn should_render_the_template_from_the_parameters() {
+    assert_eq!(
+        FixPrompt::new("rust", "fn main() {}").query(),
+        "Fix this rust code: fn main() {}"
+    );
+    assert_eq!(
+        ExplainPrompt::new("Rust").query(),
+        "Explain {braces} in Rust, then Rust again"
+    );
+}
+
+#[test]
+fn should_implement_inner_prompt_for_a_single_parameter() {
+    let translate_prompt = TranslatePrompt::new_from_prompt_template("hello".to_string());
+
+    assert_eq!(
+        InnerPrompt::query(&translate_prompt),
+        "Translate to French: hello"
+    );
+}
+
+#[test]
+fn should_fill_every_placeholder_of_inner_prompt_with_the_input() {
+    let fix_prompt = FixPrompt::new_from_prompt_template("rust".to_string());
+
+    assert_eq!(InnerPrompt::query(&fix_prompt), "Fix this rust code: rust");
+}
+
+#[test]
+fn should_reject_templates_that_do_not_match_the_function() {
+    trybuild::TestCases::new().compile_fail("tests/llm_prompt_errors/*.rs");
+}
+
+#[tokio::test]
+async fn should_return_the_typed_answer() {
+    let mock_server = MockServer::start(vec![MockResponse::chat_completion(
+        r#"{"code":"fn main() {}","explanation":"added braces"}"#,
+    )])
+    .await;
+    let open_ai_client = client_for(&mock_server);
+
+    let fixed = fix(&open_ai_client, "rust", "fn main()").await.unwrap();
+
+    assert_eq!(
+        fixed,
+        Fixed {
+            code: "fn main() {}".to_string(),
+            explanation: "added braces".to_string(),
+        }
+    );
+    assert_eq!(sent_query(&mock_server), "Fix this rust code: fn main()");
+    assert_eq!(
+        mock_server.requests()[0].json_body()["response_format"]["json_schema"]["name"],
+        "Fixed"
+    );
+}
+
+#[tokio::test]
+async fn should_return_the_answer_as_text() {
+    let mock_server = MockServer::start(vec![MockResponse::chat_completion("bonjour")]).await;
+    let open_ai_client = client_for(&mock_server);
+
+    let answer = translate(&open_ai_client, "hello".to_string())
+        .await
+        .unwrap();
+
+    assert_eq!(answer, "bonjour");
+    assert!(mock_server.requests()[0]
+        .json_body()
+        .get("response_format")
+        .is_none());
+}
+
+#[tokio::test]
+async fn should_return_the_chat_response() {
+    let mock_server = MockServer::start(vec![MockResponse::chat_completion("braces")]).await;
+    let open_ai_client = client_for(&mock_server);
+
+    let response = explain(&open_ai_client, "Rust").await.unwrap();
+
+    assert_eq!(response.answer.as_deref(), Some("braces"));
+}
@@ -0,0 +1,207 @@
+mod mock_server;
+
+use rust_llm_utils::{LlmError, LlmResponse, OpenAiClient, OpenAiModel, PromptType};
+use serde::Deserialize;
+
+use mock_server::{open_ai_client_for, MockResponse, MockServer};
+
+/// the sentiment of a review
+#[derive(Deserialize, LlmResponse, Debug, PartialEq)]
+struct Sentiment {
+    /// how the review feels overall
+    label: SentimentLabel,
+    /// between 0 and 1
+    confidence: f64,
+    aspects: Vec<String>,
+    summary: Option<String>,
+}
+
+#[derive(Deserialize, LlmResponse, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum SentimentLabel {
+    Positive,
+    NotPositive,
+}
+
+#[derive(Deserialize, LlmResponse, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct Renamed {
+    first_name: String,
+    #[serde(rename = "surname")]
+    last_name: String,
+    #[serde(skip)]
+    internal: u32,
+}
+
+#[derive(Deserialize, LlmResponse, Debug, PartialEq)]
+enum Shape {
+    /// a circle around the origin
+    Circle {
+        radius: f64,
+    },
+    Square(f64),
+    Point,
+}
+
+#[derive(Deserialize, LlmResponse, Debug, PartialEq)]
+struct Score(u8);
+
+fn client_for(mock_server: &MockServer, json_repair_attempts: usize) -> OpenAiClient {
+    open_ai_client_for(mock_server)
+        .model(OpenAiModel::Gpt4oMini)
+        .json_repair_attempts(json_repair_attempts)
+        .build()
+        .unwrap()
+}
+
+fn prompt() -> PromptType {
+    PromptType::new_zero_shot_prompt("Sentiment of: I love it".to_string())
+}
+
+#[test]
+fn should_derive_object_schema_with_descriptions() {
+    assert_eq!(
+        Sentiment::json_schema(),
+        serde_json::json!({
+            "type": "object",
+            "description": "the sentiment of a review",
+            "properties": {
+                "label": {
+                    "type": "string",
+                    "enum": ["positive", "not_positive"],
+                    "description": "how the review feels overall"
+                },
+                "confidence": {"type": "number", "description": "between 0 and 1"},
+                "aspects": {"type": "array", "items": {"type": "string"}},
+                "summary": {"anyOf": [{"type": "string"}, {"type": "null"}]}
+            },
+            "required": ["label", "confidence", "aspects", "summary"],
+            "additionalProperties": false
+        })
+    );
+    assert_eq!(Sentiment::schema_name(), "Sentiment");
+    assert!(Sentiment::response_json_schema().strict);
+}
+
+#[test]
+fn should_follow_serde_renames_and_skips() {
+    let schema = Renamed::json_schema();
+
+    assert_eq!(
+        schema["required"],
+        serde_json::json!(["firstName", "surname"])
+    );
+    assert!(schema["properties"].get("internal").is_none());
+}
+
+#[test]
+fn should_derive_externally_tagged_enum_schema() {
+    assert_eq!(
+        Shape::json_schema(),
+        serde_json::json!({
+            "anyOf": [
+                {
+                    "type": "object",
+                    "properties": {
+                        "Circle": {
+                            "type": "object",
+                            "properties": {"radius": {"type": "number"}},
+                            "required": ["radius"],
+                            "additionalProperties": false
+                        }
+                    },
+                    "required": ["Circle"],
+                    "additionalProperties": false,
+                    "description": "a circle around the origin"
+                },
+                {
+                    "type": "object",
+                    "properties": {"Square": {"type": "number"}},
+                    "required": ["Square"],
+                    "additionalProperties": false
+                },
+                {"type": "string", "enum": ["Point"]}
+            ]
+        })
+    );
+    assert_eq!(Score::json_schema(), serde_json::json!({"type": "integer"}));
+}
+
+#[tokio::test]
+async fn should_send_derived_schema_and_return_typed_answer() {
+    let mock_server = MockServer::start(vec![MockResponse::chat_completion(
+        r#"{"label":"positive","confidence":0.9,"aspects":["price"],"summary":null}"#,
+    )])
+    .await;
+    let open_ai_client = client_for(&mock_server, 0);
+
+    let sentiment: Sentiment = open_ai_client
+        .perform_request_typed(&prompt())
+        .await
+        .unwrap();
+
+    assert_eq!(
+        sentiment,
+        Sentiment {
+            label: SentimentLabel::Positive,
+            confidence: 0.9,
+            aspects: vec!["price".to_string()],
+            summary: None,
+        }
+    );
+
+    let response_format = mock_server.requests()[0].json_body()["response_format"].clone();
+    assert_eq!(response_format["json_schema"]["name"], "Sentiment");
+    assert_eq!(
+        response_format["json_schema"]["schema"],
+        Sentiment::json_schema()
+    );
+}
+
+#[tokio::test]
+async fn should_ask_for_a_correction_when_the_answer_does_not_parse() {
+    let mock_server = MockServer::start(vec![
+        MockResponse::chat_completion(
+            r#"{"label":"great","confidence":0.9,"aspects":[],"summary":null}"#,
+        ),
+        MockResponse::chat_completion(
+            r#"{"label":"positive","confidence":0.9,"aspects":[],"summary":"good"}"#,
+        ),
+    ])
+    .await;
+    let open_ai_client = client_for(&mock_server, 0);
+
+    let sentiment: Sentiment = open_ai_client
+        .perform_request_typed(&prompt())
+        .await
+        .unwrap();
+
+    assert_eq!(sentiment.summary.as_deref(), Some("good"));
+    let requests = mock_server.requests();
+    assert_eq!(requests.len(), 2);
+    let messages = requests[1].json_body()["messages"].clone();
+    assert_eq!(messages[1]["role"], "assistant");
+    assert!(messages[2]["content"].as_str().unwrap().contains("$.label"));
+}
+
+#[tokio::test]
+async fn should_fail_after_the_correction_attempts() {
+    let mock_server = MockServer::start(vec![
+        MockResponse::chat_completion("not json"),
+        MockResponse::chat_completion("still not json"),
+    ])
+    .await;
+    let open_ai_client = client_for(&mock_server, 0);
+
+    let result = open_ai_client
+        .perform_request_typed::<Sentiment>(&prompt())
+        .await;
+
+    match result {
+        Err(LlmError::SchemaValidation { path, answer, .. }) => {
+            assert_eq!(path, "$");
+            assert_eq!(answer, "still not json");
+        }
+        other => panic!("expected a schema validation error, got {other:?}"),
+    }
+}
@@ -0,0 +1,248 @@
+//! a tiny local HTTP server that plays back scripted responses, so that the
+//! clients can be tested without network access or tokens
+#![allow(dead_code)]
+
+use hyper::body::to_bytes;
+use hyper::header::HeaderMap;
+use hyper::server::conn::AddrStream;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server};
+use rust_llm_utils::{Conversation, OpenAiClientBuilder, PromptType, RetryPolicy};
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// a response the server plays back, responses are served in the order they
+/// were given and the last one is repeated once the script runs out
+#[derive(Clone, Debug)]
+pub struct MockResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+
+    /// parts of the body, each one is flushed separately with a pause of
+    /// `chunk_delay` so that the client sees them in separate reads
+    pub body_chunks: Vec<Vec<u8>>,
+
+    /// pause before the response headers are sent
+    pub delay: Duration,
+
+    pub chunk_delay: Duration,
+}
+
+impl MockResponse {
+    pub fn json(status: u16, body: impl Into<String>) -> Self {
+        Self {
+            status,
+            headers: vec![("content-type".to_string(), "application/json".to_string())],
+            body_chunks: vec![body.into().into_bytes()],
+            delay: Duration::ZERO,
+            chunk_delay: Duration::from_millis(2),
+        }
+    }
+
+    /// a `text/event-stream` body cut into pieces of `chunk_size` bytes, which
+    /// splits lines, JSON and multi byte characters at arbitrary positions
+    pub fn event_stream(body: &str, chunk_size: usize) -> Self {
+        Self {
+            status: 200,
+            headers: vec![("content-type".to_string(), "text/event-stream".to_string())],
+            body_chunks: body
+                .as_bytes()
+                .chunks(chunk_size)
+                .map(|chunk| chunk.to_vec())
+                .collect(),
+            delay: Duration::ZERO,
+            chunk_delay: Duration::from_millis(2),
+        }
+    }
+
+    /// a successful OpenAI chat completion with a single choice
+    pub fn chat_completion(answer: &str) -> Self {
+        let body = serde_json::json!({
+            "id": "chatcmpl-mock",
+            "object": "chat.completion",
+            "created": 1_700_000_000u64,
+            "model": "gpt-3.5-turbo-16k",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": answer},
+                "finish_reason": "stop"
+            }]
+        });
+        Self::json(200, body.to_string())
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn delayed(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    pub fn chunk_delay(mut self, chunk_delay: Duration) -> Self {
+        self.chunk_delay = chunk_delay;
+        self
+    }
+}
+
+/// what the server received, so tests can assert on paths, headers and bodies
+#[derive(Clone, Debug)]
+pub struct RecordedRequest {
+    /// address of the client side of the connection, identical addresses mean
+    /// the connection was reused
+    pub remote_address: SocketAddr,
+    pub method: String,
+    pub path: String,
+    pub headers: HeaderMap,
+    pub body: String,
+}
+
+impl RecordedRequest {
+    pub fn header(&self, name: &str) -> Option<String> {
+        self.headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string())
+    }
+
+    pub fn json_body(&self) -> serde_json::Value {
+        serde_json::from_str(&self.body).expect("request body should be JSON")
+    }
+}
+
+pub struct MockServer {
+    address: SocketAddr,
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+}
+
+impl MockServer {
+    /// binds to a random local port and serves the responses in order
+    pub async fn start(responses: Vec<MockResponse>) -> Self {
+        let script = Arc::new(Mutex::new(VecDeque::from(responses)));
+        let requests = Arc::new(Mutex::new(vec![]));
+
+        let service_script = script.clone();
+        let service_requests = requests.clone();
+        let make_service = make_service_fn(move |connection: &AddrStream| {
+            let remote_address = connection.remote_addr();
+            let script = service_script.clone();
+            let requests = service_requests.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    handle(remote_address, request, script.clone(), requests.clone())
+                }))
+            }
+        });
+
+        let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(make_service);
+        let address = server.local_addr();
+        tokio::spawn(server);
+
+        Self { address, requests }
+    }
+
+    /// `http://127.0.0.1:<port>`
+    pub fn url(&self) -> String {
+        format!("http://{}", self.address)
+    }
+
+    /// `http://127.0.0.1:<port>/v1`, the shape of an OpenAI compatible base url
+    pub fn base_url(&self) -> String {
+        format!("{}/v1", self.url())
+    }
+
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+/// an OpenAI client builder pointed at the server, with a test token and
+/// without retries. Tests set what differs, e.g. the model.
+pub fn open_ai_client_for(mock_server: &MockServer) -> OpenAiClientBuilder {
+    rust_llm_utils::OpenAiClient::builder()
+        .base_url(mock_server.base_url())
+        .allow_http(true)
+        .token("test-token")
+        .retry_policy(RetryPolicy::none())
+}
+
+/// the smallest prompt, for tests that care about the request rather than the
+/// answer
+pub fn ping() -> PromptType {
+    PromptType::new_zero_shot_prompt("ping".to_string())
+}
+
+/// a conversation with a system prompt and a single user message, which every
+/// backend has to translate into its own format
+pub fn weather_conversation() -> PromptType {
+    let mut conversation = Conversation::with_system_prompt("Answer in German.");
+    conversation.push_user("What is the weather like in Berlin?");
+    PromptType::new_conversation(conversation)
+}
+
+async fn handle(
+    remote_address: SocketAddr,
+    request: Request<Body>,
+    script: Arc<Mutex<VecDeque<MockResponse>>>,
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+) -> Result<Response<Body>, Infallible> {
+    let (parts, body) = request.into_parts();
+    let body = to_bytes(body).await.unwrap_or_default();
+
+    requests.lock().unwrap().push(RecordedRequest {
+        remote_address,
+        method: parts.method.to_string(),
+        path: parts
+            .uri
+            .path_and_query()
+            .map(|path_and_query| path_and_query.to_string())
+            .unwrap_or_default(),
+        headers: parts.headers,
+        body: String::from_utf8_lossy(&body).into_owned(),
+    });
+
+    let mock_response = {
+        let mut script = script.lock().unwrap();
+        if script.len() > 1 {
+            script.pop_front()
+        } else {
+            script.front().cloned()
+        }
+    }
+    .unwrap_or_else(|| {
+        MockResponse::json(404, r#"{"error": {"message": "no scripted response"}}"#)
+    });
+
+    tokio::time::sleep(mock_response.delay).await;
+
+    let mut response = Response::builder().status(mock_response.status);
+    for (name, value) in &mock_response.headers {
+        response = response.header(name, value);
+    }
+
+    if mock_response.body_chunks.len() <= 1 {
+        let body = mock_response
+            .body_chunks
+            .into_iter()
+            .next()
+            .unwrap_or_default();
+        return Ok(response.body(Body::from(body)).unwrap());
+    }
+
+    let (mut sender, body) = Body::channel();
+    tokio::spawn(async move {
+        for chunk in mock_response.body_chunks {
+            if sender.send_data(chunk.into()).await.is_err() {
+                return;
+            }
+            tokio::time::sleep(mock_response.chunk_delay).await;
+        }
+    });
+
+    Ok(response.body(body).unwrap())
+}
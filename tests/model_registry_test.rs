@@ -0,0 +1,202 @@
+mod mock_server;
+
+use rust_llm_utils::{
+    ContextWindowCheck, LlmError, ModelDescriptor, ModelPricing, ModelRegistry, OpenAiClient,
+    OpenAiModel, PromptType, Usage,
+};
+
+use mock_server::{open_ai_client_for, MockResponse, MockServer};
+
+fn client_for(
+    mock_server: &MockServer,
+    model: OpenAiModel,
+    model_registry: ModelRegistry,
+) -> OpenAiClient {
+    open_ai_client_for(mock_server)
+        .context_window_check(ContextWindowCheck::Reject)
+        .model(model)
+        .model_registry(model_registry)
+        .build()
+        .unwrap()
+}
+
+fn completion_with_usage(model: &str) -> MockResponse {
+    let body = serde_json::json!({
+        "id": "chatcmpl-mock",
+        "object": "chat.completion",
+        "created": 1_700_000_000u64,
+        "model": model,
+        "choices": [{"index": 0, "message": {"role": "assistant", "content": "pong"}}],
+        "usage": {"prompt_tokens": 1_000_000, "completion_tokens": 0, "total_tokens": 1_000_000}
+    });
+    MockResponse::json(200, body.to_string())
+}
+
+#[test]
+fn should_resolve_model_names_with_date_suffix() {
+    let model_registry = ModelRegistry::default();
+
+    assert_eq!(
+        model_registry
+            .lookup("gpt-4o-mini-2024-07-18")
+            .unwrap()
+            .name,
+        "gpt-4o-mini"
+    );
+    assert_eq!(
+        model_registry.lookup("gpt-4o-2024-08-06").unwrap().name,
+        "gpt-4o"
+    );
+    assert_eq!(model_registry.lookup("gpt-4-0613").unwrap().name, "gpt-4");
+    assert!(model_registry.lookup("gpt-4omni").is_none());
+    assert!(model_registry.get("gpt-4o-mini-2024-07-18").is_none());
+}
+
+#[test]
+fn should_not_resolve_other_models_to_a_prefix() {
+    let model_registry = ModelRegistry::default();
+
+    assert!(model_registry.lookup("o1-mini").is_none());
+    assert!(model_registry.lookup("o1-mini-2024-09-12").is_none());
+    assert!(model_registry.lookup("gpt-4.1-nano").is_none());
+    assert!(model_registry.lookup("gpt-4-1106-preview").is_none());
+    assert_eq!(
+        model_registry
+            .lookup("claude-3-5-sonnet-20241022")
+            .unwrap()
+            .name,
+        "claude-3-5-sonnet"
+    );
+    assert_eq!(
+        model_registry.lookup("gemini-2.0-flash-001").unwrap().name,
+        "gemini-2.0-flash"
+    );
+    assert_eq!(
+        model_registry.lookup("gemini-1.5-pro-latest").unwrap().name,
+        "gemini-1.5-pro"
+    );
+}
+
+#[test]
+fn should_price_the_lite_gemini_models_on_their_own() {
+    let model_registry = ModelRegistry::default();
+    let input_price = |name: &str| {
+        model_registry
+            .lookup(name)
+            .unwrap()
+            .pricing
+            .unwrap()
+            .input_per_million
+    };
+
+    assert_eq!(
+        model_registry.lookup("gemini-2.5-flash-lite").unwrap().name,
+        "gemini-2.5-flash-lite"
+    );
+    assert!(input_price("gemini-2.5-flash-lite") < input_price("gemini-2.5-flash"));
+    assert!(input_price("gemini-2.0-flash-lite-001") < input_price("gemini-2.0-flash"));
+    assert!(input_price("gemini-1.5-flash-8b-latest") < input_price("gemini-1.5-flash"));
+}
+
+#[test]
+fn should_price_usage_of_snapshots_at_the_base_model() {
+    let model_registry = ModelRegistry::default();
+    let usage = Usage {
+        prompt_tokens: 1_000_000,
+        completion_tokens: 100_000,
+        total_tokens: 1_100_000,
+        prompt_tokens_details: None,
+    };
+
+    let cost_usd = model_registry
+        .cost_usd("gpt-4o-2024-08-06", &usage)
+        .unwrap();
+
+    assert!((cost_usd - 3.5).abs() < 1e-9);
+    assert_eq!(model_registry.cost_usd("my-fine-tune", &usage), None);
+}
+
+#[tokio::test]
+async fn should_send_every_known_model_without_panicking() {
+    let mock_server = MockServer::start(vec![MockResponse::chat_completion("pong")]).await;
+
+    for model in [
+        OpenAiModel::Gpt40_32k,
+        OpenAiModel::Gpt4o,
+        OpenAiModel::O3Mini,
+    ] {
+        let open_ai_client = client_for(&mock_server, model.clone(), ModelRegistry::default());
+        assert!(open_ai_client.model_descriptor().is_some());
+
+        open_ai_client
+            .perform_request(&PromptType::new_zero_shot_prompt("ping".to_string()))
+            .await
+            .unwrap();
+    }
+
+    let sent_models: Vec<_> = mock_server
+        .requests()
+        .iter()
+        .map(|request| request.json_body()["model"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(sent_models, ["gpt-4-32k", "gpt-4o", "o3-mini"]);
+}
+
+#[tokio::test]
+async fn should_use_registered_descriptor_of_custom_model() {
+    let mock_server = MockServer::start(vec![completion_with_usage("mistral-7b-instruct")]).await;
+    let mut model_registry = ModelRegistry::default();
+    model_registry.register(ModelDescriptor {
+        context_window: 100,
+        pricing: Some(ModelPricing {
+            input_per_million: 0.25,
+            cached_input_per_million: None,
+            output_per_million: 0.25,
+        }),
+        ..ModelDescriptor::with_defaults("mistral-7b-instruct")
+    });
+    let open_ai_client = client_for(
+        &mock_server,
+        OpenAiModel::Custom("mistral-7b-instruct".to_string()),
+        model_registry,
+    );
+
+    let simplified_response = open_ai_client
+        .perform_request(&PromptType::new_zero_shot_prompt("ping".to_string()))
+        .await
+        .unwrap();
+    assert_eq!(simplified_response.cost_usd, Some(0.25));
+
+    // the registered context window of 100 tokens is enforced
+    let error = open_ai_client
+        .perform_request(&PromptType::new_zero_shot_prompt("ping ".repeat(200)))
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        LlmError::ContextWindowExceeded {
+            context_window: 100,
+            ..
+        }
+    ));
+}
+
+#[tokio::test]
+async fn should_send_unknown_custom_model_without_checks_or_cost() {
+    let mock_server = MockServer::start(vec![completion_with_usage("my-model")]).await;
+    let open_ai_client = client_for(
+        &mock_server,
+        OpenAiModel::Custom("my-model".to_string()),
+        ModelRegistry::default(),
+    );
+
+    assert!(open_ai_client.model_descriptor().is_none());
+
+    let simplified_response = open_ai_client
+        .perform_request(&PromptType::new_zero_shot_prompt("ping ".repeat(50_000)))
+        .await
+        .unwrap();
+
+    assert_eq!(simplified_response.cost_usd, None);
+    assert_eq!(mock_server.requests()[0].json_body()["model"], "my-model");
+}
@@ -1,7 +1,10 @@
+mod mock_server;
 mod topic_prompts;
 
 use rust_llm_utils::MultiShotExampleCount;
-use rust_llm_utils::{OpenAiClient, OpenAiSimplifiedResponse, PromptType};
+use rust_llm_utils::{LlmError, OpenAiClient, OpenAiSimplifiedResponse, PromptType};
+
+use mock_server::{open_ai_client_for, MockResponse, MockServer};
 
 use topic_prompts::programming::rust::fix_code::FixRustCode;
 use topic_prompts::test_prompts::{
@@ -20,8 +23,15 @@ async fn should_be_able_to_make_prompt_to_api() {
     let prompt = FixRustCode::new_from_prompt_template(rust_code_to_fix).query();
 
     let fix_rust_zero_shot_prompt = PromptType::new_zero_shot_prompt(prompt);
-    // we create the client with defaults
-    let open_ai_client = OpenAiClient::new(None, None);
+
+    // a local server standing in for OpenAI
+    let mock_server = MockServer::start(vec![MockResponse::chat_completion(
+        "fn some_func() -> String { String::from(\"abc\") }",
+    )])
+    .await;
+
+    // we create the client pointing to the local server
+    let open_ai_client = open_ai_client_for(&mock_server).build().unwrap();
 
     // then we make the call with the prompt
     let simplified_response_result = open_ai_client
@@ -30,6 +40,16 @@ async fn should_be_able_to_make_prompt_to_api() {
 
     assert!(simplified_response_result.is_ok());
 
+    let requests = mock_server.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].method, "POST");
+    assert_eq!(requests[0].path, "/v1/chat/completions");
+    assert_eq!(
+        requests[0].header("authorization").as_deref(),
+        Some("Bearer test-token")
+    );
+    assert_eq!(requests[0].json_body()["model"], "gpt-3.5-turbo-16k");
+
     // then we get the response to our question from the response
     if let Ok(simplified_response) = simplified_response_result {
         print_banner(&fix_rust_zero_shot_prompt.prompt(), simplified_response);
@@ -55,8 +75,14 @@ async fn should_be_able_to_make_prompt_to_api_using_multi_shot_prompt() {
         MultiShotExampleCount::Tree,
     );
 
-    // we create the client with defaults
-    let open_ai_client = OpenAiClient::new(None, None);
+    // a local server standing in for OpenAI
+    let mock_server = MockServer::start(vec![MockResponse::chat_completion(
+        "answer: it seems like winter weather. Es sieht aus wie Winterwetter ❄️",
+    )])
+    .await;
+
+    // we create the client pointing to the local server
+    let open_ai_client = open_ai_client_for(&mock_server).build().unwrap();
 
     // then we make the call with the prompt
     let simplified_response_result = open_ai_client
@@ -74,6 +100,71 @@ async fn should_be_able_to_make_prompt_to_api_using_multi_shot_prompt() {
     }
 }
 
+#[tokio::test]
+async fn should_send_default_headers_to_custom_base_url() {
+    let mock_server = MockServer::start(vec![MockResponse::chat_completion("ok")]).await;
+
+    let open_ai_client = OpenAiClient::builder()
+        .base_url(format!("{}/gateway/v1/", mock_server.url()))
+        .allow_http(true)
+        .default_header("x-gateway-team", "search")
+        .token("test-token")
+        .build()
+        .unwrap();
+
+    let simplified_response = open_ai_client
+        .perform_request(&PromptType::new_zero_shot_prompt("ping".to_string()))
+        .await
+        .unwrap();
+
+    assert_eq!(simplified_response.answer.as_deref(), Some("ok"));
+
+    let requests = mock_server.requests();
+    assert_eq!(requests[0].path, "/gateway/v1/chat/completions");
+    assert_eq!(
+        requests[0].header("x-gateway-team").as_deref(),
+        Some("search")
+    );
+}
+
+#[tokio::test]
+async fn should_reject_plain_http_base_url_unless_allowed() {
+    let build_result = OpenAiClient::builder()
+        .base_url("http://localhost:8000/v1")
+        .token("test-token")
+        .build();
+
+    assert!(matches!(build_result, Err(LlmError::InvalidRequest(_))));
+}
+
+#[tokio::test]
+async fn should_return_provider_error_from_error_payload() {
+    let mock_server = MockServer::start(vec![MockResponse::json(
+        401,
+        r#"{"error": {"message": "Incorrect API key provided", "type": "invalid_request_error", "param": null, "code": "invalid_api_key"}}"#,
+    )])
+    .await;
+
+    let open_ai_client = open_ai_client_for(&mock_server)
+        .token("wrong-token")
+        .build()
+        .unwrap();
+
+    let error = open_ai_client
+        .perform_request(&PromptType::new_zero_shot_prompt("ping".to_string()))
+        .await
+        .unwrap_err();
+
+    match error {
+        LlmError::Provider { status, error } => {
+            assert_eq!(status, 401);
+            assert_eq!(error.code.as_deref(), Some("invalid_api_key"));
+            assert_eq!(error.error_type.as_deref(), Some("invalid_request_error"));
+        }
+        other => panic!("expected a provider error, got {other:?}"),
+    }
+}
+
 fn print_banner(prompt: &str, simplified_response: OpenAiSimplifiedResponse) {
     let answer = if let Some(answer) = simplified_response.answer {
         answer
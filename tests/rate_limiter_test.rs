@@ -0,0 +1,85 @@
+mod mock_server;
+
+use rust_llm_utils::{OpenAiClient, RateLimiter};
+use std::time::Duration;
+use tokio::time::Instant;
+
+use mock_server::{open_ai_client_for, ping, MockResponse, MockServer};
+
+fn client_for(mock_server: &MockServer, rate_limiter: &RateLimiter) -> OpenAiClient {
+    open_ai_client_for(mock_server)
+        .rate_limiter(rate_limiter.clone())
+        .max_tokens(100)
+        .build()
+        .unwrap()
+}
+
+#[tokio::test(start_paused = true)]
+async fn should_wait_for_requests_per_minute_budget() {
+    let rate_limiter = RateLimiter::new(2, 10_000);
+    let started = Instant::now();
+
+    rate_limiter.acquire(10).await;
+    rate_limiter.acquire(10).await;
+    assert!(started.elapsed() < Duration::from_secs(1));
+
+    // the bucket refills one request every 30 seconds
+    rate_limiter.acquire(10).await;
+    assert!(started.elapsed() >= Duration::from_secs(30));
+}
+
+#[tokio::test(start_paused = true)]
+async fn should_wait_for_tokens_per_minute_budget() {
+    let rate_limiter = RateLimiter::new(1_000, 600);
+    let started = Instant::now();
+
+    rate_limiter.acquire(600).await;
+    assert!(started.elapsed() < Duration::from_secs(1));
+
+    // 300 tokens refill in 30 seconds at 600 per minute
+    rate_limiter.acquire(300).await;
+    assert!(started.elapsed() >= Duration::from_secs(30));
+    assert!(started.elapsed() < Duration::from_secs(31));
+}
+
+#[tokio::test]
+async fn should_share_budget_between_clients_and_count_max_tokens() {
+    let mock_server = MockServer::start(vec![MockResponse::chat_completion("pong")]).await;
+    let rate_limiter = RateLimiter::new(100, 10_000);
+
+    let first_client = client_for(&mock_server, &rate_limiter);
+    let second_client = first_client.clone();
+    let third_client = client_for(&mock_server, &rate_limiter);
+
+    first_client.perform_request(&ping()).await.unwrap();
+    second_client.perform_request(&ping()).await.unwrap();
+    third_client.perform_request(&ping()).await.unwrap();
+
+    assert_eq!(rate_limiter.available_requests(), 97);
+    // each request reserves its max tokens on top of the prompt estimate
+    assert!(rate_limiter.available_tokens() <= 10_000 - 3 * 100);
+    assert_eq!(mock_server.requests()[0].json_body()["max_tokens"], 100);
+}
+
+#[tokio::test]
+async fn should_follow_rate_limit_headers() {
+    let mock_server = MockServer::start(vec![MockResponse::chat_completion("pong")
+        .header("x-ratelimit-limit-requests", "60")
+        .header("x-ratelimit-remaining-requests", "0")
+        .header("x-ratelimit-remaining-tokens", "500")])
+    .await;
+    let rate_limiter = RateLimiter::new(10_000, 1_000_000);
+
+    client_for(&mock_server, &rate_limiter)
+        .perform_request(&ping())
+        .await
+        .unwrap();
+
+    assert_eq!(rate_limiter.available_requests(), 0);
+    assert!(rate_limiter.available_tokens() <= 1_000);
+
+    // with the limit lowered to 60 per minute the next request waits a second
+    let started = std::time::Instant::now();
+    rate_limiter.acquire(1).await;
+    assert!(started.elapsed() >= Duration::from_millis(900));
+}
@@ -0,0 +1,236 @@
+mod mock_server;
+
+use rust_llm_utils::{
+    JsonSchema, LlmError, OpenAiClient, OpenAiModel, PromptType, ResponseFormat, SchemaViolation,
+};
+use serde::Deserialize;
+
+use mock_server::{open_ai_client_for, MockResponse, MockServer};
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Sentiment {
+    label: String,
+    confidence: f64,
+}
+
+fn sentiment_schema() -> JsonSchema {
+    JsonSchema::new(
+        "sentiment",
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "label": {"type": "string", "enum": ["positive", "negative"]},
+                "confidence": {"type": "number", "minimum": 0, "maximum": 1}
+            },
+            "required": ["label", "confidence"],
+            "additionalProperties": false
+        }),
+    )
+}
+
+fn client_for(mock_server: &MockServer, json_repair_attempts: usize) -> OpenAiClient {
+    open_ai_client_for(mock_server)
+        .model(OpenAiModel::Gpt4oMini)
+        .json_repair_attempts(json_repair_attempts)
+        .build()
+        .unwrap()
+}
+
+fn prompt() -> PromptType {
+    PromptType::new_zero_shot_prompt("Sentiment of: I love it".to_string())
+        .with_json_schema(sentiment_schema())
+}
+
+#[tokio::test]
+async fn should_send_json_schema_and_return_valid_answer() {
+    let mock_server = MockServer::start(vec![MockResponse::chat_completion(
+        r#"{"label":"positive","confidence":0.9}"#,
+    )])
+    .await;
+    let open_ai_client = client_for(&mock_server, 0);
+
+    let response = open_ai_client.perform_request(&prompt()).await.unwrap();
+
+    assert_eq!(
+        response.answer_json::<Sentiment>().unwrap(),
+        Sentiment {
+            label: "positive".to_string(),
+            confidence: 0.9
+        }
+    );
+
+    let response_format = mock_server.requests()[0].json_body()["response_format"].clone();
+    assert_eq!(response_format["type"], "json_schema");
+    assert_eq!(response_format["json_schema"]["name"], "sentiment");
+    assert_eq!(response_format["json_schema"]["strict"], true);
+    assert_eq!(
+        response_format["json_schema"]["schema"]["required"][1],
+        "confidence"
+    );
+}
+
+#[tokio::test]
+async fn should_return_violation_path_without_repair_attempts() {
+    let mock_server = MockServer::start(vec![MockResponse::chat_completion(
+        r#"{"label":"meh","confidence":0.5}"#,
+    )])
+    .await;
+    let open_ai_client = client_for(&mock_server, 0);
+
+    let error = open_ai_client.perform_request(&prompt()).await.unwrap_err();
+
+    match error {
+        LlmError::SchemaValidation {
+            path,
+            message,
+            answer,
+        } => {
+            assert_eq!(path, "$.label");
+            assert!(message.contains("\"meh\""), "{message}");
+            assert_eq!(answer, r#"{"label":"meh","confidence":0.5}"#);
+        }
+        other => panic!("unexpected error {other:?}"),
+    }
+    assert_eq!(mock_server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn should_repair_invalid_answer() {
+    let mock_server = MockServer::start(vec![
+        MockResponse::chat_completion("Sure! Here is the JSON: {\"label\": \"positive\"}"),
+        MockResponse::chat_completion(r#"{"label":"positive","confidence":1.5}"#),
+        MockResponse::chat_completion(r#"{"label":"positive","confidence":1.0}"#),
+    ])
+    .await;
+    let open_ai_client = client_for(&mock_server, 2);
+
+    let response = open_ai_client.perform_request(&prompt()).await.unwrap();
+
+    assert_eq!(
+        response.answer.as_deref(),
+        Some(r#"{"label":"positive","confidence":1.0}"#)
+    );
+
+    let requests = mock_server.requests();
+    assert_eq!(requests.len(), 3);
+    let messages = requests[2].json_body()["messages"].clone();
+    assert_eq!(messages.as_array().unwrap().len(), 5);
+    assert_eq!(messages[1]["role"], "assistant");
+    assert_eq!(
+        messages[1]["content"],
+        "Sure! Here is the JSON: {\"label\": \"positive\"}"
+    );
+    assert!(messages[2]["content"]
+        .as_str()
+        .unwrap()
+        .contains("$: not valid JSON"));
+    assert!(messages[4]["content"]
+        .as_str()
+        .unwrap()
+        .contains("$.confidence: 1.5 violates maximum 1"));
+}
+
+#[tokio::test]
+async fn should_reject_models_without_json_mode() {
+    let mock_server = MockServer::start(vec![MockResponse::chat_completion("{}")]).await;
+    let open_ai_client = open_ai_client_for(&mock_server)
+        .model(OpenAiModel::Gpt40)
+        .build()
+        .unwrap();
+
+    let json_object_prompt = PromptType::new_zero_shot_prompt("Reply in JSON".to_string())
+        .with_response_format(ResponseFormat::JsonObject);
+    let error = open_ai_client
+        .perform_request(&json_object_prompt)
+        .await
+        .unwrap_err();
+
+    assert!(matches!(error, LlmError::InvalidRequest(_)));
+    assert!(mock_server.requests().is_empty());
+}
+
+#[test]
+fn should_validate_nested_values_and_references() {
+    let json_schema = JsonSchema::new(
+        "order",
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "items": {"type": "array", "items": {"$ref": "#/$defs/item"}, "minItems": 1},
+                "note": {"anyOf": [{"type": "string"}, {"type": "null"}]}
+            },
+            "required": ["items", "note"],
+            "$defs": {
+                "item": {
+                    "type": "object",
+                    "properties": {
+                        "name": {"type": "string", "minLength": 1},
+                        "quantity": {"type": "integer"}
+                    },
+                    "required": ["name", "quantity"]
+                }
+            }
+        }),
+    );
+
+    let valid = serde_json::json!({"items": [{"name": "tea", "quantity": 2}], "note": null});
+    assert_eq!(json_schema.validate(&valid), Ok(()));
+
+    let cases = [
+        (
+            serde_json::json!({"items": [{"name": "tea", "quantity": 2.5}], "note": null}),
+            "$.items[0].quantity",
+            "expected integer, got number",
+        ),
+        (
+            serde_json::json!({"items": [{"name": "", "quantity": 1}], "note": null}),
+            "$.items[0].name",
+            "length 0 violates minLength 1",
+        ),
+        (
+            serde_json::json!({"items": [], "note": null}),
+            "$.items",
+            "length 0 violates minItems 1",
+        ),
+        (
+            serde_json::json!({"items": [{"name": "tea", "quantity": 1}], "note": 3}),
+            "$.note",
+            "does not match any schema of anyOf",
+        ),
+        (
+            serde_json::json!({"items": [{"name": "tea"}], "note": null}),
+            "$.items[0]",
+            "missing required property quantity",
+        ),
+    ];
+    for (value, path, message) in cases {
+        assert_eq!(
+            json_schema.validate(&value),
+            Err(SchemaViolation {
+                path: path.to_string(),
+                message: message.to_string()
+            })
+        );
+    }
+
+    assert_eq!(
+        ResponseFormat::JsonObject
+            .validate("[1, 2]")
+            .unwrap_err()
+            .message,
+        "expected an object"
+    );
+}
+
+#[test]
+fn should_turn_a_violation_into_a_schema_validation_error() {
+    assert_eq!(
+        ResponseFormat::JsonObject.validate_answer("[1, 2]"),
+        Err(LlmError::SchemaValidation {
+            path: "$".to_string(),
+            message: "expected an object".to_string(),
+            answer: "[1, 2]".to_string(),
+        })
+    );
+    assert!(ResponseFormat::JsonObject.validate_answer("{}").is_ok());
+}
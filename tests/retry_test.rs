@@ -0,0 +1,161 @@
+mod mock_server;
+
+use rust_llm_utils::{LlmError, OpenAiClient, RetryPolicy};
+use std::time::{Duration, Instant};
+
+use mock_server::{open_ai_client_for, ping, MockResponse, MockServer};
+
+fn fast_retry_policy() -> RetryPolicy {
+    RetryPolicy {
+        max_attempts: 3,
+        base_delay: Duration::from_millis(1),
+        max_delay: Duration::from_millis(10),
+        jitter: 0.0,
+        ..RetryPolicy::default()
+    }
+}
+
+fn client_for(mock_server: &MockServer) -> OpenAiClient {
+    open_ai_client_for(mock_server)
+        .retry_policy(fast_retry_policy())
+        .build()
+        .unwrap()
+}
+
+/// waits for server hints of up to 2s
+fn patient_client_for(mock_server: &MockServer) -> OpenAiClient {
+    open_ai_client_for(mock_server)
+        .retry_policy(RetryPolicy {
+            max_delay: Duration::from_secs(2),
+            ..fast_retry_policy()
+        })
+        .build()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn should_retry_server_errors_until_success() {
+    let mock_server = MockServer::start(vec![
+        MockResponse::json(503, r#"{"error": {"message": "overloaded"}}"#),
+        MockResponse::json(500, "internal error"),
+        MockResponse::chat_completion("pong"),
+    ])
+    .await;
+
+    let simplified_response = client_for(&mock_server)
+        .perform_request(&ping())
+        .await
+        .unwrap();
+
+    assert_eq!(simplified_response.answer.as_deref(), Some("pong"));
+    assert_eq!(mock_server.requests().len(), 3);
+}
+
+#[tokio::test]
+async fn should_report_every_attempt_when_retries_are_exhausted() {
+    let mock_server = MockServer::start(vec![
+        MockResponse::json(
+            429,
+            r#"{"error": {"message": "slow down", "code": "rate_limit_exceeded"}}"#,
+        ),
+        MockResponse::json(503, "unavailable"),
+    ])
+    .await;
+
+    let error = client_for(&mock_server)
+        .perform_request(&ping())
+        .await
+        .unwrap_err();
+
+    let LlmError::AttemptsFailed(attempts) = &error else {
+        panic!("expected all attempts to be reported, got {error:?}");
+    };
+    assert_eq!(attempts.len(), 3);
+    assert!(matches!(
+        &attempts[0],
+        LlmError::Provider { status: 429, .. }
+    ));
+    assert!(matches!(
+        &attempts[1],
+        LlmError::HttpStatus { status: 503, .. }
+    ));
+    assert_eq!(error.last_attempt(), &attempts[2]);
+    assert_eq!(mock_server.requests().len(), 3);
+}
+
+#[tokio::test]
+async fn should_not_retry_client_errors() {
+    let mock_server = MockServer::start(vec![
+        MockResponse::json(400, r#"{"error": {"message": "bad request"}}"#),
+        MockResponse::chat_completion("pong"),
+    ])
+    .await;
+
+    let error = client_for(&mock_server)
+        .perform_request(&ping())
+        .await
+        .unwrap_err();
+
+    assert!(matches!(error, LlmError::Provider { status: 400, .. }));
+    assert_eq!(mock_server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn should_honour_retry_after_header() {
+    let mock_server = MockServer::start(vec![
+        MockResponse::json(429, r#"{"error": {"message": "slow down"}}"#)
+            .header("retry-after", "1"),
+        MockResponse::chat_completion("pong"),
+    ])
+    .await;
+
+    let started = Instant::now();
+    let simplified_response = patient_client_for(&mock_server)
+        .perform_request(&ping())
+        .await
+        .unwrap();
+
+    assert_eq!(simplified_response.answer.as_deref(), Some("pong"));
+    assert!(started.elapsed() >= Duration::from_secs(1));
+}
+
+#[tokio::test]
+async fn should_honour_rate_limit_reset_headers() {
+    let mock_server = MockServer::start(vec![
+        MockResponse::json(429, r#"{"error": {"message": "slow down"}}"#)
+            .header("x-ratelimit-reset-requests", "20ms")
+            .header("x-ratelimit-reset-tokens", "300ms"),
+        MockResponse::chat_completion("pong"),
+    ])
+    .await;
+
+    let started = Instant::now();
+    patient_client_for(&mock_server)
+        .perform_request(&ping())
+        .await
+        .unwrap();
+
+    assert!(started.elapsed() >= Duration::from_millis(300));
+    assert_eq!(mock_server.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn should_fail_fast_when_the_server_asks_to_wait_too_long() {
+    let mock_server = MockServer::start(vec![
+        MockResponse::json(429, r#"{"error": {"message": "daily limit reached"}}"#)
+            .header("retry-after", "86400")
+            .header("x-ratelimit-reset-requests", "23h59m"),
+        MockResponse::chat_completion("pong"),
+    ])
+    .await;
+
+    let started = Instant::now();
+    let error = patient_client_for(&mock_server)
+        .perform_request(&ping())
+        .await
+        .unwrap_err();
+
+    assert!(matches!(error, LlmError::Provider { status: 429, .. }));
+    assert!(started.elapsed() < Duration::from_secs(1));
+    assert_eq!(mock_server.requests().len(), 1);
+}
@@ -0,0 +1,110 @@
+mod mock_server;
+
+use futures::StreamExt;
+use rust_llm_utils::{LlmError, OpenAiClient, PromptType};
+
+use mock_server::{open_ai_client_for, MockResponse, MockServer};
+
+const WEATHER_EVENT_STREAM: &str = concat!(
+    ": keep-alive\n\n",
+    "data: {\"id\":\"chatcmpl-1\",\"object\":\"chat.completion.chunk\",\"created\":1700000000,\"model\":\"gpt-3.5-turbo-16k\",\"choices\":[{\"index\":0,\"delta\":{\"role\":\"assistant\",\"content\":\"\"},\"finish_reason\":null}]}\n\n",
+    "data: {\"id\":\"chatcmpl-1\",\"object\":\"chat.completion.chunk\",\"created\":1700000000,\"model\":\"gpt-3.5-turbo-16k\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Es sieht aus \"},\"finish_reason\":null}]}\n\n",
+    "data: {\"id\":\"chatcmpl-1\",\"object\":\"chat.completion.chunk\",\"created\":1700000000,\"model\":\"gpt-3.5-turbo-16k\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"wie Winterwetter ❄️\"},\"finish_reason\":null}]}\r\n\r\n",
+    "data: {\"id\":\"chatcmpl-1\",\"object\":\"chat.completion.chunk\",\"created\":1700000000,\"model\":\"gpt-3.5-turbo-16k\",\"choices\":[{\"index\":0,\"delta\":{},\"finish_reason\":\"stop\"}]}\n\n",
+    "data: [DONE]\n\n",
+);
+
+fn client_for(mock_server: &MockServer) -> OpenAiClient {
+    open_ai_client_for(mock_server).build().unwrap()
+}
+
+fn weather_prompt() -> PromptType {
+    PromptType::new_zero_shot_prompt("it is -8c and snowing in Berlin".to_string())
+}
+
+#[tokio::test]
+async fn should_stream_deltas_and_aggregate_the_answer() {
+    let mock_server =
+        MockServer::start(vec![MockResponse::event_stream(WEATHER_EVENT_STREAM, 4096)]).await;
+
+    let mut completion_stream = client_for(&mock_server)
+        .perform_request_stream(&weather_prompt())
+        .await
+        .unwrap();
+
+    let mut deltas = vec![];
+    while let Some(delta) = completion_stream.next().await {
+        deltas.push(delta.unwrap());
+    }
+
+    assert_eq!(deltas.len(), 4);
+    assert_eq!(deltas[0].role.as_deref(), Some("assistant"));
+    assert_eq!(deltas[1].content.as_deref(), Some("Es sieht aus "));
+    assert_eq!(deltas[3].finish_reason.as_deref(), Some("stop"));
+    assert_eq!(
+        completion_stream.response().answer.as_deref(),
+        Some("Es sieht aus wie Winterwetter ❄️")
+    );
+
+    assert_eq!(mock_server.requests()[0].json_body()["stream"], true);
+}
+
+#[tokio::test]
+async fn should_handle_events_split_across_reads() {
+    for chunk_size in [1, 3, 7, 64] {
+        let mock_server = MockServer::start(vec![MockResponse::event_stream(
+            WEATHER_EVENT_STREAM,
+            chunk_size,
+        )])
+        .await;
+
+        let simplified_response = client_for(&mock_server)
+            .perform_request_stream(&weather_prompt())
+            .await
+            .unwrap()
+            .into_response()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            simplified_response.answer.as_deref(),
+            Some("Es sieht aus wie Winterwetter ❄️"),
+            "chunk size {chunk_size}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn should_return_error_payload_sent_mid_stream() {
+    let event_stream = concat!(
+        "data: {\"id\":\"chatcmpl-1\",\"object\":\"chat.completion.chunk\",\"created\":1700000000,\"model\":\"gpt-3.5-turbo-16k\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Es\"},\"finish_reason\":null}]}\n\n",
+        "data: {\"error\":{\"message\":\"The server had an error\",\"type\":\"server_error\"}}\n\n",
+    );
+    let mock_server = MockServer::start(vec![MockResponse::event_stream(event_stream, 16)]).await;
+
+    let error = client_for(&mock_server)
+        .perform_request_stream(&weather_prompt())
+        .await
+        .unwrap()
+        .into_response()
+        .await
+        .unwrap_err();
+
+    assert!(matches!(error, LlmError::Provider { .. }));
+}
+
+#[tokio::test]
+async fn should_fail_when_stream_ends_without_terminator() {
+    let event_stream = "data: {\"id\":\"chatcmpl-1\",\"object\":\"chat.completion.chunk\",\"created\":1700000000,\"model\":\"gpt-3.5-turbo-16k\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Es\"},\"finish_reason\":null}]}\n\n";
+    let mock_server = MockServer::start(vec![MockResponse::event_stream(event_stream, 4096)]).await;
+
+    let error = client_for(&mock_server)
+        .perform_request_stream(&weather_prompt())
+        .await
+        .unwrap()
+        .into_response()
+        .await
+        .unwrap_err();
+
+    assert!(matches!(error, LlmError::Transport(_)));
+}
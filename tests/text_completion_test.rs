@@ -0,0 +1,203 @@
+mod mock_server;
+
+use rust_llm_utils::{
+    CostLedger, GenerationParams, JsonSchema, LlmError, MultiShotExampleCount,
+    MultiShotQuestionsAndAnswers, OpenAiClient, OpenAiModel, OpenAiTextCompletionOptions,
+    PromptType, TokenLogprob,
+};
+
+use mock_server::{open_ai_client_for, MockResponse, MockServer};
+
+struct SentimentQnA;
+
+impl MultiShotQuestionsAndAnswers for SentimentQnA {
+    fn multi_shot_questions_and_answers(&self) -> [String; 5] {
+        [
+            "question: I love it, answer: positive\n".to_string(),
+            "question: It broke after a day, answer: negative\n".to_string(),
+            "question: It is fine, answer: neutral\n".to_string(),
+            "question: Never again, answer: negative\n".to_string(),
+            "question: Best purchase this year, answer: positive\n".to_string(),
+        ]
+    }
+}
+
+fn client_for(mock_server: &MockServer) -> OpenAiClient {
+    open_ai_client_for(mock_server)
+        .model(OpenAiModel::Gpt35TurboInstruct)
+        .build()
+        .unwrap()
+}
+
+fn sentiment_prompt() -> PromptType {
+    PromptType::new_multi_shot_prompt(
+        "Arrived late but works".to_string(),
+        SentimentQnA,
+        MultiShotExampleCount::Two,
+    )
+}
+
+fn text_completion(text: &str, logprobs: serde_json::Value) -> MockResponse {
+    let body = serde_json::json!({
+        "id": "cmpl-1",
+        "object": "text_completion",
+        "created": 1700000000,
+        "model": "gpt-3.5-turbo-instruct",
+        "choices": [{
+            "index": 0,
+            "text": text,
+            "logprobs": logprobs,
+            "finish_reason": "stop"
+        }],
+        "usage": {"prompt_tokens": 1000, "completion_tokens": 500, "total_tokens": 1500}
+    });
+    MockResponse::json(200, body.to_string())
+}
+
+#[test]
+fn should_render_multi_shot_prompts_as_completion_prompts() {
+    assert_eq!(
+        sentiment_prompt().completion_prompt(),
+        concat!(
+            "question: I love it, answer: positive\n",
+            "question: It broke after a day, answer: negative\n",
+            "\nquestion: Arrived late but works\n",
+            "answer:",
+        )
+    );
+    assert_eq!(
+        PromptType::new_zero_shot_prompt("Once upon a time".to_string()).completion_prompt(),
+        "Once upon a time"
+    );
+}
+
+#[tokio::test]
+async fn should_send_the_completion_prompt_with_suffix_and_logprobs() {
+    let logprobs = serde_json::json!({
+        "tokens": [" positive", "\n"],
+        "token_logprobs": [-0.125, -0.5],
+        "top_logprobs": [
+            {" neutral": -2.5, " positive": -0.125},
+            {"\n": -0.5}
+        ],
+        "text_offset": [0, 9]
+    });
+    let mock_server = MockServer::start(vec![text_completion(" positive\n", logprobs)]).await;
+    let cost_ledger = CostLedger::default();
+    let open_ai_client = open_ai_client_for(&mock_server)
+        .model(OpenAiModel::Gpt35TurboInstruct)
+        .generation_params(GenerationParams::default().max_tokens(3))
+        .cost_ledger(cost_ledger.clone())
+        .build()
+        .unwrap();
+    let options = OpenAiTextCompletionOptions::default()
+        .suffix("\n---")
+        .logprobs(2);
+
+    let chat_response = open_ai_client
+        .perform_text_completion(&sentiment_prompt(), &options)
+        .await
+        .unwrap();
+
+    assert_eq!(chat_response.answer.as_deref(), Some(" positive\n"));
+    assert_eq!(
+        chat_response.tokens,
+        vec![
+            TokenLogprob {
+                token: " positive".to_string(),
+                logprob: -0.125,
+                token_id_maybe: None,
+                top_logprobs: vec![
+                    (" positive".to_string(), -0.125),
+                    (" neutral".to_string(), -2.5)
+                ],
+            },
+            TokenLogprob {
+                token: "\n".to_string(),
+                logprob: -0.5,
+                token_id_maybe: None,
+                top_logprobs: vec![("\n".to_string(), -0.5)],
+            },
+        ]
+    );
+    // 1000 * 1.5 / 1M + 500 * 2 / 1M
+    assert_eq!(chat_response.cost_usd, Some(0.0025));
+    assert_eq!(cost_ledger.total().requests, 1);
+
+    let request = &mock_server.requests()[0];
+    assert_eq!(request.path, "/v1/completions");
+    let body = request.json_body();
+    assert_eq!(body["model"], "gpt-3.5-turbo-instruct");
+    assert_eq!(body["prompt"], sentiment_prompt().completion_prompt());
+    assert_eq!(body["suffix"], "\n---");
+    assert_eq!(body["logprobs"], 2);
+    assert_eq!(body["max_tokens"], 3);
+    assert!(body.get("echo").is_none());
+    assert!(body.get("messages").is_none());
+}
+
+#[tokio::test]
+async fn should_return_the_logprobs_of_the_echoed_prompt() {
+    let logprobs = serde_json::json!({
+        "tokens": ["The", " sky", " is", " blue"],
+        "token_logprobs": [null, -6.25, -1.5, -0.75],
+        "top_logprobs": null,
+        "text_offset": [0, 3, 7, 10]
+    });
+    let mock_server = MockServer::start(vec![text_completion("The sky is blue", logprobs)]).await;
+    let open_ai_client = client_for(&mock_server);
+    let options = OpenAiTextCompletionOptions::default()
+        .echo(true)
+        .logprobs(0);
+
+    let response_body = open_ai_client
+        .perform_text_completion_request(
+            "The sky is blue",
+            &options,
+            &GenerationParams::default().max_tokens(1),
+        )
+        .await
+        .unwrap();
+
+    let logprobs = response_body.choices[0].logprobs.as_ref().unwrap();
+    assert_eq!(logprobs.token_logprobs[0], None);
+    assert_eq!(logprobs.text_offset, vec![0, 3, 7, 10]);
+    let tokens = logprobs.token_logprobs();
+    assert_eq!(tokens[0].logprob, 0.0);
+    assert_eq!(tokens[1].logprob, -6.25);
+    assert!(tokens[1].top_logprobs.is_empty());
+
+    let body = mock_server.requests()[0].json_body();
+    assert_eq!(body["echo"], true);
+    assert_eq!(body["logprobs"], 0);
+    assert_eq!(body["max_tokens"], 1);
+}
+
+#[tokio::test]
+async fn should_reject_more_than_five_logprobs() {
+    let open_ai_client = OpenAiClient::new(Some(OpenAiModel::Gpt35TurboInstruct), Some("token"));
+    let options = OpenAiTextCompletionOptions::default().logprobs(6);
+
+    let error = open_ai_client
+        .perform_text_completion(&sentiment_prompt(), &options)
+        .await
+        .unwrap_err();
+
+    assert!(matches!(error, LlmError::InvalidRequest(_)));
+}
+
+#[tokio::test]
+async fn should_reject_response_formats() {
+    let open_ai_client = OpenAiClient::new(Some(OpenAiModel::Gpt35TurboInstruct), Some("token"));
+    let prompt = sentiment_prompt().with_json_schema(JsonSchema::new(
+        "sentiment",
+        serde_json::json!({"type": "object"}),
+    ));
+
+    let error = open_ai_client
+        .perform_text_completion(&prompt, &OpenAiTextCompletionOptions::default())
+        .await
+        .unwrap_err();
+
+    assert!(matches!(error, LlmError::InvalidRequest(_)));
+}
@@ -0,0 +1,299 @@
+mod mock_server;
+
+use futures::StreamExt;
+use rust_llm_utils::{
+    ChatMessage, ChatTemplate, GenerationParams, JsonSchema, LlmClient, LlmError, PromptType,
+    RetryPolicy, TgiClient, TokenLogprob,
+};
+
+use mock_server::{weather_conversation, MockResponse, MockServer};
+
+fn client_for(mock_server: &MockServer, chat_template: ChatTemplate) -> TgiClient {
+    TgiClient::builder()
+        .base_url(mock_server.url())
+        .chat_template(chat_template)
+        .retry_policy(RetryPolicy::none())
+        .build()
+        .unwrap()
+}
+
+fn generate_response(generated_text: &str) -> MockResponse {
+    let body = serde_json::json!({
+        "generated_text": generated_text,
+        "details": {
+            "finish_reason": "eos_token",
+            "generated_tokens": 3,
+            "seed": null,
+            "prefill": [],
+            "tokens": [
+                {"id": 3201, "text": "Es", "logprob": -0.25, "special": false},
+                {"id": 1149, "text": " schneit", "logprob": -1.5, "special": false},
+                {"id": 2, "text": "</s>", "logprob": -0.01, "special": true}
+            ],
+            "top_tokens": [
+                [
+                    {"id": 3201, "text": "Es", "logprob": -0.25, "special": false},
+                    {"id": 4023, "text": "In", "logprob": -1.75, "special": false}
+                ],
+                [{"id": 1149, "text": " schneit", "logprob": -1.5, "special": false}],
+                [{"id": 2, "text": "</s>", "logprob": -0.01, "special": true}]
+            ]
+        }
+    });
+    MockResponse::json(200, body.to_string())
+        .header("x-prompt-tokens", "21")
+        .header("x-generated-tokens", "3")
+}
+
+#[test]
+fn should_render_chat_ml() {
+    let messages = [
+        ChatMessage::developer("Be brief."),
+        ChatMessage::user("Hi"),
+        ChatMessage::assistant("Hello!"),
+        ChatMessage::user("Bye"),
+    ];
+
+    assert_eq!(
+        ChatTemplate::ChatMl.render(&messages),
+        concat!(
+            "<|im_start|>system\nBe brief.<|im_end|>\n",
+            "<|im_start|>user\nHi<|im_end|>\n",
+            "<|im_start|>assistant\nHello!<|im_end|>\n",
+            "<|im_start|>user\nBye<|im_end|>\n",
+            "<|im_start|>assistant\n",
+        )
+    );
+}
+
+#[test]
+fn should_render_mistral_instruct_with_the_system_prompt_in_the_first_turn() {
+    let messages = [
+        ChatMessage::system("Be brief."),
+        ChatMessage::user("Hi"),
+        ChatMessage::assistant("Hello!"),
+        ChatMessage::user("Bye"),
+    ];
+
+    assert_eq!(
+        ChatTemplate::MistralInstruct.render(&messages),
+        "<s>[INST] Be brief.\n\nHi [/INST]Hello!</s>[INST] Bye [/INST]"
+    );
+}
+
+#[tokio::test]
+async fn should_send_the_rendered_prompt_to_generate() {
+    let mock_server = MockServer::start(vec![generate_response("Es schneit")]).await;
+    let tgi_client = TgiClient::builder()
+        .base_url(mock_server.url())
+        .chat_template(ChatTemplate::ChatMl)
+        .generation_params(GenerationParams {
+            temperature: Some(0.5),
+            max_tokens: Some(64),
+            stop: Some(vec!["\n\n".to_string()]),
+            seed: Some(7),
+            ..Default::default()
+        })
+        .top_n_tokens(2)
+        .build()
+        .unwrap();
+
+    let chat_response = tgi_client
+        .perform_request(&weather_conversation())
+        .await
+        .unwrap();
+
+    assert_eq!(chat_response.answer.as_deref(), Some("Es schneit"));
+    let usage = chat_response.usage.unwrap();
+    assert_eq!(
+        (
+            usage.prompt_tokens,
+            usage.completion_tokens,
+            usage.total_tokens
+        ),
+        (21, 3, 24)
+    );
+
+    let request = &mock_server.requests()[0];
+    assert_eq!(request.path, "/generate");
+    assert_eq!(request.header("authorization"), None);
+    assert_eq!(
+        request.json_body(),
+        serde_json::json!({
+            "inputs": concat!(
+                "<|im_start|>system\nAnswer in German.<|im_end|>\n",
+                "<|im_start|>user\nWhat is the weather like in Berlin?<|im_end|>\n",
+                "<|im_start|>assistant\n",
+            ),
+            "parameters": {
+                "max_new_tokens": 64,
+                "temperature": 0.5,
+                "stop": ["\n\n", "<|im_end|>"],
+                "seed": 7,
+                "top_n_tokens": 2,
+                "details": true,
+                "return_full_text": false
+            }
+        })
+    );
+}
+
+#[tokio::test]
+async fn should_return_the_tokens_with_their_logprobs() {
+    let mock_server = MockServer::start(vec![generate_response("Es schneit")]).await;
+    let tgi_client = client_for(&mock_server, ChatTemplate::MistralInstruct);
+
+    let chat_response = tgi_client
+        .perform_request(&weather_conversation())
+        .await
+        .unwrap();
+
+    assert_eq!(chat_response.tokens.len(), 3);
+    assert_eq!(
+        chat_response.tokens[0],
+        TokenLogprob {
+            token: "Es".to_string(),
+            logprob: -0.25,
+            token_id_maybe: Some(3201),
+            top_logprobs: vec![("Es".to_string(), -0.25), ("In".to_string(), -1.75)],
+        }
+    );
+    assert_eq!(chat_response.tokens[2].token_id_maybe, Some(2));
+
+    let request = &mock_server.requests()[0];
+    assert_eq!(
+        request.json_body()["inputs"],
+        "<s>[INST] Answer in German.\n\nWhat is the weather like in Berlin? [/INST]"
+    );
+}
+
+#[tokio::test]
+async fn should_send_the_bearer_token_of_inference_endpoints() {
+    let mock_server = MockServer::start(vec![generate_response("Es schneit")]).await;
+    let tgi_client = TgiClient::builder()
+        .base_url(mock_server.url())
+        .token("hf_test")
+        .build()
+        .unwrap();
+
+    tgi_client
+        .perform_request(&weather_conversation())
+        .await
+        .unwrap();
+
+    let request = &mock_server.requests()[0];
+    assert_eq!(
+        request.header("authorization").as_deref(),
+        Some("Bearer hf_test")
+    );
+}
+
+#[tokio::test]
+async fn should_constrain_the_answer_with_a_json_grammar() {
+    let schema = serde_json::json!({
+        "type": "object",
+        "properties": {"city": {"type": "string"}},
+        "required": ["city"]
+    });
+    let body = serde_json::json!({"generated_text": "{\"town\":\"Berlin\"}", "details": null});
+    let mock_server = MockServer::start(vec![MockResponse::json(200, body.to_string())]).await;
+    let tgi_client = client_for(&mock_server, ChatTemplate::Llama2);
+    let prompt = PromptType::new_zero_shot_prompt("Name a city".to_string())
+        .with_json_schema(JsonSchema::new("city", schema.clone()));
+
+    let error = tgi_client.perform_request(&prompt).await.unwrap_err();
+
+    assert!(matches!(error, LlmError::SchemaValidation { .. }));
+    let request = &mock_server.requests()[0];
+    assert_eq!(
+        request.json_body()["parameters"]["grammar"],
+        serde_json::json!({"type": "json", "value": schema})
+    );
+}
+
+#[tokio::test]
+async fn should_stream_the_token_texts() {
+    let events = concat!(
+        "data:{\"index\":1,\"token\":{\"id\":3201,\"text\":\"Es\",\"logprob\":-0.25,\"special\":false},\"generated_text\":null,\"details\":null}\n\n",
+        "data:{\"index\":2,\"token\":{\"id\":1149,\"text\":\" schneit\",\"logprob\":-1.5,\"special\":false},\"generated_text\":null,\"details\":null}\n\n",
+        "data:{\"index\":3,\"token\":{\"id\":2,\"text\":\"</s>\",\"logprob\":-0.01,\"special\":true},\"generated_text\":\"Es schneit\",\"details\":{\"finish_reason\":\"eos_token\",\"generated_tokens\":3,\"seed\":null}}\n\n",
+    );
+    let mock_server = MockServer::start(vec![MockResponse::event_stream(events, 40)]).await;
+    let tgi_client = client_for(&mock_server, ChatTemplate::Llama2);
+
+    let chat_stream = tgi_client
+        .chat_stream(&weather_conversation())
+        .await
+        .unwrap();
+    let pieces: Vec<String> = chat_stream.map(|piece| piece.unwrap()).collect().await;
+
+    assert_eq!(pieces, vec!["Es".to_string(), " schneit".to_string()]);
+    assert_eq!(mock_server.requests()[0].path, "/generate_stream");
+}
+
+#[tokio::test]
+async fn should_fail_the_stream_on_an_error_event() {
+    let events = concat!(
+        "data:{\"index\":1,\"token\":{\"id\":3201,\"text\":\"Es\",\"logprob\":-0.25,\"special\":false},\"generated_text\":null,\"details\":null}\n\n",
+        "data:{\"error\":\"Request failed during generation: CUDA out of memory\",\"error_type\":\"generation\"}\n\n",
+    );
+    let mock_server = MockServer::start(vec![MockResponse::event_stream(events, 64)]).await;
+    let tgi_client = client_for(&mock_server, ChatTemplate::Llama2);
+
+    let results: Vec<Result<String, LlmError>> = tgi_client
+        .perform_request_stream(&weather_conversation())
+        .await
+        .unwrap()
+        .collect()
+        .await;
+
+    assert_eq!(results.len(), 2);
+    assert!(matches!(
+        &results[1],
+        Err(LlmError::HttpStatus { status: 200, body }) if body.contains("CUDA out of memory")
+    ));
+}
+
+#[tokio::test]
+async fn should_return_the_status_of_failed_requests() {
+    let body = serde_json::json!({"error": "Input validation error: `inputs` must have less than 4096 tokens", "error_type": "validation"});
+    let mock_server = MockServer::start(vec![MockResponse::json(422, body.to_string())]).await;
+    let tgi_client = client_for(&mock_server, ChatTemplate::Llama2);
+
+    let error = tgi_client
+        .perform_request(&weather_conversation())
+        .await
+        .unwrap_err();
+
+    assert!(matches!(error, LlmError::HttpStatus { status: 422, .. }));
+}
+
+#[tokio::test]
+async fn should_reject_unsupported_params() {
+    let tgi_client = TgiClient::builder().build().unwrap();
+    let params = GenerationParams {
+        presence_penalty: Some(0.5),
+        ..Default::default()
+    };
+
+    let error = tgi_client
+        .perform_request_with_params(&weather_conversation(), &params)
+        .await
+        .unwrap_err();
+
+    assert!(matches!(error, LlmError::InvalidRequest(_)));
+}
+
+#[test]
+fn should_count_the_tokens_of_the_rendered_prompt() {
+    let tgi_client = TgiClient::builder()
+        .chat_template(ChatTemplate::ChatMl)
+        .build()
+        .unwrap();
+
+    assert_eq!(tgi_client.model_name(), "tgi");
+    let empty_prompt = PromptType::new_zero_shot_prompt(String::new());
+    assert!(
+        tgi_client.count_tokens(&weather_conversation()) > tgi_client.count_tokens(&empty_prompt)
+    );
+}
@@ -0,0 +1,102 @@
+mod mock_server;
+
+use futures::StreamExt;
+use rust_llm_utils::{LlmError, OpenAiClient, TimeoutKind, Timeouts};
+use std::collections::HashSet;
+use std::time::Duration;
+
+use mock_server::{open_ai_client_for, ping, MockResponse, MockServer};
+
+fn client_for(mock_server: &MockServer, timeouts: Timeouts) -> OpenAiClient {
+    open_ai_client_for(mock_server)
+        .timeouts(timeouts)
+        .build()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn should_reuse_pooled_connection_across_requests_and_clones() {
+    let mock_server = MockServer::start(vec![MockResponse::chat_completion("pong")]).await;
+    let open_ai_client = client_for(&mock_server, Timeouts::default());
+    let cloned_client = open_ai_client.clone();
+
+    for _ in 0..3 {
+        open_ai_client.perform_request(&ping()).await.unwrap();
+        cloned_client.perform_request(&ping()).await.unwrap();
+    }
+
+    let remote_addresses: HashSet<_> = mock_server
+        .requests()
+        .iter()
+        .map(|request| request.remote_address)
+        .collect();
+    assert_eq!(mock_server.requests().len(), 6);
+    assert_eq!(remote_addresses.len(), 1);
+}
+
+#[tokio::test]
+async fn should_fail_with_read_timeout_when_headers_are_late() {
+    let mock_server = MockServer::start(vec![
+        MockResponse::chat_completion("pong").delayed(Duration::from_millis(500))
+    ])
+    .await;
+    let timeouts = Timeouts {
+        read: Some(Duration::from_millis(50)),
+        ..Timeouts::default()
+    };
+
+    let error = client_for(&mock_server, timeouts)
+        .perform_request(&ping())
+        .await
+        .unwrap_err();
+
+    assert_eq!(error, LlmError::Timeout(TimeoutKind::Read));
+}
+
+#[tokio::test]
+async fn should_fail_with_overall_timeout() {
+    let mock_server = MockServer::start(vec![
+        MockResponse::chat_completion("pong").delayed(Duration::from_millis(500))
+    ])
+    .await;
+    let timeouts = Timeouts {
+        overall: Some(Duration::from_millis(50)),
+        ..Timeouts::default()
+    };
+
+    let error = client_for(&mock_server, timeouts)
+        .perform_request(&ping())
+        .await
+        .unwrap_err();
+
+    assert_eq!(error, LlmError::Timeout(TimeoutKind::Overall));
+}
+
+#[tokio::test]
+async fn should_fail_with_read_timeout_when_stream_stalls() {
+    let event_stream = concat!(
+        "data: {\"id\":\"chatcmpl-1\",\"object\":\"chat.completion.chunk\",\"created\":1700000000,\"model\":\"gpt-3.5-turbo-16k\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Es\"},\"finish_reason\":null}]}\n\n",
+        "data: [DONE]\n\n",
+    );
+    let stalled_response =
+        MockResponse::event_stream(event_stream, 80).chunk_delay(Duration::from_millis(500));
+    let mock_server = MockServer::start(vec![stalled_response]).await;
+    let timeouts = Timeouts {
+        read: Some(Duration::from_millis(100)),
+        ..Timeouts::default()
+    };
+
+    let mut completion_stream = client_for(&mock_server, timeouts)
+        .perform_request_stream(&ping())
+        .await
+        .unwrap();
+
+    let mut error_maybe = None;
+    while let Some(delta_result) = completion_stream.next().await {
+        if let Err(error) = delta_result {
+            error_maybe = Some(error);
+        }
+    }
+
+    assert_eq!(error_maybe, Some(LlmError::Timeout(TimeoutKind::Read)));
+}
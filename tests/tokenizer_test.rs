@@ -0,0 +1,170 @@
+mod mock_server;
+
+use rust_llm_utils::{
+    BpeTokenizer, ChatMessage, ContextWindowCheck, LlmError, OpenAiModel, PromptType, Tokenizer,
+    TokenizerEncoding,
+};
+
+use mock_server::{open_ai_client_for, MockResponse, MockServer};
+
+/// a tiny vocabulary in the `.tiktoken` format: every single byte plus a few
+/// merges that spell out "hello world"
+fn tiny_vocabulary() -> String {
+    use base64::engine::general_purpose::STANDARD as BASE64;
+    use base64::Engine;
+
+    let merges: [&[u8]; 9] = [
+        b"he", b"ll", b"llo", b"hello", b" w", b"or", b" wor", b"ld", b" world",
+    ];
+
+    (0u8..=255)
+        .map(|byte| vec![byte])
+        .chain(merges.iter().map(|merge| merge.to_vec()))
+        .enumerate()
+        .map(|(rank, token)| format!("{} {rank}\n", BASE64.encode(token)))
+        .collect()
+}
+
+fn tiny_tokenizer() -> BpeTokenizer {
+    BpeTokenizer::from_tiktoken_bytes(TokenizerEncoding::Cl100kBase, tiny_vocabulary().as_bytes())
+        .unwrap()
+}
+
+#[test]
+fn should_encode_with_lowest_rank_merges_first() {
+    let bpe_tokenizer = tiny_tokenizer();
+
+    assert_eq!(bpe_tokenizer.encode("hello world"), vec![259, 264]);
+    // "hellow" is not in the vocabulary, so it merges down to "hello" + "w"
+    assert_eq!(bpe_tokenizer.encode("hellow"), vec![259, b'w' as u32]);
+    // numbers are split into groups of at most three digits before merging
+    assert_eq!(bpe_tokenizer.encode("12345").len(), 5);
+    assert_eq!(bpe_tokenizer.count_tokens("hello world!"), 3);
+}
+
+#[test]
+fn should_decode_back_to_the_text() {
+    let bpe_tokenizer = tiny_tokenizer();
+    let text = "hello world, grüße ❄️\n\n  it's -8c";
+
+    assert_eq!(bpe_tokenizer.decode(&bpe_tokenizer.encode(text)), text);
+}
+
+#[test]
+fn should_load_the_vocabulary_from_a_tiktoken_file() {
+    let path = std::env::temp_dir().join(format!(
+        "rust_llm_utils_tiny_{}.tiktoken",
+        std::process::id()
+    ));
+    std::fs::write(&path, tiny_vocabulary()).unwrap();
+
+    let bpe_tokenizer = BpeTokenizer::from_tiktoken_file(TokenizerEncoding::Cl100kBase, &path);
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(bpe_tokenizer.unwrap().encode("hello world"), vec![259, 264]);
+}
+
+#[test]
+fn should_reject_invalid_vocabulary() {
+    let result =
+        BpeTokenizer::from_tiktoken_bytes(TokenizerEncoding::O200kBase, b"not-base64!! x\n");
+
+    assert!(matches!(result, Err(LlmError::Tokenizer(_))));
+}
+
+#[test]
+fn should_count_prompt_and_message_tokens() {
+    let tokenizer = Tokenizer::from(tiny_tokenizer());
+    let prompt = PromptType::new_zero_shot_prompt("hello world".to_string());
+    let message = ChatMessage::user("hello world");
+
+    assert_eq!(prompt.count_tokens(&tokenizer), 2);
+    // 3 tokens of chat format overhead, "user" as single bytes and the content
+    assert_eq!(message.count_tokens(&tokenizer), 3 + 4 + 2);
+    assert_eq!(prompt.count_tokens(&Tokenizer::Estimate), 3);
+}
+
+#[tokio::test]
+async fn should_reject_prompt_exceeding_context_window_before_sending() {
+    let mock_server = MockServer::start(vec![MockResponse::chat_completion("pong")]).await;
+    let open_ai_client = open_ai_client_for(&mock_server)
+        .context_window_check(ContextWindowCheck::Reject)
+        .max_tokens(1_000)
+        .build()
+        .unwrap();
+
+    // about 16k estimated tokens, which only overflows together with max tokens
+    let long_prompt = PromptType::new_zero_shot_prompt("abcd".repeat(16_000));
+
+    let error = open_ai_client
+        .perform_request(&long_prompt)
+        .await
+        .unwrap_err();
+
+    match error {
+        LlmError::ContextWindowExceeded {
+            prompt_tokens,
+            max_tokens,
+            context_window,
+            ..
+        } => {
+            assert!(prompt_tokens >= 16_000);
+            assert_eq!(max_tokens, 1_000);
+            assert_eq!(context_window, 16_385);
+        }
+        other => panic!("expected the context window to be exceeded, got {other:?}"),
+    }
+    assert!(mock_server.requests().is_empty());
+}
+
+#[tokio::test]
+async fn should_send_prompt_exceeding_context_window_when_only_warning() {
+    let mock_server = MockServer::start(vec![MockResponse::chat_completion("pong")]).await;
+    let open_ai_client = open_ai_client_for(&mock_server)
+        .context_window_check(ContextWindowCheck::Warn)
+        .build()
+        .unwrap();
+
+    let long_prompt = PromptType::new_zero_shot_prompt("abcd".repeat(20_000));
+
+    open_ai_client.perform_request(&long_prompt).await.unwrap();
+
+    assert_eq!(mock_server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn should_reject_a_tokenizer_with_another_encoding_than_the_model() {
+    let mock_server = MockServer::start(vec![]).await;
+
+    let result = open_ai_client_for(&mock_server)
+        .model(OpenAiModel::Gpt4o)
+        .tokenizer(tiny_tokenizer())
+        .build();
+
+    assert!(matches!(result, Err(LlmError::InvalidRequest(_))));
+
+    let o200k_tokenizer = BpeTokenizer::from_tiktoken_bytes(
+        TokenizerEncoding::O200kBase,
+        tiny_vocabulary().as_bytes(),
+    )
+    .unwrap();
+    open_ai_client_for(&mock_server)
+        .model(OpenAiModel::Gpt4o)
+        .tokenizer(o200k_tokenizer)
+        .build()
+        .unwrap();
+}
+
+#[test]
+fn should_count_the_tokens_of_a_chat() {
+    let tokenizer = Tokenizer::from(tiny_tokenizer());
+    let messages = [
+        ChatMessage::system("hello"),
+        ChatMessage::user("hello world"),
+    ];
+
+    assert_eq!(
+        tokenizer.count_chat_tokens(&messages),
+        tokenizer.count_messages_tokens([("system", "hello"), ("user", "hello world")])
+    );
+}
@@ -0,0 +1,253 @@
+mod mock_server;
+
+use rust_llm_utils::{
+    CostLedger, LlmError, ModelDescriptor, ModelRegistry, OpenAiClient, OpenAiModel, PromptType,
+    ResponseFormat, ToolRegistry,
+};
+use serde::Deserialize;
+
+use mock_server::{open_ai_client_for, MockResponse, MockServer};
+
+#[derive(Deserialize)]
+struct WeatherArguments {
+    city: String,
+}
+
+fn tool_registry() -> ToolRegistry {
+    let mut tool_registry = ToolRegistry::default();
+    tool_registry.register(
+        "get_weather",
+        "current weather of a city",
+        serde_json::json!({
+            "type": "object",
+            "properties": {"city": {"type": "string"}},
+            "required": ["city"]
+        }),
+        |arguments: WeatherArguments| async move {
+            match arguments.city.as_str() {
+                "Atlantis" => Err(format!("{} is not on the map", arguments.city)),
+                city => Ok(serde_json::json!({"city": city, "forecast": "sunny"})),
+            }
+        },
+    );
+    tool_registry
+}
+
+fn tool_calls_response(calls: &[(&str, &str, &str)]) -> MockResponse {
+    let tool_calls: Vec<serde_json::Value> = calls
+        .iter()
+        .map(|(id, name, arguments)| {
+            serde_json::json!({
+                "id": id,
+                "type": "function",
+                "function": {"name": name, "arguments": arguments}
+            })
+        })
+        .collect();
+    let body = serde_json::json!({
+        "id": "chatcmpl-mock",
+        "object": "chat.completion",
+        "created": 1_700_000_000u64,
+        "model": "gpt-4o-mini",
+        "choices": [{
+            "index": 0,
+            "message": {"role": "assistant", "content": null, "tool_calls": tool_calls},
+            "finish_reason": "tool_calls"
+        }],
+        "usage": {"prompt_tokens": 100, "completion_tokens": 20, "total_tokens": 120}
+    });
+    MockResponse::json(200, body.to_string())
+}
+
+fn final_response(answer: &str) -> MockResponse {
+    let body = serde_json::json!({
+        "id": "chatcmpl-mock",
+        "object": "chat.completion",
+        "created": 1_700_000_000u64,
+        "model": "gpt-4o-mini",
+        "choices": [{
+            "index": 0,
+            "message": {"role": "assistant", "content": answer},
+            "finish_reason": "stop"
+        }],
+        "usage": {"prompt_tokens": 200, "completion_tokens": 10, "total_tokens": 210}
+    });
+    MockResponse::json(200, body.to_string())
+}
+
+fn client_for(mock_server: &MockServer, model: OpenAiModel) -> OpenAiClient {
+    open_ai_client_for(mock_server)
+        .model(model)
+        .build()
+        .unwrap()
+}
+
+fn prompt() -> PromptType {
+    PromptType::new_zero_shot_prompt("What is the weather in Paris and Atlantis?".to_string())
+}
+
+#[tokio::test]
+async fn should_run_tool_handlers_until_final_answer() {
+    let mock_server = MockServer::start(vec![
+        tool_calls_response(&[
+            ("call_1", "get_weather", r#"{"city":"Paris"}"#),
+            ("call_2", "get_weather", r#"{"city":"Atlantis"}"#),
+        ]),
+        tool_calls_response(&[
+            ("call_3", "get_tides", r#"{"city":"Paris"}"#),
+            ("call_4", "get_weather", r#"{"town":"Paris"}"#),
+        ]),
+        final_response("Sunny in Paris, Atlantis could not be found."),
+    ])
+    .await;
+    let cost_ledger = CostLedger::default();
+    let open_ai_client = open_ai_client_for(&mock_server)
+        .model(OpenAiModel::Gpt4oMini)
+        .cost_ledger(cost_ledger.clone())
+        .build()
+        .unwrap();
+
+    let response = open_ai_client
+        .perform_request_with_tools(&prompt(), &tool_registry())
+        .await
+        .unwrap();
+
+    assert_eq!(
+        response.answer.as_deref(),
+        Some("Sunny in Paris, Atlantis could not be found.")
+    );
+    let usage = response.usage.unwrap();
+    assert_eq!(usage.prompt_tokens, 400);
+    assert_eq!(usage.completion_tokens, 50);
+    assert_eq!(cost_ledger.total().requests, 3);
+    assert!((response.cost_usd.unwrap() - cost_ledger.total().cost_usd).abs() < 1e-12);
+
+    let requests = mock_server.requests();
+    assert_eq!(requests.len(), 3);
+
+    let first_body = requests[0].json_body();
+    assert_eq!(first_body["tools"][0]["type"], "function");
+    assert_eq!(first_body["tools"][0]["function"]["name"], "get_weather");
+    assert_eq!(
+        first_body["tools"][0]["function"]["parameters"]["required"][0],
+        "city"
+    );
+
+    let second_messages = requests[1].json_body()["messages"].clone();
+    assert_eq!(second_messages.as_array().unwrap().len(), 4);
+    assert_eq!(second_messages[1]["role"], "assistant");
+    assert_eq!(second_messages[1]["tool_calls"][0]["id"], "call_1");
+    assert_eq!(second_messages[2]["role"], "tool");
+    assert_eq!(second_messages[2]["tool_call_id"], "call_1");
+    assert_eq!(
+        second_messages[2]["content"],
+        r#"{"city":"Paris","forecast":"sunny"}"#
+    );
+    assert_eq!(second_messages[3]["tool_call_id"], "call_2");
+    assert_eq!(
+        second_messages[3]["content"],
+        "error: Atlantis is not on the map"
+    );
+
+    let third_messages = requests[2].json_body()["messages"].clone();
+    assert_eq!(third_messages.as_array().unwrap().len(), 7);
+    assert_eq!(
+        third_messages[5]["content"],
+        "error: unknown tool get_tides"
+    );
+    assert!(third_messages[6]["content"]
+        .as_str()
+        .unwrap()
+        .starts_with("error: invalid arguments"));
+}
+
+#[tokio::test]
+async fn should_stop_after_max_steps() {
+    let mock_server = MockServer::start(vec![tool_calls_response(&[(
+        "call_1",
+        "get_weather",
+        r#"{"city":"Paris"}"#,
+    )])])
+    .await;
+    let open_ai_client = client_for(&mock_server, OpenAiModel::Gpt4oMini);
+
+    let error = open_ai_client
+        .perform_request_with_tools(&prompt(), &ToolRegistry::with_max_steps(2))
+        .await
+        .unwrap_err();
+
+    assert_eq!(error, LlmError::ToolStepsExceeded { max_steps: 2 });
+    assert_eq!(mock_server.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn should_answer_without_tools_and_reject_models_without_tools() {
+    let mock_server = MockServer::start(vec![final_response("I cannot check the weather.")]).await;
+    let open_ai_client = client_for(&mock_server, OpenAiModel::Gpt4oMini);
+
+    let response = open_ai_client
+        .perform_request_with_tools(&prompt(), &tool_registry())
+        .await
+        .unwrap();
+    assert_eq!(
+        response.answer.as_deref(),
+        Some("I cannot check the weather.")
+    );
+    assert_eq!(response.usage.unwrap().prompt_tokens, 200);
+
+    let mut model_registry = ModelRegistry::empty();
+    model_registry.register(ModelDescriptor::with_defaults("no-tools"));
+    let no_tools_client = open_ai_client_for(&mock_server)
+        .model(OpenAiModel::Custom("no-tools".to_string()))
+        .model_registry(model_registry)
+        .build()
+        .unwrap();
+    let error = no_tools_client
+        .perform_request_with_tools(&prompt(), &tool_registry())
+        .await
+        .unwrap_err();
+    assert!(matches!(error, LlmError::InvalidRequest(_)));
+    assert_eq!(mock_server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn should_repair_a_final_answer_that_does_not_match_the_response_format() {
+    let mock_server = MockServer::start(vec![
+        tool_calls_response(&[("call_1", "get_weather", r#"{"city":"Paris"}"#)]),
+        final_response("Sunny in Paris."),
+        final_response(r#"{"forecast":"sunny"}"#),
+    ])
+    .await;
+    let open_ai_client = open_ai_client_for(&mock_server)
+        .model(OpenAiModel::Gpt4oMini)
+        .json_repair_attempts(1)
+        .build()
+        .unwrap();
+    let prompt = prompt().with_response_format(ResponseFormat::JsonObject);
+
+    let response = open_ai_client
+        .perform_request_with_tools(&prompt, &tool_registry())
+        .await
+        .unwrap();
+
+    assert_eq!(response.answer.as_deref(), Some(r#"{"forecast":"sunny"}"#));
+    assert_eq!(response.usage.unwrap().prompt_tokens, 500);
+    let third_messages = mock_server.requests()[2].json_body()["messages"].clone();
+    assert_eq!(third_messages[3]["content"], "Sunny in Paris.");
+    assert_eq!(third_messages[4]["role"], "user");
+}
+
+#[tokio::test]
+async fn should_fail_when_the_final_answer_does_not_match_the_response_format() {
+    let mock_server = MockServer::start(vec![final_response("Sunny in Paris.")]).await;
+    let open_ai_client = client_for(&mock_server, OpenAiModel::Gpt4oMini);
+    let prompt = prompt().with_response_format(ResponseFormat::JsonObject);
+
+    let error = open_ai_client
+        .perform_request_with_tools(&prompt, &tool_registry())
+        .await
+        .unwrap_err();
+
+    assert!(matches!(error, LlmError::SchemaValidation { .. }));
+    assert_eq!(mock_server.requests().len(), 1);
+}
@@ -0,0 +1,137 @@
+mod mock_server;
+
+use rust_llm_utils::{CostLedger, OpenAiClient};
+
+use mock_server::{open_ai_client_for, ping, MockResponse, MockServer};
+
+fn completion_with_usage(
+    model: &str,
+    prompt_tokens: u64,
+    cached_tokens: u64,
+    completion_tokens: u64,
+) -> MockResponse {
+    let body = serde_json::json!({
+        "id": "chatcmpl-mock",
+        "object": "chat.completion",
+        "created": 1_700_000_000u64,
+        "model": model,
+        "choices": [{
+            "index": 0,
+            "message": {"role": "assistant", "content": "pong"},
+            "finish_reason": "stop"
+        }],
+        "usage": {
+            "prompt_tokens": prompt_tokens,
+            "completion_tokens": completion_tokens,
+            "total_tokens": prompt_tokens + completion_tokens,
+            "prompt_tokens_details": {"cached_tokens": cached_tokens}
+        }
+    });
+    MockResponse::json(200, body.to_string())
+}
+
+fn client_for(mock_server: &MockServer, cost_ledger: &CostLedger) -> OpenAiClient {
+    open_ai_client_for(mock_server)
+        .cost_ledger(cost_ledger.clone())
+        .build()
+        .unwrap()
+}
+
+fn assert_close(actual: f64, expected: f64) {
+    assert!(
+        (actual - expected).abs() < 1e-12,
+        "expected {expected}, got {actual}"
+    );
+}
+
+#[tokio::test]
+async fn should_expose_usage_and_cost_on_response() {
+    let mock_server = MockServer::start(vec![completion_with_usage(
+        "gpt-3.5-turbo-16k-0613",
+        1_000,
+        200,
+        500,
+    )])
+    .await;
+
+    let simplified_response = client_for(&mock_server, &CostLedger::default())
+        .perform_request(&ping())
+        .await
+        .unwrap();
+
+    let usage = simplified_response.usage.unwrap();
+    assert_eq!(usage.prompt_tokens, 1_000);
+    assert_eq!(usage.cached_tokens(), 200);
+    assert_eq!(usage.completion_tokens, 500);
+    // 1000 input tokens at 3 USD and 500 output tokens at 4 USD per million
+    assert_close(simplified_response.cost_usd.unwrap(), 0.005);
+}
+
+#[tokio::test]
+async fn should_aggregate_spend_by_model_and_tag() {
+    let mock_server = MockServer::start(vec![
+        completion_with_usage("gpt-3.5-turbo-16k", 1_000, 0, 1_000),
+        completion_with_usage("gpt-4-32k-0613", 1_000, 0, 1_000),
+        completion_with_usage("my-fine-tuned-llama", 100, 0, 100),
+    ])
+    .await;
+    let cost_ledger = CostLedger::default();
+    let open_ai_client = client_for(&mock_server, &cost_ledger);
+
+    open_ai_client
+        .with_tag("ingest")
+        .perform_request(&ping())
+        .await
+        .unwrap();
+    open_ai_client
+        .with_tag("ingest")
+        .perform_request(&ping())
+        .await
+        .unwrap();
+    open_ai_client.perform_request(&ping()).await.unwrap();
+
+    let total = cost_ledger.total();
+    assert_eq!(total.requests, 3);
+    assert_eq!(total.prompt_tokens, 2_100);
+    assert_close(total.cost_usd, 0.007 + 0.18);
+
+    let by_model = cost_ledger.by_model();
+    assert_close(by_model["gpt-3.5-turbo-16k"].cost_usd, 0.007);
+    assert_close(by_model["gpt-4-32k-0613"].cost_usd, 0.18);
+    // unknown models are counted without cost
+    assert_eq!(by_model["my-fine-tuned-llama"].completion_tokens, 100);
+    assert_close(by_model["my-fine-tuned-llama"].cost_usd, 0.0);
+
+    let by_tag = cost_ledger.by_tag();
+    assert_eq!(by_tag.len(), 1);
+    assert_eq!(by_tag["ingest"].requests, 2);
+    assert_close(by_tag["ingest"].cost_usd, 0.187);
+}
+
+#[tokio::test]
+async fn should_record_usage_of_streamed_completion() {
+    let event_stream = concat!(
+        "data: {\"id\":\"chatcmpl-1\",\"object\":\"chat.completion.chunk\",\"created\":1700000000,\"model\":\"gpt-3.5-turbo-16k\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"pong\"},\"finish_reason\":\"stop\"}],\"usage\":null}\n\n",
+        "data: {\"id\":\"chatcmpl-1\",\"object\":\"chat.completion.chunk\",\"created\":1700000000,\"model\":\"gpt-3.5-turbo-16k\",\"choices\":[],\"usage\":{\"prompt_tokens\":1000,\"completion_tokens\":1000,\"total_tokens\":2000}}\n\n",
+        "data: [DONE]\n\n",
+    );
+    let mock_server = MockServer::start(vec![MockResponse::event_stream(event_stream, 64)]).await;
+    let cost_ledger = CostLedger::default();
+
+    let simplified_response = client_for(&mock_server, &cost_ledger)
+        .with_tag("chat")
+        .perform_request_stream(&ping())
+        .await
+        .unwrap()
+        .into_response()
+        .await
+        .unwrap();
+
+    assert_eq!(simplified_response.usage.unwrap().total_tokens, 2_000);
+    assert_close(simplified_response.cost_usd.unwrap(), 0.007);
+    assert_eq!(cost_ledger.by_tag()["chat"].requests, 1);
+    assert_eq!(
+        mock_server.requests()[0].json_body()["stream_options"]["include_usage"],
+        true
+    );
+}